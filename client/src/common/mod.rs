@@ -43,8 +43,9 @@ use diem_crypto::{
     key_file::{load_pri_key, save_pri_key},
     PrivateKey, Uniform,
 };
-use diem_types::validator_config::{
-    ConsensusPrivateKey, ConsensusVRFPrivateKey,
+use diem_types::{
+    chain_id::ChainId,
+    validator_config::{ConsensusPrivateKey, ConsensusVRFPrivateKey},
 };
 use keylib::KeyPair;
 use malloc_size_of::{new_malloc_size_ops, MallocSizeOf, MallocSizeOfOps};
@@ -422,6 +423,7 @@ pub fn initialize_common_modules(
                 .clone(),
             vrf_proposal_threshold: conf.raw_conf.vrf_proposal_threshold,
             pos_state_config: conf.pos_state_config(),
+            chain_id: ChainId::new(network.network_id()),
         },
         conf.raw_conf.pos_reference_enable_height,
     ));
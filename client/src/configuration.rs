@@ -12,7 +12,9 @@ use cfx_addr::{cfx_addr_decode, Network};
 use cfx_internal_common::{
     ChainIdParams, ChainIdParamsInner, ChainIdParamsOneChainInner,
 };
-use cfx_parameters::block::DEFAULT_TARGET_BLOCK_GAS_LIMIT;
+use cfx_parameters::{
+    block::DEFAULT_TARGET_BLOCK_GAS_LIMIT, staking::BLOCKS_PER_HOUR,
+};
 use cfx_storage::{
     defaults::DEFAULT_DEBUG_SNAPSHOT_CHECKER_THREADS, storage_dir,
     ConsensusParam, ProvideExtraSnapshotSyncConfig, StorageConfiguration,
@@ -156,8 +158,11 @@ build_config! {
         (cip105_transition_number, (Option<u64>), None)
         (sigma_fix_transition_number, (Option<u64>), None)
         (cip107_transition_number, (Option<u64>), None)
+        (vote_stake_sweep_transition_number, (Option<u64>), None)
+        (skip_unregistered_pos_interest_transition_number, (Option<u64>), None)
         (referee_bound, (usize), REFEREE_DEFAULT_BOUND)
         (params_dao_vote_period, (u64), DAO_PARAMETER_VOTE_PERIOD)
+        (pos_distribute_window_blocks, (u64), BLOCKS_PER_HOUR)
         (timer_chain_beta, (u64), TIMER_CHAIN_DEFAULT_BETA)
         (timer_chain_block_difficulty_ratio, (u64), TIMER_CHAIN_BLOCK_DEFAULT_DIFFICULTY_RATIO)
         // FIXME: this is part of spec.
@@ -311,6 +316,7 @@ build_config! {
         (print_memory_usage_period_s, (Option<u64>), None)
         (target_block_gas_limit, (u64), DEFAULT_TARGET_BLOCK_GAS_LIMIT)
         (executive_trace, (bool), false)
+        (state_cache_byte_budget, (Option<usize>), None)
         (check_status_genesis, (bool), true)
         (packing_gas_limit_block_count, (u64), 10)
         (poll_lifetime_in_seconds, (Option<u32>), None)
@@ -1077,6 +1083,7 @@ impl Configuration {
     pub fn execution_config(&self) -> ConsensusExecutionConfiguration {
         ConsensusExecutionConfiguration {
             executive_trace: self.raw_conf.executive_trace,
+            state_cache_byte_budget: self.raw_conf.state_cache_byte_budget,
         }
     }
 
@@ -1220,6 +1227,14 @@ impl Configuration {
             .raw_conf
             .cip107_transition_number
             .unwrap_or(default_transition_time);
+        params.transition_numbers.vote_stake_sweep = self
+            .raw_conf
+            .vote_stake_sweep_transition_number
+            .unwrap_or(default_transition_time);
+        params.transition_numbers.skip_unregistered_pos_interest = self
+            .raw_conf
+            .skip_unregistered_pos_interest_transition_number
+            .unwrap_or(default_transition_time);
         if self.is_test_or_dev_mode() {
             params.transition_numbers.cip43b =
                 self.raw_conf.cip43_init_end_number.unwrap_or(u64::MAX);
@@ -1278,6 +1293,13 @@ impl Configuration {
             .dao_vote_transition_height
             .unwrap_or(non_genesis_default_transition_time);
         params.params_dao_vote_period = self.raw_conf.params_dao_vote_period;
+        // The window changes the PoS interest, so it is only tunable on test
+        // and dev chains.
+        params.distribute_window_blocks = if self.is_test_or_dev_mode() {
+            self.raw_conf.pos_distribute_window_blocks
+        } else {
+            BLOCKS_PER_HOUR
+        };
 
         let mut base_block_rewards = BTreeMap::new();
         base_block_rewards.insert(0, INITIAL_BASE_MINING_REWARD_IN_UCFX.into());
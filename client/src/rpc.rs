@@ -18,6 +18,7 @@ use jsonrpc_ws_server::{
     ServerBuilder as WsServerBuilder,
 };
 use std::{
+    convert::TryFrom,
     net::{Ipv4Addr, SocketAddr, SocketAddrV4},
     sync::Arc,
 };
@@ -382,11 +383,16 @@ fn setup_rpc_apis(
                 );
             }
             Api::Pos => {
+                let pos_chain_id = u32::try_from(
+                    common.pos_handler.config().chain_id.id(),
+                )
+                .expect("PoS chain id does not fit in u32");
                 let pos = PosHandler::new(
                     common.pos_handler.clone(),
                     rpc.consensus.get_data_manager().clone(),
                     *rpc.sync.network.get_network_type(),
                     rpc.consensus.clone(),
+                    pos_chain_id,
                 )
                 .to_delegate();
                 let pos_interceptor =
@@ -5,7 +5,8 @@
 use crate::rpc::types::{
     call_request::rpc_call_request_network, errors::check_rpc_address_network,
     pos::PoSEpochReward, PoSEconomics, RpcAddress, SponsorInfo,
-    TokenSupplyInfo, VoteParamsInfo, WrapTransaction,
+    SponsorWhitelist, StakingBalanceInfo, TokenSupplyInfo, VoteParamsInfo,
+    WrapTransaction,
 };
 use blockgen::BlockGenerator;
 use cfx_statedb::StateDbExt;
@@ -59,14 +60,15 @@ use crate::{
         types::{
             eth::Transaction as EthTransaction, pos::Block as PosBlock,
             sign_call, Account as RpcAccount, AccountPendingInfo,
-            AccountPendingTransactions, BlameInfo, Block as RpcBlock,
-            BlockHashOrEpochNumber, Bytes, CallRequest, CfxRpcLogFilter,
+            AccountPendingTransactions, AccountProof as RpcAccountProof,
+            BlameInfo, Block as RpcBlock, BlockHashOrEpochNumber, Bytes,
+            CallRequest, CfxRpcLogFilter,
             CheckBalanceAgainstTransactionResponse, ConsensusGraphStates,
             EpochNumber, EstimateGasAndCollateralResponse, Log as RpcLog,
             PackedOrExecuted, Receipt as RpcReceipt,
             RewardInfo as RpcRewardInfo, SendTxRequest, Status as RpcStatus,
-            StorageCollateralInfo, SyncGraphStates,
-            Transaction as RpcTransaction,
+            StorageCollateralInfo, StorageProof as RpcStorageProof,
+            SyncGraphStates, Transaction as RpcTransaction,
         },
         RpcResult,
     },
@@ -85,7 +87,7 @@ use cfxcore::{
     spec::genesis::{
         genesis_contract_address_four_year, genesis_contract_address_two_year,
     },
-    state::State,
+    state::{State, WorldStatisticsDelta},
 };
 use diem_types::account_address::AccountAddress;
 use serde::Serialize;
@@ -279,6 +281,26 @@ impl RpcImpl {
         }
     }
 
+    fn sponsor_whitelist(
+        &self, address: RpcAddress, num: Option<EpochNumber>,
+    ) -> RpcResult<SponsorWhitelist> {
+        self.check_address_network(address.network)?;
+        let epoch_num = num.unwrap_or(EpochNumber::LatestState).into();
+
+        info!(
+            "RPC Request: cfx_getSponsorWhitelist address={:?} epoch_num={:?}",
+            address, epoch_num
+        );
+
+        let state_db = self
+            .consensus
+            .get_state_db_by_epoch_number(epoch_num, "num")?;
+        let state = State::new(state_db)?;
+        let users = state.commission_privilege_list(&address.hex_address)?;
+
+        Ok(SponsorWhitelist::try_from(users, address.network)?)
+    }
+
     fn staking_balance(
         &self, address: RpcAddress, num: Option<EpochNumber>,
     ) -> RpcResult<U256> {
@@ -299,6 +321,81 @@ impl RpcImpl {
         Ok(acc.map_or(U256::zero(), |acc| acc.staking_balance).into())
     }
 
+    fn accumulated_interest(
+        &self, address: RpcAddress, amount: Option<U256>,
+        num: Option<EpochNumber>,
+    ) -> RpcResult<U256>
+    {
+        self.check_address_network(address.network)?;
+        let epoch_num: primitives::EpochNumber =
+            num.unwrap_or(EpochNumber::LatestState).into();
+
+        info!(
+            "RPC Request: cfx_getAccumulatedInterest address={:?} amount={:?} epoch_num={:?}",
+            address, amount, epoch_num
+        );
+
+        // The interest is computed with the spec of the next block.
+        let pivot_hash =
+            self.consensus.get_hash_from_epoch_number(epoch_num.clone())?;
+        let block_number = self
+            .consensus
+            .get_block_number(&pivot_hash)?
+            .ok_or("block_number is missing for pivot_hash")?
+            + 1;
+        let cip_97 = self.tx_pool.machine().spec(block_number).cip97;
+
+        let state_db = self
+            .consensus
+            .get_state_db_by_epoch_number(epoch_num, "num")?;
+        let state = State::new(state_db)?;
+        let address = address.hex_address;
+        let amount = match amount {
+            Some(amount) => amount,
+            None => state.staking_balance(&address)?,
+        };
+
+        Ok(state.preview_withdraw_interest(&address, &amount, cip_97)?)
+    }
+
+    fn staking_balance_info(
+        &self, address: RpcAddress, num: Option<EpochNumber>,
+    ) -> RpcResult<StakingBalanceInfo> {
+        self.check_address_network(address.network)?;
+        let epoch_num: primitives::EpochNumber =
+            num.unwrap_or(EpochNumber::LatestState).into();
+
+        info!(
+            "RPC Request: cfx_getStakingBalanceInfo address={:?} epoch_num={:?}",
+            address, epoch_num
+        );
+
+        // The balance is withdrawn in the next block at the earliest.
+        let pivot_hash =
+            self.consensus.get_hash_from_epoch_number(epoch_num.clone())?;
+        let block_number = self
+            .consensus
+            .get_block_number(&pivot_hash)?
+            .ok_or("block_number is missing for pivot_hash")?
+            + 1;
+
+        let state_db = self
+            .consensus
+            .get_state_db_by_epoch_number(epoch_num, "num")?;
+        let state = State::new(state_db)?;
+        let address = address.hex_address;
+        let vote_locked = state
+            .locked_staking_balance_at_block_number(&address, block_number)?;
+        let available =
+            state.available_staking_balance(&address, block_number)?;
+        Ok(StakingBalanceInfo {
+            staking_balance: state.staking_balance(&address)?,
+            vote_locked,
+            pos_locked: state.pos_locked_staking(&address)?,
+            available,
+        })
+    }
+
     fn deposit_list(
         &self, address: RpcAddress, num: Option<EpochNumber>,
     ) -> RpcResult<Vec<DepositInfo>> {
@@ -313,13 +410,9 @@ impl RpcImpl {
         let state_db = self
             .consensus
             .get_state_db_by_epoch_number(epoch_num, "num")?;
+        let state = State::new(state_db)?;
 
-        match state_db
-            .get_deposit_list(&address.hex_address.with_native_space())?
-        {
-            None => Ok(vec![]),
-            Some(deposit_list) => Ok(deposit_list.0),
-        }
+        Ok(state.deposit_list(&address.hex_address)?)
     }
 
     fn vote_list(
@@ -336,13 +429,9 @@ impl RpcImpl {
         let state_db = self
             .consensus
             .get_state_db_by_epoch_number(epoch_num, "num")?;
+        let state = State::new(state_db)?;
 
-        match state_db
-            .get_vote_list(&address.hex_address.with_native_space())?
-        {
-            None => Ok(vec![]),
-            Some(vote_list) => Ok(vote_list.0),
-        }
+        Ok(state.vote_stake_list(&address.hex_address)?)
     }
 
     fn collateral_for_storage(
@@ -629,6 +718,122 @@ impl RpcImpl {
         Ok(Some(root))
     }
 
+    fn get_proof(
+        &self, address: RpcAddress, keys: Vec<H256>,
+        epoch_num: Option<EpochNumber>,
+    ) -> RpcResult<RpcAccountProof>
+    {
+        self.check_address_network(address.network)?;
+        let epoch_num = epoch_num.unwrap_or(EpochNumber::LatestState).into();
+
+        info!(
+            "RPC Request: cfx_getProof address={:?} keys={:?} epoch={:?}",
+            address, keys, epoch_num
+        );
+
+        let state_db = self
+            .consensus
+            .get_state_db_by_epoch_number(epoch_num, "epoch_num")?;
+        let root = state_db.get_state_root()?;
+        let state = State::new(state_db)?;
+        let address_with_space = address.hex_address.with_native_space();
+
+        // A freshly loaded state has no uncommitted changes, so the proofs
+        // should always be available.
+        let account_proof = match state.prove_account(&address_with_space)? {
+            Some(proof) => proof,
+            None => bail!(internal_error("Account has uncommitted changes")),
+        };
+        let mut storage_proof = Vec::with_capacity(keys.len());
+        for key in keys {
+            let proof = match state
+                .prove_storage(&address_with_space, key.as_bytes())?
+            {
+                Some(proof) => proof,
+                None => {
+                    bail!(internal_error("Storage has uncommitted changes"))
+                }
+            };
+            storage_proof.push(RpcStorageProof {
+                key,
+                value: proof
+                    .storage_value
+                    .map_or(U256::zero(), |entry| entry.value),
+                raw_value: proof.value.map(Bytes::new),
+                proof: Bytes::new(rlp::encode(&proof.proof)),
+            });
+        }
+
+        let account = account_proof.account.unwrap_or_else(|| {
+            Account::new_empty_with_balance(
+                &address_with_space,
+                &U256::zero(), /* balance */
+                &U256::zero(), /* nonce */
+            )
+        });
+        Ok(RpcAccountProof {
+            address,
+            balance: account.balance,
+            nonce: account.nonce,
+            code_hash: account.code_hash,
+            state_root: root.state_root,
+            intermediate_padding: root
+                .aux_info
+                .maybe_intermediate_mpt_key_padding,
+            account_value: account_proof.value.map(Bytes::new),
+            account_proof: Bytes::new(rlp::encode(&account_proof.proof)),
+            storage_proof,
+        })
+    }
+
+    fn debug_storage_root(
+        &self, address: RpcAddress, epoch_num: Option<EpochNumber>,
+    ) -> RpcResult<Option<StorageRoot>> {
+        self.check_address_network(address.network)?;
+        let epoch_num = epoch_num.unwrap_or(EpochNumber::LatestState).into();
+
+        info!(
+            "RPC Request: debug_getStorageRoot address={:?} epoch={:?}",
+            address, epoch_num
+        );
+
+        let state_db = self
+            .consensus
+            .get_state_db_by_epoch_number(epoch_num, "epoch_num")?;
+        let state = State::new(state_db)?;
+
+        Ok(state.storage_root(&address.hex_address.with_native_space())?)
+    }
+
+    fn debug_world_statistics_delta(
+        &self, epoch_num: Option<EpochNumber>,
+    ) -> RpcResult<Option<WorldStatisticsDelta>> {
+        let epoch_num: primitives::EpochNumber =
+            epoch_num.unwrap_or(EpochNumber::LatestState).into();
+
+        info!(
+            "RPC Request: debug_getWorldStatisticsDelta epoch={:?}",
+            epoch_num
+        );
+
+        let height = self
+            .consensus_graph()
+            .get_height_from_epoch_number(epoch_num.clone())?;
+        if height == 0 {
+            return Ok(None);
+        }
+        let state_db = self
+            .consensus
+            .get_state_db_by_epoch_number(epoch_num, "epoch_num")?;
+        let parent_state_db = self.consensus.get_state_db_by_epoch_number(
+            primitives::EpochNumber::Number(height - 1),
+            "epoch_num",
+        )?;
+        let state = State::new(state_db)?;
+        let parent_state = State::new(parent_state_db)?;
+        Ok(Some(state.statistics_delta_since(&parent_state)))
+    }
+
     fn send_usable_genesis_accounts(
         &self, account_start_index: usize,
     ) -> RpcResult<Bytes> {
@@ -1519,9 +1724,11 @@ impl RpcImpl {
             .unwrap_or(U256::zero());
         let total_circulating =
             total_issued - two_year_locked - four_year_locked;
+        let total_pos_circulating = state.total_circulating_tokens()?;
         let total_espace_tokens = state.total_espace_tokens();
         Ok(TokenSupplyInfo {
             total_circulating,
+            total_pos_circulating,
             total_issued,
             total_staking,
             total_collateral,
@@ -2108,10 +2315,14 @@ impl Cfx for CfxHandler {
                 -> BoxFuture<Option<RpcAddress>>;
             fn sponsor_info(&self, address: RpcAddress, num: Option<EpochNumber>)
                 -> BoxFuture<SponsorInfo>;
+            fn sponsor_whitelist(&self, address: RpcAddress, num: Option<EpochNumber>)
+                -> BoxFuture<SponsorWhitelist>;
             fn balance(&self, address: RpcAddress, block_hash_or_epoch_number: Option<BlockHashOrEpochNumber>) -> BoxFuture<U256>;
             fn staking_balance(&self, address: RpcAddress, num: Option<EpochNumber>)
                 -> BoxFuture<U256>;
             fn deposit_list(&self, address: RpcAddress, num: Option<EpochNumber>) -> BoxFuture<Vec<DepositInfo>>;
+            fn accumulated_interest(&self, address: RpcAddress, amount: Option<U256>, num: Option<EpochNumber>) -> BoxFuture<U256>;
+            fn staking_balance_info(&self, address: RpcAddress, num: Option<EpochNumber>) -> BoxFuture<StakingBalanceInfo>;
             fn vote_list(&self, address: RpcAddress, num: Option<EpochNumber>) -> BoxFuture<Vec<VoteStakeInfo>>;
             fn collateral_for_storage(&self, address: RpcAddress, num: Option<EpochNumber>)
                 -> BoxFuture<U256>;
@@ -2131,6 +2342,7 @@ impl Cfx for CfxHandler {
             fn transaction_by_hash(&self, hash: H256) -> BoxFuture<Option<RpcTransaction>>;
             fn transaction_receipt(&self, tx_hash: H256) -> BoxFuture<Option<RpcReceipt>>;
             fn storage_root(&self, address: RpcAddress, epoch_num: Option<EpochNumber>) -> BoxFuture<Option<StorageRoot>>;
+            fn get_proof(&self, address: RpcAddress, keys: Vec<H256>, epoch_num: Option<EpochNumber>) -> BoxFuture<RpcAccountProof>;
             fn get_supply_info(&self, epoch_num: Option<EpochNumber>) -> JsonRpcResult<TokenSupplyInfo>;
             fn get_collateral_info(&self, epoch_num: Option<EpochNumber>) -> JsonRpcResult<StorageCollateralInfo>;
             fn get_vote_params(&self, epoch_num: Option<EpochNumber>) -> JsonRpcResult<VoteParamsInfo>;
@@ -2257,6 +2469,8 @@ impl LocalRpc for LocalRpcImpl {
             fn sign_transaction(&self, tx: SendTxRequest, password: Option<String>) -> JsonRpcResult<String>;
             fn transactions_by_epoch(&self, epoch_number: U64) -> JsonRpcResult<Vec<WrapTransaction>>;
             fn transactions_by_block(&self, block_hash: H256) -> JsonRpcResult<Vec<WrapTransaction>>;
+            fn debug_storage_root(&self, address: RpcAddress, epoch_num: Option<EpochNumber>) -> JsonRpcResult<Option<StorageRoot>>;
+            fn debug_world_statistics_delta(&self, epoch_num: Option<EpochNumber>) -> JsonRpcResult<Option<WorldStatisticsDelta>>;
         }
     }
 }
@@ -11,6 +11,7 @@ use cfxcore::{
         self, query_service::TxInfo, Error as LightError, ErrorKind,
     },
     rpc_errors::{account_result_to_rpc_result, invalid_params_check},
+    state::WorldStatisticsDelta,
     verification::EpochReceiptProof,
     ConsensusGraph, LightQueryService, PeerInfo, SharedConsensusGraph,
 };
@@ -39,14 +40,16 @@ use crate::{
             errors::check_rpc_address_network,
             pos::{Block as PosBlock, PoSEpochReward},
             Account as RpcAccount, AccountPendingInfo,
-            AccountPendingTransactions, BlameInfo, Block as RpcBlock,
-            BlockHashOrEpochNumber, Bytes, CallRequest, CfxRpcLogFilter,
+            AccountPendingTransactions, AccountProof as RpcAccountProof,
+            BlameInfo, Block as RpcBlock, BlockHashOrEpochNumber, Bytes,
+            CallRequest, CfxRpcLogFilter,
             CheckBalanceAgainstTransactionResponse, ConsensusGraphStates,
             EpochNumber, EstimateGasAndCollateralResponse, Log as RpcLog,
             PoSEconomics, Receipt as RpcReceipt, RewardInfo as RpcRewardInfo,
-            RpcAddress, SendTxRequest, SponsorInfo, Status as RpcStatus,
-            StorageCollateralInfo, SyncGraphStates, TokenSupplyInfo,
-            Transaction as RpcTransaction, VoteParamsInfo, WrapTransaction,
+            RpcAddress, SendTxRequest, SponsorInfo, SponsorWhitelist,
+            StakingBalanceInfo, Status as RpcStatus, StorageCollateralInfo,
+            SyncGraphStates, TokenSupplyInfo, Transaction as RpcTransaction,
+            VoteParamsInfo, WrapTransaction,
         },
         RpcBoxFuture, RpcResult,
     },
@@ -1150,6 +1153,10 @@ impl Cfx for CfxHandler {
         fn get_collateral_info(&self, epoch_num: Option<EpochNumber>) -> JsonRpcResult<StorageCollateralInfo>;
         fn get_vote_params(&self, epoch_num: Option<EpochNumber>) -> JsonRpcResult<VoteParamsInfo>;
         fn get_pos_reward_by_epoch(&self, epoch: EpochNumber) -> JsonRpcResult<Option<PoSEpochReward>>;
+        fn get_proof(&self, address: RpcAddress, keys: Vec<H256>, epoch_num: Option<EpochNumber>) -> BoxFuture<RpcAccountProof>;
+        fn sponsor_whitelist(&self, address: RpcAddress, num: Option<EpochNumber>) -> BoxFuture<SponsorWhitelist>;
+        fn accumulated_interest(&self, address: RpcAddress, amount: Option<U256>, num: Option<EpochNumber>) -> BoxFuture<U256>;
+        fn staking_balance_info(&self, address: RpcAddress, num: Option<EpochNumber>) -> BoxFuture<StakingBalanceInfo>;
     }
 }
 
@@ -1260,5 +1267,7 @@ impl LocalRpc for DebugRpcImpl {
         fn sync_graph_state(&self) -> JsonRpcResult<SyncGraphStates>;
         fn transactions_by_epoch(&self, epoch_number: U64) -> JsonRpcResult<Vec<WrapTransaction>>;
         fn transactions_by_block(&self, block_hash: H256) -> JsonRpcResult<Vec<WrapTransaction>>;
+        fn debug_storage_root(&self, address: RpcAddress, epoch_num: Option<EpochNumber>) -> JsonRpcResult<Option<StorageRoot>>;
+        fn debug_world_statistics_delta(&self, epoch_num: Option<EpochNumber>) -> JsonRpcResult<Option<WorldStatisticsDelta>>;
     }
 }
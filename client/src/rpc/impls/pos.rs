@@ -17,7 +17,8 @@ use crate::{
                 tx_type, Account, Block, BlockNumber, CommitteeState, Decision,
                 EpochState as RpcEpochState,
                 LedgerInfoWithSignatures as RpcLedgerInfoWithSignatures,
-                NodeLockStatus, PoSEpochReward, RpcCommittee, RpcTermData,
+                NodeLockStatus, PoSEpochReward, PosReward, RpcCommittee,
+                RpcTermData,
                 RpcTransactionStatus, RpcTransactionType, Signature, Status,
                 Transaction, VotePowerState,
             },
@@ -28,7 +29,7 @@ use crate::{
 };
 use cfx_addr::Network;
 use cfx_parameters::internal_contract_addresses::POS_REGISTER_CONTRACT_ADDRESS;
-use cfx_types::{hexstr_to_h256, Address, H256, U64};
+use cfx_types::{hexstr_to_h256, Address, H256, U256, U64};
 use cfxcore::{
     consensus::pos_handler::PosVerifier,
     executive::{EstimateRequest, ExecutionOutcome},
@@ -74,17 +75,25 @@ impl RpcInterceptor for PoSInterceptor {
     }
 }
 
+/// The maximum number of entries returned by `pos_getRewardHistory`.
+const MAX_POS_REWARD_HISTORY: u64 = 100;
+
+/// The maximum number of PoS epochs visited by one `pos_getRewardHistory`
+/// call.
+const POS_REWARD_HISTORY_SCAN_EPOCHS: u64 = 1000;
+
 pub struct PosHandler {
     pos_handler: Arc<PosVerifier>,
     pow_data_manager: Arc<BlockDataManager>,
     network_type: Network,
     consensus: SharedConsensusGraph,
+    chain_id: u32,
 }
 
 impl PosHandler {
     pub fn new(
         pos_handler: Arc<PosVerifier>, pow_data_manager: Arc<BlockDataManager>,
-        network_type: Network, consensus: SharedConsensusGraph,
+        network_type: Network, consensus: SharedConsensusGraph, chain_id: u32,
     ) -> Self
     {
         PosHandler {
@@ -92,6 +101,7 @@ impl PosHandler {
             pow_data_manager,
             network_type,
             consensus,
+            chain_id,
         }
     }
 
@@ -115,17 +125,23 @@ impl PosHandler {
         let decision = state.pivot_decision();
         let epoch_state = state.epoch_state();
         let block_number = state.current_view();
-        let latest_voted = self.latest_voted().map(|b| U64::from(b.height));
-        let latest_tx_number = self
-            .block_by_number(BlockNumber::Num(U64::from(block_number)))
-            .map(|b| b.last_tx_number.into())
-            .unwrap_or_default();
+        let latest_voted_block = self.latest_voted();
+        let latest_committed_block =
+            self.block_by_number(BlockNumber::Num(U64::from(block_number)));
         Status {
+            chain_id: self.chain_id,
             epoch: U64::from(epoch_state.epoch),
             latest_committed: U64::from(block_number),
+            latest_committed_block_hash: latest_committed_block
+                .as_ref()
+                .map(|b| b.hash)
+                .unwrap_or_default(),
             pivot_decision: Decision::from(decision),
-            latest_voted,
-            latest_tx_number,
+            latest_voted: latest_voted_block.as_ref().map(|b| b.height),
+            latest_voted_round: latest_voted_block.as_ref().map(|b| b.round),
+            latest_tx_number: latest_committed_block
+                .map(|b| b.last_tx_number)
+                .unwrap_or_default(),
         }
     }
 
@@ -145,6 +161,14 @@ impl PosHandler {
                 return Ok(Account {
                     address,
                     block_number: U64::from(state.current_view()),
+                    current_term: U64::from(state.term_list().current_term()),
+                    voting_power: U64::from(
+                        state
+                            .epoch_state()
+                            .verifier()
+                            .get_voting_power(&addr)
+                            .unwrap_or(0),
+                    ),
                     status: NodeLockStatus {
                         in_queue: map_votes(&lock_status.in_queue),
                         locked: U64::from(lock_status.locked),
@@ -165,6 +189,7 @@ impl PosHandler {
         let mut default_acct: Account = Account::default();
         default_acct.address = address;
         default_acct.block_number = U64::from(state.current_view());
+        default_acct.current_term = U64::from(state.term_list().current_term());
         return Ok(default_acct);
     }
 
@@ -296,7 +321,12 @@ impl PosHandler {
                             .pos_ledger_db()
                             .get_pos_state(&block_hash)
                     })
-                    .map_err(|_| format!("PoS state of {} not found", v))?;
+                    .map_err(|_| {
+                        format!(
+                            "PoS state of {} not found, it may have been pruned",
+                            v
+                        )
+                    })?;
                 Arc::new(state)
             }
         };
@@ -319,6 +349,7 @@ impl PosHandler {
             .collect();
 
         Ok(CommitteeState {
+            current_term: U64::from(pos_state.term_list().current_term()),
             current_committee,
             elections,
         })
@@ -640,6 +671,48 @@ impl PosHandler {
         }
     }
 
+    fn rewards_impl(
+        &self, identifier: H256, count: u64, from_epoch: Option<u64>,
+    ) -> Vec<PosReward> {
+        let count = count.min(MAX_POS_REWARD_HISTORY) as usize;
+        let mut rewards = Vec::with_capacity(count);
+        let last_epoch = from_epoch
+            .unwrap_or(u64::MAX)
+            .min(self.current_epoch());
+        let first_epoch = last_epoch
+            .saturating_sub(POS_REWARD_HISTORY_SCAN_EPOCHS - 1)
+            .max(1);
+        for epoch in (first_epoch..=last_epoch).rev() {
+            if rewards.len() >= count {
+                break;
+            }
+            let reward_info =
+                match self.pow_data_manager.pos_reward_by_pos_epoch(epoch) {
+                    Some(reward_info) => reward_info,
+                    None => continue,
+                };
+            let reward = reward_info
+                .account_rewards
+                .iter()
+                .filter(|r| r.pos_identifier == identifier)
+                .fold(U256::zero(), |sum, r| sum + r.reward);
+            if reward.is_zero() {
+                continue;
+            }
+            if let Some(block_number) = self
+                .pow_data_manager
+                .block_height_by_hash(&reward_info.execution_epoch_hash)
+            {
+                rewards.push(PosReward {
+                    epoch: U64::from(epoch),
+                    block_number: U64::from(block_number),
+                    reward,
+                });
+            }
+        }
+        rewards
+    }
+
     fn ledger_info_by_block_number(
         &self, block_number: BlockNumber,
     ) -> Option<LedgerInfoWithSignatures> {
@@ -782,4 +855,14 @@ impl Pos for PosHandler {
             .unwrap_or(None);
         Ok(reward)
     }
+
+    fn pos_rewards(
+        &self, identifier: H256, count: U64, from_epoch: Option<U64>,
+    ) -> JsonRpcResult<Vec<PosReward>> {
+        Ok(self.rewards_impl(
+            identifier,
+            count.as_u64(),
+            from_epoch.map(|e| e.as_u64()),
+        ))
+    }
 }
@@ -4,13 +4,13 @@
 
 use crate::rpc::types::{
     pos::PoSEpochReward, Account as RpcAccount, AccountPendingInfo,
-    AccountPendingTransactions, Block, BlockHashOrEpochNumber, Bytes,
-    CallRequest, CfxFilterChanges, CfxRpcLogFilter,
-    CheckBalanceAgainstTransactionResponse, EpochNumber,
+    AccountPendingTransactions, AccountProof as RpcAccountProof, Block,
+    BlockHashOrEpochNumber, Bytes, CallRequest, CfxFilterChanges,
+    CfxRpcLogFilter, CheckBalanceAgainstTransactionResponse, EpochNumber,
     EstimateGasAndCollateralResponse, Log as RpcLog, PoSEconomics,
     Receipt as RpcReceipt, RewardInfo as RpcRewardInfo, RpcAddress,
-    SponsorInfo, Status as RpcStatus, StorageCollateralInfo, TokenSupplyInfo,
-    Transaction, VoteParamsInfo,
+    SponsorInfo, SponsorWhitelist, StakingBalanceInfo, Status as RpcStatus,
+    StorageCollateralInfo, TokenSupplyInfo, Transaction, VoteParamsInfo,
 };
 use cfx_types::{H128, H256, U256, U64};
 use jsonrpc_core::{BoxFuture, Result as JsonRpcResult};
@@ -65,6 +65,12 @@ pub trait Cfx {
         &self, addr: RpcAddress, epoch_number: Option<EpochNumber>,
     ) -> BoxFuture<SponsorInfo>;
 
+    /// Returns the users whitelisted by the given contract for sponsorship.
+    #[rpc(name = "cfx_getSponsorWhitelist")]
+    fn sponsor_whitelist(
+        &self, addr: RpcAddress, epoch_number: Option<EpochNumber>,
+    ) -> BoxFuture<SponsorWhitelist>;
+
     /// Returns balance of the given account.
     #[rpc(name = "cfx_getStakingBalance")]
     fn staking_balance(
@@ -77,6 +83,23 @@ pub trait Cfx {
         &self, addr: RpcAddress, epoch_number: Option<EpochNumber>,
     ) -> BoxFuture<Vec<DepositInfo>>;
 
+    /// Returns the interest that withdrawing `amount` of the staking balance
+    /// of the given account would pay. The whole staking balance is withdrawn
+    /// by default.
+    #[rpc(name = "cfx_getAccumulatedInterest")]
+    fn accumulated_interest(
+        &self, addr: RpcAddress, amount: Option<U256>,
+        epoch_number: Option<EpochNumber>,
+    ) -> BoxFuture<U256>;
+
+    /// Returns the staking balance of the given account together with the
+    /// amounts locked by votes and for PoS, and the amount which can be
+    /// withdrawn in the next block.
+    #[rpc(name = "cfx_getStakingBalanceInfo")]
+    fn staking_balance_info(
+        &self, addr: RpcAddress, epoch_number: Option<EpochNumber>,
+    ) -> BoxFuture<StakingBalanceInfo>;
+
     /// Returns vote list of the given account.
     #[rpc(name = "cfx_getVoteList")]
     fn vote_list(
@@ -108,6 +131,14 @@ pub trait Cfx {
         &self, address: RpcAddress, epoch_num: Option<EpochNumber>,
     ) -> BoxFuture<Option<StorageRoot>>;
 
+    /// Returns the account and the storage entries under `keys` with their
+    /// Merkle proofs against the state root of the given epoch.
+    #[rpc(name = "cfx_getProof")]
+    fn get_proof(
+        &self, address: RpcAddress, keys: Vec<H256>,
+        epoch_num: Option<EpochNumber>,
+    ) -> BoxFuture<RpcAccountProof>;
+
     /// Returns block with given hash.
     #[rpc(name = "cfx_getBlockByHash")]
     fn block_by_hash(
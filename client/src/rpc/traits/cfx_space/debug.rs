@@ -4,17 +4,18 @@
 
 use crate::rpc::types::{
     BlockHashOrEpochNumber, Bytes as RpcBytes, ConsensusGraphStates,
-    Receipt as RpcReceipt, RpcAddress, SendTxRequest, SyncGraphStates,
-    Transaction as RpcTransaction, WrapTransaction,
+    EpochNumber, Receipt as RpcReceipt, RpcAddress, SendTxRequest,
+    SyncGraphStates, Transaction as RpcTransaction, WrapTransaction,
 };
 use cfx_types::{H256, H520, U128, U64};
-use cfxcore::verification::EpochReceiptProof;
+use cfxcore::{state::WorldStatisticsDelta, verification::EpochReceiptProof};
 use jsonrpc_core::{BoxFuture, Result as JsonRpcResult};
 use jsonrpc_derive::rpc;
 use network::{
     node_table::{Node, NodeId},
     throttling, SessionDetails, UpdateNodeOperation,
 };
+use primitives::StorageRoot;
 use std::collections::BTreeMap;
 
 #[rpc(server)]
@@ -126,4 +127,18 @@ pub trait LocalRpc {
     fn transactions_by_block(
         &self, block_hash: H256,
     ) -> JsonRpcResult<Vec<WrapTransaction>>;
+
+    /// Returns the storage root of an account, or `None` if the account does
+    /// not exist.
+    #[rpc(name = "debug_getStorageRoot")]
+    fn debug_storage_root(
+        &self, address: RpcAddress, epoch_num: Option<EpochNumber>,
+    ) -> JsonRpcResult<Option<StorageRoot>>;
+
+    /// Returns the changes of the world statistics made by an epoch, or
+    /// `None` for the genesis epoch.
+    #[rpc(name = "debug_getWorldStatisticsDelta")]
+    fn debug_world_statistics_delta(
+        &self, epoch_num: Option<EpochNumber>,
+    ) -> JsonRpcResult<Option<WorldStatisticsDelta>>;
 }
@@ -5,7 +5,8 @@
 use crate::rpc::types::{
     pos::{
         Account, Block, BlockNumber, CommitteeState, EpochState,
-        LedgerInfoWithSignatures, PoSEpochReward, Status, Transaction,
+        LedgerInfoWithSignatures, PoSEpochReward, PosReward, Status,
+        Transaction,
     },
     RpcAddress,
 };
@@ -82,4 +83,13 @@ pub trait Pos {
     fn pos_get_rewards_by_epoch(
         &self, epoch: U64,
     ) -> JsonRpcResult<Option<PoSEpochReward>>;
+
+    /// Returns the rewards of the PoS node `identifier` in its latest
+    /// rewarded epochs, newest first. The scan starts at `from_epoch`
+    /// (default: the current PoS epoch) and visits at most 1000 epochs.
+    /// At most 100 entries are returned.
+    #[rpc(name = "pos_getRewardHistory")]
+    fn pos_rewards(
+        &self, identifier: H256, count: U64, from_epoch: Option<U64>,
+    ) -> JsonRpcResult<Vec<PosReward>>;
 }
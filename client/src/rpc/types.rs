@@ -3,6 +3,7 @@
 // See http://www.gnu.org/licenses/
 
 mod account;
+mod account_proof;
 pub mod address;
 mod blame_info;
 mod block;
@@ -22,6 +23,7 @@ pub mod pubsub;
 mod receipt;
 mod reward_info;
 mod sponsor_info;
+mod staking_balance_info;
 mod status;
 mod storage_collateral_info;
 mod sync_graph_states;
@@ -34,6 +36,7 @@ mod vote_params_info;
 
 pub use self::{
     account::Account,
+    account_proof::{AccountProof, StorageProof},
     address::RpcAddress,
     blame_info::BlameInfo,
     block::{Block, BlockTransactions, Header},
@@ -51,7 +54,8 @@ pub use self::{
     provenance::Origin,
     receipt::Receipt,
     reward_info::RewardInfo,
-    sponsor_info::SponsorInfo,
+    sponsor_info::{SponsorInfo, SponsorWhitelist},
+    staking_balance_info::StakingBalanceInfo,
     status::Status,
     storage_collateral_info::StorageCollateralInfo,
     sync_graph_states::SyncGraphStates,
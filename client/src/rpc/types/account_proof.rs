@@ -0,0 +1,36 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use super::{Bytes, RpcAddress};
+use cfx_types::{H256, U256};
+use primitives::{DeltaMptKeyPadding, StateRoot};
+
+/// The response of `cfx_getProof`, modeled after `eth_getProof`. Each proof
+/// is an rlp encoded `StateProof` of the raw trie value against `state_root`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountProof {
+    pub address: RpcAddress,
+    pub balance: U256,
+    pub nonce: U256,
+    pub code_hash: H256,
+    pub state_root: StateRoot,
+    /// The key padding of the intermediate delta trie, required to verify
+    /// the proofs which go through it.
+    pub intermediate_padding: Option<DeltaMptKeyPadding>,
+    /// The rlp encoded account, `None` if the account does not exist.
+    pub account_value: Option<Bytes>,
+    pub account_proof: Bytes,
+    pub storage_proof: Vec<StorageProof>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageProof {
+    pub key: H256,
+    pub value: U256,
+    /// The rlp encoded storage entry, `None` if the entry does not exist.
+    pub raw_value: Option<Bytes>,
+    pub proof: Bytes,
+}
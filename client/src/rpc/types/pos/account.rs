@@ -14,5 +14,9 @@ pub struct Account {
     ///
     pub block_number: U64,
     ///
+    pub current_term: U64,
+    ///
+    pub voting_power: U64,
+    ///
     pub status: NodeLockStatus,
 }
@@ -4,9 +4,9 @@
 
 use super::Decision;
 use cfx_types::{H256, U64};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Block {
     ///
@@ -31,7 +31,7 @@ pub struct Block {
     pub signatures: Vec<Signature>,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Signature {
     ///
@@ -41,3 +41,58 @@ pub struct Signature {
     ///
     pub votes: U64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Block, Decision, Signature};
+    use cfx_types::{H256, U64};
+    use serde_json;
+
+    #[test]
+    fn test_block_serde_round_trip() {
+        let block = Block {
+            hash: H256::from_low_u64_be(1),
+            height: U64::from(10),
+            epoch: U64::from(2),
+            round: U64::from(3),
+            last_tx_number: U64::from(20),
+            miner: Some(H256::from_low_u64_be(2)),
+            parent_hash: H256::from_low_u64_be(3),
+            timestamp: U64::from(1000),
+            pivot_decision: Some(Decision {
+                block_hash: H256::from_low_u64_be(4),
+                height: U64::from(100),
+            }),
+            signatures: vec![Signature {
+                account: H256::from_low_u64_be(5),
+                votes: U64::from(7),
+            }],
+        };
+        let serialized = serde_json::to_string(&block).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"hash":"0x0000000000000000000000000000000000000000000000000000000000000001","height":"0xa","epoch":"0x2","round":"0x3","lastTxNumber":"0x14","miner":"0x0000000000000000000000000000000000000000000000000000000000000002","parentHash":"0x0000000000000000000000000000000000000000000000000000000000000003","timestamp":"0x3e8","pivotDecision":{"blockHash":"0x0000000000000000000000000000000000000000000000000000000000000004","height":"0x64"},"signatures":[{"account":"0x0000000000000000000000000000000000000000000000000000000000000005","votes":"0x7"}]}"#
+        );
+        let deserialized: Block = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, block);
+    }
+
+    #[test]
+    fn test_block_without_miner_and_decision_serde_round_trip() {
+        let block = Block {
+            hash: H256::default(),
+            height: U64::default(),
+            epoch: U64::default(),
+            round: U64::default(),
+            last_tx_number: U64::default(),
+            miner: None,
+            parent_hash: H256::default(),
+            timestamp: U64::default(),
+            pivot_decision: None,
+            signatures: vec![],
+        };
+        let serialized = serde_json::to_string(&block).unwrap();
+        let deserialized: Block = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, block);
+    }
+}
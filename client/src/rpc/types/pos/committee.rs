@@ -7,6 +7,7 @@ use diem_types::{
 #[derive(Debug, Serialize, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct CommitteeState {
+    pub current_term: U64,
     pub current_committee: RpcCommittee,
     pub elections: Vec<RpcTermData>,
 }
@@ -4,9 +4,9 @@
 
 use cfx_types::{H256, U64};
 use diem_types::block_info::PivotBlockDecision;
-use serde_derive::Serialize;
+use serde_derive::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct Decision {
     pub block_hash: H256,
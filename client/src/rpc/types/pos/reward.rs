@@ -4,7 +4,7 @@
 
 use super::super::RpcAddress;
 use cfx_addr::Network;
-use cfx_types::{H256, U256};
+use cfx_types::{H256, U256, U64};
 use cfxcore::block_data_manager::block_data_types::PosRewardInfo;
 use std::collections::HashMap;
 
@@ -26,6 +26,20 @@ pub struct PoSEpochReward {
     pub pow_epoch_hash: H256,
     //
     pub account_rewards: Vec<Reward>,
+    /// The total PoS interest distributed in this epoch.
+    pub total_reward: U256,
+}
+
+/// The reward of a PoS node in one PoS epoch.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PosReward {
+    /// The PoS epoch the reward is for.
+    pub epoch: U64,
+    /// The height of the PoW epoch where the reward is distributed.
+    pub block_number: U64,
+    /// The reward in drip.
+    pub reward: U256,
 }
 
 impl PoSEpochReward {
@@ -35,7 +49,9 @@ impl PoSEpochReward {
         let default_value = U256::from(0);
         let mut account_reward_map = HashMap::new();
         let mut account_address_map = HashMap::new();
+        let mut total_reward = U256::zero();
         for r in reward.account_rewards.iter() {
+            total_reward += r.reward;
             let key = r.pos_identifier;
             let r1 = account_reward_map.get(&key).unwrap_or(&default_value);
             let merged_reward = r.reward + r1;
@@ -57,6 +73,7 @@ impl PoSEpochReward {
         Ok(PoSEpochReward {
             pow_epoch_hash: reward.execution_epoch_hash,
             account_rewards,
+            total_reward,
         })
     }
 }
@@ -9,28 +9,37 @@ use serde_derive::Serialize;
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Status {
+    ///
+    pub chain_id: u32,
     ///
     pub latest_committed: U64,
     ///
+    pub latest_committed_block_hash: H256,
+    ///
     pub epoch: U64,
     ///
     pub pivot_decision: Decision,
     ///
     pub latest_voted: Option<U64>,
     ///
+    pub latest_voted_round: Option<U64>,
+    ///
     pub latest_tx_number: U64,
 }
 
 impl Default for Status {
     fn default() -> Status {
         Status {
+            chain_id: 0,
             epoch: U64::default(),
             latest_committed: U64::default(),
+            latest_committed_block_hash: H256::default(),
             pivot_decision: Decision {
                 height: U64::default(),
                 block_hash: H256::default(),
             },
             latest_voted: None,
+            latest_voted_round: None,
             latest_tx_number: U64::default(),
         }
     }
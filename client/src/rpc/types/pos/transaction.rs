@@ -259,3 +259,76 @@ impl From<KeptVMStatus> for RpcTransactionStatus {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{tx_type, RpcTransactionPayload, RpcTransactionType};
+    use cfx_types::{H256, U64};
+    use diem_types::{
+        account_address::AccountAddress,
+        block_info::PivotBlockDecision,
+        transaction::{
+            RetirePayload, TransactionPayload, UpdateVotingPowerPayload,
+        },
+    };
+
+    #[test]
+    fn test_decode_pivot_decision_payload() {
+        let payload = TransactionPayload::PivotDecision(PivotBlockDecision {
+            height: 100,
+            block_hash: H256::from_low_u64_be(1),
+        });
+        assert!(matches!(
+            tx_type(payload.clone()),
+            RpcTransactionType::PivotDecision
+        ));
+        match RpcTransactionPayload::from(payload) {
+            RpcTransactionPayload::PivotDecision(p) => {
+                assert_eq!(p.height, U64::from(100));
+                assert_eq!(p.block_hash, H256::from_low_u64_be(1));
+            }
+            p => panic!("unexpected payload {:?}", p),
+        }
+    }
+
+    #[test]
+    fn test_decode_retire_payload() {
+        let node_id = AccountAddress::new([1; AccountAddress::LENGTH]);
+        let payload = TransactionPayload::Retire(RetirePayload {
+            node_id,
+            votes: 5,
+        });
+        assert!(matches!(
+            tx_type(payload.clone()),
+            RpcTransactionType::Retire
+        ));
+        match RpcTransactionPayload::from(payload) {
+            RpcTransactionPayload::Retire(r) => {
+                assert_eq!(r.address, H256::from(node_id.to_u8()));
+                assert_eq!(r.voting_power, U64::from(5));
+            }
+            p => panic!("unexpected payload {:?}", p),
+        }
+    }
+
+    #[test]
+    fn test_decode_update_voting_power_payload() {
+        let node_address = AccountAddress::new([2; AccountAddress::LENGTH]);
+        let payload =
+            TransactionPayload::UpdateVotingPower(UpdateVotingPowerPayload {
+                node_address,
+                voting_power: 7,
+            });
+        assert!(matches!(
+            tx_type(payload.clone()),
+            RpcTransactionType::UpdateVotingPower
+        ));
+        match RpcTransactionPayload::from(payload) {
+            RpcTransactionPayload::UpdateVotingPower(u) => {
+                assert_eq!(u.address, H256::from(node_address.to_u8()));
+                assert_eq!(u.voting_power, U64::from(7));
+            }
+            p => panic!("unexpected payload {:?}", p),
+        }
+    }
+}
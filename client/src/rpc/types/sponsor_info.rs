@@ -5,7 +5,8 @@
 use super::RpcAddress;
 use cfx_addr::Network;
 use cfx_parameters::staking::DRIPS_PER_STORAGE_COLLATERAL_UNIT;
-use cfx_types::U256;
+use cfx_types::{Address, U256};
+use cfxcore::state::COMMISSION_PRIVILEGE_SPECIAL_KEY;
 use primitives::SponsorInfo as PrimitiveSponsorInfo;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -71,3 +72,33 @@ impl SponsorInfo {
         })
     }
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorWhitelist {
+    /// This is true if all users are whitelisted by the contract.
+    pub all_users: bool,
+    /// This is the list of the whitelisted users.
+    pub users: Vec<RpcAddress>,
+}
+
+impl SponsorWhitelist {
+    /// Converts the list returned by `State::commission_privilege_list`.
+    pub fn try_from(
+        users: Vec<Address>, network: Network,
+    ) -> Result<Self, String> {
+        let mut all_users = false;
+        let mut rpc_users = Vec::with_capacity(users.len());
+        for user in users {
+            if user == *COMMISSION_PRIVILEGE_SPECIAL_KEY {
+                all_users = true;
+            } else {
+                rpc_users.push(RpcAddress::try_from_h160(user, network)?);
+            }
+        }
+        Ok(Self {
+            all_users,
+            users: rpc_users,
+        })
+    }
+}
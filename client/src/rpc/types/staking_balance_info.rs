@@ -0,0 +1,17 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use cfx_types::U256;
+
+#[derive(Debug, Serialize, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StakingBalanceInfo {
+    pub staking_balance: U256,
+    /// The staking balance locked by the votes in the staking contract.
+    pub vote_locked: U256,
+    /// The staking balance locked for the PoS votes.
+    pub pos_locked: U256,
+    /// The staking balance which can be withdrawn in the next block.
+    pub available: U256,
+}
@@ -4,6 +4,9 @@ use cfx_types::U256;
 #[serde(rename_all = "camelCase")]
 pub struct TokenSupplyInfo {
     pub total_circulating: U256,
+    /// The circulating tokens used to compute the PoS interest, which also
+    /// exclude the balance of the zero address.
+    pub total_pos_circulating: U256,
     pub total_issued: U256,
     pub total_staking: U256,
     pub total_collateral: U256,
@@ -0,0 +1,92 @@
+// Copyright 2021 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use cfx_state::CleanupMode;
+use cfx_statedb::StateDb;
+use cfx_storage::{
+    defaults::DEFAULT_EXECUTION_PREFETCH_THREADS,
+    tests::new_state_manager_for_unit_test, StateIndex, StorageManager,
+    StorageManagerTrait,
+};
+use cfx_types::{
+    address_util::AddressUtil, Address, AddressSpaceUtil, AddressWithSpace,
+    BigEndianHash, U256,
+};
+use cfxcore::state::{RequireCache, State};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use primitives::EpochId;
+use rayon::ThreadPoolBuilder;
+use std::sync::Arc;
+
+const NUM_ACCOUNTS: u64 = 2000;
+
+fn get_state(
+    storage_manager: &Arc<StorageManager>, epoch_id: &EpochId,
+) -> State {
+    State::new(StateDb::new(
+        storage_manager
+            .get_state_for_next_epoch(StateIndex::new_for_test_only_delta_mpt(
+                epoch_id,
+            ))
+            .unwrap()
+            .unwrap(),
+    ))
+    .unwrap()
+}
+
+fn prefetch_benchmark(c: &mut Criterion) {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = State::new(StateDb::new(
+        storage_manager.get_state_for_genesis_write(),
+    ))
+    .unwrap();
+    let addresses = (1..=NUM_ACCOUNTS)
+        .map(|i| {
+            let mut address = Address::from_low_u64_be(i);
+            address.set_user_account_type_bits();
+            address.with_native_space()
+        })
+        .collect::<Vec<AddressWithSpace>>();
+    for address in &addresses {
+        state
+            .add_balance(address, &U256::one(), CleanupMode::NoEmpty)
+            .unwrap();
+    }
+    let epoch_id = EpochId::from_uint(&U256::one());
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+    let accounts = addresses
+        .iter()
+        .map(|address| (*address, RequireCache::None))
+        .collect::<Vec<_>>();
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(DEFAULT_EXECUTION_PREFETCH_THREADS)
+        .build()
+        .unwrap();
+
+    c.bench_function("Load accounts sequentially", |b| {
+        b.iter_batched(
+            || get_state(&storage_manager, &epoch_id),
+            |state| {
+                for address in &addresses {
+                    state.try_load(address).unwrap();
+                }
+                state
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    c.bench_function("Prefetch accounts", |b| {
+        b.iter_batched(
+            || get_state(&storage_manager, &epoch_id),
+            |state| {
+                state.prefetch_accounts(&accounts, &pool).unwrap();
+                state
+            },
+            BatchSize::SmallInput,
+        );
+    });
+}
+
+criterion_group!(benches, prefetch_benchmark);
+criterion_main!(benches);
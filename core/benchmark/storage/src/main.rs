@@ -1736,7 +1736,7 @@ impl TxReplayer {
         Ok(state_root_with_aux)
     }
 
-    // FIXME: use and test performance with ExecutionStatePrefetcher
+    // FIXME: use and test performance with State::prefetch_accounts
     pub fn add_tx(
         &self, tx: RealizedEthTx, latest_state: &mut StateDb,
         last_state_root: &mut StateRootWithAuxInfo,
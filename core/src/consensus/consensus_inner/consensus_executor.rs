@@ -24,6 +24,7 @@ use cfx_internal_common::{
 };
 use cfx_parameters::{
     consensus::*, consensus_internal::CIP107_STORAGE_POINT_PROP_INIT,
+    internal_contract_addresses::STORAGE_INTEREST_STAKING_CONTRACT_ADDRESS,
 };
 use cfx_state::CleanupMode;
 use cfx_statedb::{ErrorKind as DbErrorKind, Result as DbResult, StateDb};
@@ -42,6 +43,7 @@ use primitives::{
     Action, Block, BlockHeaderBuilder, BlockNumber, EpochId, SignedTransaction,
     TransactionIndex, MERKLE_NULL_NODE,
 };
+use rayon::{ThreadPool, ThreadPoolBuilder};
 
 use crate::{
     block_data_manager::{BlockDataManager, BlockRewardResult, PosRewardInfo},
@@ -66,12 +68,7 @@ use crate::{
     observer::trace::{ExecTrace, TransactionExecTraces},
     rpc_errors::{invalid_params_check, Result as RpcResult},
     spec::genesis::initialize_internal_contract_accounts,
-    state::{
-        prefetcher::{
-            prefetch_accounts, ExecutionStatePrefetcher, PrefetchTaskHandle,
-        },
-        State,
-    },
+    state::{RequireCache, State, StateNotifier},
     verification::{
         compute_receipts_root, VerificationConfig, VerifyTxLocalMode,
         VerifyTxMode,
@@ -826,7 +823,9 @@ pub struct ConsensusExecutionHandler {
     verification_config: VerificationConfig,
     machine: Arc<Machine>,
     pos_verifier: Arc<PosVerifier>,
-    execution_state_prefetcher: Option<Arc<ExecutionStatePrefetcher>>,
+    execution_prefetch_pool: Option<ThreadPool>,
+    /// Delivers the accounts modified on the local pivot chain to `tx_pool`.
+    state_notifier: StateNotifier,
 }
 
 impl ConsensusExecutionHandler {
@@ -838,23 +837,26 @@ impl ConsensusExecutionHandler {
     ) -> Self
     {
         ConsensusExecutionHandler {
+            state_notifier: StateNotifier::new_for_txpool(tx_pool.clone()),
             tx_pool,
             data_man,
             config,
             verification_config,
             machine,
             pos_verifier,
-            execution_state_prefetcher: if DEFAULT_EXECUTION_PREFETCH_THREADS
-                > 0
+            execution_prefetch_pool: if DEFAULT_EXECUTION_PREFETCH_THREADS > 0
             {
                 Some(
-                    ExecutionStatePrefetcher::new(
-                        DEFAULT_EXECUTION_PREFETCH_THREADS,
-                    )
-                    .expect(
-                        // Do not accept error at starting up.
-                        &concat!(file!(), ":", line!(), ":", column!()),
-                    ),
+                    ThreadPoolBuilder::new()
+                        .num_threads(DEFAULT_EXECUTION_PREFETCH_THREADS)
+                        .thread_name(|i| {
+                            format!("Execution state prefetcher {}", i)
+                        })
+                        .build()
+                        .expect(
+                            // Do not accept error at starting up.
+                            &concat!(file!(), ":", line!(), ":", column!()),
+                        ),
                 )
             } else {
                 None
@@ -870,7 +872,10 @@ impl ConsensusExecutionHandler {
                 self.handle_epoch_execution(task, None)
             }
             ExecutionTask::GetResult(task) => self.handle_get_result_task(task),
-            ExecutionTask::Stop => return false,
+            ExecutionTask::Stop => {
+                self.state_notifier.flush();
+                return false;
+            }
         }
         true
     }
@@ -1064,6 +1069,7 @@ impl ConsensusExecutionHandler {
                 .expect("State exists"),
         ))
         .expect("Failed to initialize state");
+        state.set_cache_byte_budget(self.config.state_cache_byte_budget);
 
         let epoch_receipts = self
             .process_epoch_transactions(
@@ -1114,15 +1120,19 @@ impl ConsensusExecutionHandler {
             // The pos_reference is continuous, so after seeing a new
             // pos_reference, we only need to process the new
             // unlock_txs in it.
-            for (unlock_node_id, votes) in self
+            let unlock_nodes = self
                 .pos_verifier
-                .get_unlock_nodes(current_pos_ref, parent_pos_ref)
-            {
+                .get_unlock_nodes(current_pos_ref, parent_pos_ref);
+            for (unlock_node_id, votes) in &unlock_nodes {
                 debug!("unlock node: {:?} {}", unlock_node_id, votes);
-                state
-                    .update_pos_status(unlock_node_id, votes)
-                    .expect("db error");
             }
+            // Every unlocked node must be registered and its unlocked votes
+            // cannot decrease, so an error here means either the PoS unlock
+            // invariant is violated or the db failed.
+            state.update_pos_statuses(&unlock_nodes).expect(
+                "PoS unlock invariant violated (unregistered identifier or \
+                 decreasing unlocked votes) or db error",
+            );
             if let Some((pos_epoch, reward_event)) = self
                 .pos_verifier
                 .get_reward_distribution_event(current_pos_ref, parent_pos_ref)
@@ -1130,12 +1140,26 @@ impl ConsensusExecutionHandler {
                 .and_then(|x| x.first())
             {
                 debug!("distribute_pos_interest: {:?}", reward_event);
-                let account_rewards = state
+                let skip_unregistered = current_block_number
+                    >= self
+                        .machine
+                        .params()
+                        .transition_numbers
+                        .skip_unregistered_pos_interest;
+                let (account_rewards, forfeited) = state
                     .distribute_pos_interest(
                         Box::new(reward_event.rewards()),
                         current_block_number,
+                        skip_unregistered,
                     )
                     .expect("db error");
+                if !forfeited.is_zero() {
+                    warn!(
+                        "pos interest {} of unregistered identifiers is kept \
+                         for the next distribution",
+                        forfeited
+                    );
+                }
                 self.data_man.insert_pos_reward(
                     *pos_epoch,
                     &PosRewardInfo::new(account_rewards, *epoch_hash),
@@ -1149,7 +1173,7 @@ impl ConsensusExecutionHandler {
             state_root = state
                 .commit_and_notify(
                     *epoch_hash,
-                    &self.tx_pool,
+                    &self.state_notifier,
                     debug_record.as_deref_mut(),
                 )
                 .expect(&concat!(file!(), ":", line!(), ":", column!()));
@@ -1192,46 +1216,65 @@ impl ConsensusExecutionHandler {
         on_local_pivot: bool,
     ) -> DbResult<Vec<Arc<BlockReceipts>>>
     {
-        // Prefetch accounts for transactions.
-        // The return value _prefetch_join_handles is used to join all threads
-        // before the exit of this function.
-        let prefetch_join_handles = match self
-            .execution_state_prefetcher
-            .as_ref()
-        {
-            Some(prefetcher) => {
-                let mut accounts = vec![];
-                for block in epoch_blocks.iter() {
-                    for transaction in block.transactions.iter() {
-                        accounts.push(&transaction.sender);
-                        match transaction.action() {
-                            Action::Call(ref address) => accounts.push(address),
-                            _ => {}
+        // Prefetch the senders and receivers of the transactions, and then
+        // the sponsors of the called contracts, which are only known once the
+        // contracts are loaded. The code of the called contracts and the
+        // deposit lists of the stakers are prefetched with the accounts.
+        if let Some(pool) = &self.execution_prefetch_pool {
+            let mut accounts = vec![];
+            let mut contracts = vec![];
+            for block in epoch_blocks.iter() {
+                for transaction in block.transactions.iter() {
+                    let native = transaction.space() == Space::Native;
+                    let mut sender_require = RequireCache::None;
+                    if let Action::Call(address) = transaction.action() {
+                        let mut receiver_require = RequireCache::None;
+                        if native && address.is_contract_address() {
+                            contracts.push(*address);
+                            receiver_require = RequireCache::Code;
                         }
+                        if native
+                            && *address
+                                == STORAGE_INTEREST_STAKING_CONTRACT_ADDRESS
+                        {
+                            sender_require = RequireCache::DepositList;
+                        }
+                        accounts.push((
+                            address.with_space(transaction.space()),
+                            receiver_require,
+                        ));
                     }
+                    accounts.push((transaction.sender(), sender_require));
                 }
+            }
+            state.prefetch_accounts(&accounts, pool)?;
 
-                prefetch_accounts(prefetcher, epoch_id, state, accounts)
+            let mut sponsors = vec![];
+            for contract in &contracts {
+                let sponsor_info = match state.sponsor_info(contract)? {
+                    Some(sponsor_info) => sponsor_info,
+                    None => continue,
+                };
+                sponsors.extend(
+                    [
+                        sponsor_info.sponsor_for_gas,
+                        sponsor_info.sponsor_for_collateral,
+                    ]
+                    .iter()
+                    .filter(|sponsor| !sponsor.is_zero())
+                    .map(|sponsor| {
+                        (sponsor.with_native_space(), RequireCache::None)
+                    }),
+                );
             }
-            None => PrefetchTaskHandle {
-                task_epoch_id: epoch_id,
-                state,
-                prefetcher: None,
-                accounts: vec![],
-            },
-        };
-        // TODO:
-        //   Make the state shared ref for vm execution, then remove this drop.
-        //   When the state can be made shared, prefetch can happen at the same
-        //   time of the execution, the vm execution do not have to wait
-        //   for prefetching to finish.
-        prefetch_join_handles.wait_for_task();
-        drop(prefetch_join_handles);
+            state.prefetch_accounts(&sponsors, pool)?;
+        }
 
         let pivot_block = epoch_blocks.last().expect("Epoch not empty");
 
         let mut epoch_receipts = Vec::with_capacity(epoch_blocks.len());
         let mut epoch_staking_events = Vec::new();
+        let mut staking_accounts = BTreeSet::new();
         let mut to_pending = Vec::new();
         let mut block_number = start_block_number;
         let mut last_block_hash =
@@ -1284,6 +1327,7 @@ impl ConsensusExecutionHandler {
                 state.bump_block_number_accumulate_interest();
             }
             let secondary_reward = state.secondary_reward();
+            state.set_distribute_window_blocks(spec.distribute_window_blocks);
             state.inc_distributable_pos_interest(env.number)?;
             initialize_internal_contract_accounts(
                 state,
@@ -1401,6 +1445,16 @@ impl ConsensusExecutionHandler {
                             block_traces.push(executed.trace.into());
                         }
 
+                        if let Action::Call(address) = transaction.action() {
+                            if transaction.space() == Space::Native
+                                && *address
+                                    == STORAGE_INTEREST_STAKING_CONTRACT_ADDRESS
+                            {
+                                staking_accounts
+                                    .insert(transaction.sender().address);
+                            }
+                        }
+
                         if self.pos_verifier.pos_option().is_some() {
                             trace!("Check {} events", transaction_logs.len());
                             for log in &transaction_logs {
@@ -1521,6 +1575,22 @@ impl ConsensusExecutionHandler {
 
             epoch_receipts.push(block_receipts);
         }
+
+        // The staking contract emits no events, so the expired vote stakes of
+        // the accounts which called it in this epoch are swept.
+        let last_block_number = block_number - 1;
+        if last_block_number
+            >= self.machine.params().transition_numbers.vote_stake_sweep
+        {
+            let staking_accounts: Vec<_> =
+                staking_accounts.into_iter().collect();
+            let removed = state.sweep_expired_vote_stakes(
+                &staking_accounts,
+                last_block_number,
+            )?;
+            debug!("swept {} expired vote stakes", removed);
+        }
+
         if self.pos_verifier.pos_option().is_some() {
             debug!(
                 "put_staking_events: {:?} height={} len={}",
@@ -2045,4 +2115,7 @@ impl ConsensusExecutionHandler {
 
 pub struct ConsensusExecutionConfiguration {
     pub executive_trace: bool,
+    /// Byte budget of the clean accounts cached by the state during epoch
+    /// execution, unlimited if `None`.
+    pub state_cache_byte_budget: Option<usize>,
 }
@@ -208,7 +208,7 @@ impl PosHandler {
         pos_config.consensus.safety_rules.export_consensus_key = true;
         pos_config.consensus.safety_rules.vrf_proposal_threshold =
             self.conf.vrf_proposal_threshold;
-        pos_config.consensus.chain_id = ChainId::new(network.network_id());
+        pos_config.consensus.chain_id = self.conf.chain_id;
 
         let pos_drop_handle = start_pos_consensus(
             &pos_config,
@@ -639,6 +639,8 @@ pub struct PosConfiguration {
     pub pos_initial_nodes_path: String,
     pub vrf_proposal_threshold: U256,
     pub pos_state_config: PosStateConfig,
+    /// The chain id signed into PoS consensus messages.
+    pub chain_id: ChainId,
 }
 
 fn diem_hash_to_h256(h: &HashValue) -> PosBlockId { H256::from(h.as_ref()) }
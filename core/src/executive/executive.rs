@@ -19,7 +19,9 @@ use crate::{
     observer::{
         tracer::ExecutiveTracer, AddressPocket, GasMan, StateTracer, VmObserve,
     },
-    state::{cleanup_mode, CallStackInfo, State, Substate},
+    state::{
+        cleanup_mode, CallStackInfo, CollateralEstimation, State, Substate,
+    },
     verification::VerificationConfig,
     vm::{
         self, ActionParams, ActionValue, CallType, CreateContractAddress,
@@ -586,10 +588,10 @@ impl<'a> CallCreateExecutive<'a> {
                     .push(create_address.with_space(self.context.space));
             }
             maybe_substate = Some(substate);
-            state.discard_checkpoint();
+            state.discard_checkpoint()?;
         } else {
             maybe_substate = None;
-            state.revert_to_checkpoint();
+            state.revert_to_checkpoint()?;
         }
 
         let create_address = self.create_address;
@@ -1061,7 +1063,7 @@ impl<'a> ExecutiveGeneric<'a> {
             "Transaction estimate first pass outcome {:?}",
             sender_pay_executed
         );
-        self.state.revert_to_checkpoint();
+        self.state.revert_to_checkpoint()?;
 
         // Second pass
         let mut contract_pay_executed: Option<Executed> = None;
@@ -1094,7 +1096,7 @@ impl<'a> ExecutiveGeneric<'a> {
                         &tx,
                         TransactOptions::estimate_second_pass(request),
                     )?;
-                    self.state.revert_to_checkpoint();
+                    self.state.revert_to_checkpoint()?;
 
                     contract_pay_executed = match res {
                         ExecutionOutcome::Finished(executed) => Some(executed),
@@ -1284,7 +1286,7 @@ impl<'a> ExecutiveGeneric<'a> {
             self.state.sponsor_balance_for_collateral(&code_address)?
                 + self
                     .state
-                    .avaliable_storage_point_for_collateral(&code_address)?;
+                    .available_storage_point_for_collateral(&code_address)?;
         let storage_sponsored = match settings.charge_collateral {
             ChargeCollateral::Normal => {
                 storage_sponsor_eligible
@@ -1597,7 +1599,7 @@ impl<'a> ExecutiveGeneric<'a> {
                     && self.state.is_contract_with_code(&new_address)?
                 {
                     observer.as_state_tracer().revert_to_checkpoint();
-                    self.state.revert_to_checkpoint();
+                    self.state.revert_to_checkpoint()?;
                     return Ok(ExecutionOutcome::ExecutionErrorBumpNonce(
                         ExecutionError::VmError(vm::Error::ConflictAddress(
                             new_address.address.clone(),
@@ -1665,6 +1667,7 @@ impl<'a> ExecutiveGeneric<'a> {
         };
 
         // Charge collateral and process the checkpoint.
+        let mut collateral_estimation = None;
         let (result, output) = {
             let res = res.and_then(|finalize_res| {
                 let dry_run_no_charge = !matches!(
@@ -1672,6 +1675,17 @@ impl<'a> ExecutiveGeneric<'a> {
                     ChargeCollateral::Normal
                 );
 
+                // An estimation charges nothing, so the collateral changes
+                // are only estimated instead of being settled.
+                if dry_run_no_charge {
+                    self.state.collect_ownership_changed(&mut substate)?;
+                    collateral_estimation = Some(
+                        self.state
+                            .estimate_collateral_change(&substate, &self.spec)?,
+                    );
+                    return Ok(finalize_res);
+                }
+
                 // For a ethereum space tx, this function has no op.
                 // TODO: in fact, we don't need collect again here. But this is
                 // only the performance optimization and we put it in the later
@@ -1691,7 +1705,7 @@ impl<'a> ExecutiveGeneric<'a> {
             let out = match &res {
                 Ok(res) => {
                     observer.as_state_tracer().discard_checkpoint();
-                    self.state.discard_checkpoint();
+                    self.state.discard_checkpoint()?;
                     tx_substate.accrue(substate);
                     res.return_data.to_vec()
                 }
@@ -1701,7 +1715,7 @@ impl<'a> ExecutiveGeneric<'a> {
                 }
                 Err(_) => {
                     observer.as_state_tracer().revert_to_checkpoint();
-                    self.state.revert_to_checkpoint();
+                    self.state.revert_to_checkpoint()?;
                     Vec::new()
                 }
             };
@@ -1733,6 +1747,7 @@ impl<'a> ExecutiveGeneric<'a> {
             },
             observer,
             estimated_gas_limit,
+            collateral_estimation,
         )?)
     }
 
@@ -1770,7 +1785,7 @@ impl<'a> ExecutiveGeneric<'a> {
         }
 
         let res = self.state.settle_collateral_for_all(
-            &substate, tracer, spec,
+            &mut substate, tracer, spec,
             // Kill process does not occupy new storage entries.
             false,
         )?;
@@ -1863,6 +1878,7 @@ impl<'a> ExecutiveGeneric<'a> {
         result: vm::Result<FinalizationResult>, output: Bytes,
         refund_receiver: Option<Address>, storage_sponsor_paid: bool,
         mut observer: Observer, estimated_gas_limit: Option<U256>,
+        collateral_estimation: Option<CollateralEstimation>,
     ) -> DbResult<ExecutionOutcome>
     {
         let gas_left = match result {
@@ -1988,12 +2004,25 @@ impl<'a> ExecutiveGeneric<'a> {
                 let trace =
                     observer.tracer.map_or(Default::default(), |t| t.drain());
 
-                let estimated_storage_limit =
-                    if let Some(x) = storage_collateralized.first() {
-                        x.collaterals.as_u64()
-                    } else {
-                        0
-                    };
+                let estimated_storage_limit = match collateral_estimation {
+                    // The collateral of an estimation is not settled, so it
+                    // is taken from the estimated settlement.
+                    Some(estimation) if r.apply_state => estimation
+                        .owners
+                        .iter()
+                        .find(|owner| !owner.charge.is_zero())
+                        .map_or(0, |owner| {
+                            (owner.charge / *DRIPS_PER_STORAGE_COLLATERAL_UNIT)
+                                .as_u64()
+                        }),
+                    _ => {
+                        if let Some(x) = storage_collateralized.first() {
+                            x.collaterals.as_u64()
+                        } else {
+                            0
+                        }
+                    }
+                };
 
                 let executed = Executed {
                     gas_used,
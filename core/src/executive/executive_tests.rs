@@ -142,7 +142,7 @@ fn test_sender_balance() {
             .unwrap()
             .into_vm_result()
             .unwrap();
-        state.discard_checkpoint();
+        state.discard_checkpoint().unwrap();
         res
     };
 
@@ -402,7 +402,7 @@ fn test_call_to_create() {
             .unwrap()
             .into_vm_result()
             .unwrap();
-        state.discard_checkpoint();
+        state.discard_checkpoint().unwrap();
         res
     };
     assert_eq!(state.balance(&sender_with_space).unwrap(), U256::from(0));
@@ -1017,7 +1017,7 @@ fn test_commission_privilege_all_whitelisted_across_epochs() {
             false,
         )
         .unwrap();
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     let mut debug_record = ComputeEpochDebugRecord::default();
     state.commit(epoch_id, Some(&mut debug_record)).unwrap();
     debug!("{:?}", debug_record);
@@ -1091,7 +1091,7 @@ fn test_commission_privilege_all_whitelisted_across_epochs() {
             false,
         )
         .unwrap();
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     state.commit(epoch_id, None).unwrap();
 
     let state = State::new(StateDb::new(
@@ -1672,7 +1672,7 @@ fn test_storage_commission_privilege() {
             .unwrap(),
         CollateralCheckResult::Valid
     );
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     assert_eq!(substate.storage_collateralized.len(), 1);
     assert_eq!(
         substate.storage_collateralized[&sender.address()],
@@ -2012,7 +2012,7 @@ fn test_storage_commission_privilege() {
         state.balance(&sender_with_space).unwrap(),
         U256::from(1_875_000_000_000_000_000u64)
     );
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     assert_eq!(
         state.collateral_for_storage(&sender.address()).unwrap(),
         *COLLATERAL_DRIPS_PER_STORAGE_KEY * U256::from(1),
@@ -20,9 +20,17 @@ impl CollateralCheckResultToVmResult for CollateralCheckResult {
             CollateralCheckResult::ExceedStorageLimit { .. } => {
                 Err(vmError::ExceedStorageLimit)
             }
-            CollateralCheckResult::NotEnoughBalance { required, got } => {
-                Err(vmError::NotEnoughBalanceForStorage { required, got })
-            }
+            CollateralCheckResult::NotEnoughBalance {
+                required,
+                got,
+                address,
+                source,
+            } => Err(vmError::NotEnoughBalanceForStorage {
+                required,
+                got,
+                address,
+                source,
+            }),
             CollateralCheckResult::Valid => Ok(()),
         }
     }
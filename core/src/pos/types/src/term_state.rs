@@ -353,6 +353,8 @@ impl TermList {
     }
 
     pub fn term_list(&self) -> &Vec<TermData> { &self.term_list }
+
+    pub fn current_term(&self) -> u64 { self.current_term }
 }
 
 impl TermList {
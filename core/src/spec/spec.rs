@@ -14,6 +14,7 @@ use cfx_parameters::{
         ANTICONE_PENALTY_RATIO, DAO_PARAMETER_VOTE_PERIOD,
         INITIAL_BASE_MINING_REWARD_IN_UCFX,
     },
+    staking::BLOCKS_PER_HOUR,
 };
 use cfx_types::{AllChainID, U256, U512};
 use primitives::{block::BlockHeight, BlockNumber};
@@ -47,6 +48,9 @@ pub struct CommonParams {
     /// transactions
     pub evm_transaction_gas_ratio: u64,
     pub params_dao_vote_period: u64,
+    /// The number of blocks after the last PoS interest distribution during
+    /// which the PoS interest is accumulated.
+    pub distribute_window_blocks: u64,
 
     /// Set the internal contracts to state at the genesis blocks, even if it
     /// is not activated.
@@ -87,6 +91,12 @@ pub struct TransitionsBlockNumber {
     /// CIP-107: Reduce the refunded storage collateral.
     pub cip107: BlockNumber,
     pub cip_sigma_fix: BlockNumber,
+    /// Sweep the expired vote stakes of the accounts which interacted with
+    /// the staking contract at the end of each epoch.
+    pub vote_stake_sweep: BlockNumber,
+    /// Skip the PoS interest of the identifiers which resolve to the zero
+    /// address, and keep it for the next distribution.
+    pub skip_unregistered_pos_interest: BlockNumber,
 }
 
 #[derive(Default, Debug, Clone)]
@@ -122,6 +132,7 @@ impl Default for CommonParams {
             evm_transaction_block_ratio: EVM_TRANSACTION_BLOCK_RATIO,
             evm_transaction_gas_ratio: EVM_TRANSACTION_GAS_RATIO,
             params_dao_vote_period: DAO_PARAMETER_VOTE_PERIOD,
+            distribute_window_blocks: BLOCKS_PER_HOUR,
             early_set_internal_contracts_states: false,
             transition_numbers: Default::default(),
             transition_heights: Default::default(),
@@ -152,6 +152,22 @@ impl OverlayAccount {
         overlay_account
     }
 
+    /// Create an OverlayAccount from a fully specified account which replaces
+    /// the account at `address`, e.g. in genesis. The CIP-107 storage points
+    /// are only kept for a native space account.
+    pub fn new_imported(
+        address: &AddressWithSpace, mut account: Account,
+        invalidated_storage: bool,
+    ) -> Self
+    {
+        if address.space == Space::Ethereum {
+            account.sponsor_info.storage_points = None;
+        }
+        let mut overlay_account = Self::from_loaded(address, account);
+        overlay_account.invalidated_storage = invalidated_storage;
+        overlay_account
+    }
+
     /// Create an OverlayAccount of basic account when the account doesn't exist
     /// before.
     pub fn new_basic(address: &AddressWithSpace, balance: U256) -> Self {
@@ -280,13 +296,22 @@ impl OverlayAccount {
         self.code_hash != KECCAK_EMPTY || self.is_newly_created_contract
     }
 
-    fn fresh_storage(&self) -> bool {
+    /// Whether the storage in the db is discarded, because the account is
+    /// newly created or killed.
+    pub fn fresh_storage(&self) -> bool {
         let builtin_address = self.address.space == Space::Native
             && self.address.address.is_builtin_address();
         (self.is_newly_created_contract && !builtin_address)
             || self.invalidated_storage
     }
 
+    /// Whether the storage has changes which are not committed to the db.
+    pub fn has_dirty_storage(&self) -> bool {
+        !self.storage_value_write_cache.is_empty()
+            || self.storage_layout_change.is_some()
+            || self.fresh_storage()
+    }
+
     pub fn removed_without_update(&self) -> bool {
         self.invalidated_storage && self.as_account().is_default()
     }
@@ -474,15 +499,24 @@ impl OverlayAccount {
         charge_from_points
     }
 
-    fn refund_for_sponsored_collateral(&mut self, by: U256) -> U256 {
-        assert!(self.is_contract());
-        let refund_from_points = std::cmp::min(
+    /// The part of `sub_collateral_for_storage(by)` which is refunded as
+    /// storage points.
+    pub fn storage_point_refund(&self, by: &U256) -> U256 {
+        if !self.is_contract() {
+            return U256::zero();
+        }
+        std::cmp::min(
             self.sponsor_info
                 .storage_points
                 .as_ref()
                 .map_or(U256::zero(), |x| x.used),
-            by,
-        );
+            *by,
+        )
+    }
+
+    fn refund_for_sponsored_collateral(&mut self, by: U256) -> U256 {
+        assert!(self.is_contract());
+        let refund_from_points = self.storage_point_refund(&by);
         if !refund_from_points.is_zero() {
             let storage_points = self
                 .sponsor_info
@@ -771,6 +805,24 @@ impl OverlayAccount {
         account
     }
 
+    /// Same as `clone_dirty`, but the storage caches are copied instead of
+    /// shared, so that `self` can still be committed.
+    pub fn clone_owned(&self) -> Self {
+        let mut account = self.clone_basic();
+        account.storage_value_write_cache =
+            Arc::new((*self.storage_value_write_cache).clone());
+        account.storage_value_read_cache = Arc::new(RwLock::new(
+            self.storage_value_read_cache.read().clone(),
+        ));
+        account.storage_owner_lv2_write_cache = RwLock::new(Arc::new(
+            (**self.storage_owner_lv2_write_cache.read()).clone(),
+        ));
+        account.storage_owner_lv1_write_cache =
+            Arc::new((*self.storage_owner_lv1_write_cache).clone());
+        account.storage_layout_change = self.storage_layout_change.clone();
+        account
+    }
+
     pub fn set_storage(&mut self, key: Vec<u8>, value: U256, owner: Address) {
         Arc::make_mut(&mut self.storage_value_write_cache)
             .insert(key.clone(), value);
@@ -852,6 +904,31 @@ impl OverlayAccount {
         }
     }
 
+    /// The net number of storage entries created by the write cache, i.e. the
+    /// new non-zero entries minus the cleared entries which exist in the db.
+    /// The db storage is not counted if it is discarded.
+    pub fn storage_delta(&self, db: &StateDbGeneric) -> DbResult<i64> {
+        let mut delta = 0;
+        for (key, value) in self.storage_value_write_cache.iter() {
+            let existed = !self.fresh_storage()
+                && db
+                    .get::<StorageValue>(
+                        StorageKey::new_storage_key(
+                            &self.address.address,
+                            key.as_ref(),
+                        )
+                        .with_space(self.address.space),
+                    )?
+                    .is_some();
+            match (existed, value.is_zero()) {
+                (false, false) => delta += 1,
+                (true, true) => delta -= 1,
+                _ => {}
+            }
+        }
+        Ok(delta)
+    }
+
     pub fn change_storage_value(
         &mut self, db: &StateDbGeneric, key: &[u8], value: U256,
     ) -> DbResult<()> {
@@ -905,6 +982,11 @@ impl OverlayAccount {
         }
     }
 
+    /// Size of the code held in the cache, 0 if it has not been loaded.
+    pub fn cached_code_size(&self) -> usize {
+        self.code.as_ref().map_or(0, |c| c.code_size())
+    }
+
     pub fn init_code(&mut self, code: Bytes, owner: Address) {
         self.code_hash = keccak(&code);
         self.code = Some(CodeInfo {
@@ -970,6 +1052,15 @@ impl OverlayAccount {
             .clone())
     }
 
+    /// Return the owner of `key` recorded in the write caches, or `None` if
+    /// the ownership of `key` is not changed in this execution.
+    pub fn cached_storage_owner(&self, key: &[u8]) -> Option<Option<Address>> {
+        if let Some(owner) = self.storage_owner_lv1_write_cache.get(key) {
+            return Some(*owner);
+        }
+        self.storage_owner_lv2_write_cache.read().get(key).cloned()
+    }
+
     /// Return the storage change of each related account.
     /// Each account is associated with a pair of `(usize, usize)`. The first
     /// value means the number of keys occupied by this account in current
@@ -1196,6 +1287,15 @@ impl AccountEntry {
         }
     }
 
+    /// Same as `clone_dirty`, without sharing the storage caches.
+    pub fn clone_owned(&self) -> AccountEntry {
+        AccountEntry {
+            old_balance: self.old_balance,
+            account: self.account.as_ref().map(OverlayAccount::clone_owned),
+            state: self.state,
+        }
+    }
+
     pub fn new_dirty(account: Option<OverlayAccount>) -> AccountEntry {
         AccountEntry {
             old_balance: account.as_ref().map(|acc| acc.balance().clone()),
@@ -3,14 +3,18 @@
 // See http://www.gnu.org/licenses/
 
 use std::{
-    collections::{hash_map::Entry, HashMap, HashSet},
-    sync::Arc,
+    collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet},
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
 
 use num::integer::Roots;
 use parking_lot::{
     lock_api::{MappedRwLockReadGuard, RwLockReadGuard},
-    MappedRwLockWriteGuard, RawRwLock, RwLock, RwLockUpgradableReadGuard,
+    MappedRwLockWriteGuard, Mutex, RawRwLock, RwLock, RwLockUpgradableReadGuard,
     RwLockWriteGuard,
 };
 
@@ -27,23 +31,29 @@ use cfx_parameters::{
     },
     staking::*,
 };
-use cfx_state::{maybe_address, CleanupMode, CollateralCheckResult};
+use cfx_state::{
+    maybe_address, CleanupMode, CollateralCheckResult, CollateralSource,
+};
 use cfx_statedb::{
     ErrorKind as DbErrorKind, Result as DbResult, StateDbExt,
     StateDbGeneric as StateDb,
 };
-use cfx_storage::utils::access_mode;
+use cfx_storage::{utils::access_mode, StateProof};
 use cfx_types::{
     address_util::AddressUtil, Address, AddressSpaceUtil, AddressWithSpace,
     BigEndianHash, Space, H256, U256,
 };
 use diem_types::term_state::MAX_TERM_POINTS;
+use metrics::{Gauge, GaugeUsize};
 #[cfg(test)]
 use primitives::storage::STORAGE_LAYOUT_REGULAR_V0;
 use primitives::{
-    Account, DepositList, EpochId, SkipInputCheck, SponsorInfo, StorageKey,
-    StorageKeyWithSpace, StorageLayout, StorageValue, VoteStakeList,
+    Account, DepositInfo, DepositList, EpochId, SkipInputCheck, SponsorInfo,
+    StorageKey, StorageKeyWithSpace, StorageLayout, StorageRoot, StorageValue,
+    VoteStakeInfo, VoteStakeList,
 };
+use rayon::{prelude::*, ThreadPool};
+use rlp::{Encodable, Rlp, RlpStream};
 
 use crate::{
     executive::internal_contract::{
@@ -51,25 +61,29 @@ use crate::{
         pos_internal_entries, settle_current_votes, storage_point_prop,
         IndexStatus,
     },
-    hash::KECCAK_EMPTY,
+    hash::{keccak, KECCAK_EMPTY},
     observer::{AddressPocket, StateTracer},
     spec::genesis::{
         genesis_contract_address_four_year, genesis_contract_address_two_year,
     },
-    transaction_pool::SharedTransactionPool,
     vm::Spec,
 };
 
 use self::account_entry::{AccountEntry, AccountState};
 pub use self::{
     account_entry::{OverlayAccount, COMMISSION_PRIVILEGE_SPECIAL_KEY},
+    notifier::{StateNotificationHandler, StateNotifier},
+    state_diff::{AccountDiff, AccountSummary, StateDiff},
+    statistics_delta::{StatisticChange, WorldStatisticsDelta},
     substate::{cleanup_mode, CallStackInfo, Substate},
 };
 
 mod account_entry;
 #[cfg(test)]
 mod account_entry_tests;
-pub mod prefetcher;
+mod notifier;
+mod state_diff;
+mod statistics_delta;
 #[cfg(test)]
 mod state_tests;
 mod substate;
@@ -99,6 +113,352 @@ pub enum RequireCache {
     VoteStakeList,
 }
 
+/// Lifecycle events of the checkpoint stack, reported to the observer
+/// registered by `State::set_checkpoint_observer`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CheckpointEvent {
+    Create,
+    Discard,
+    Revert,
+    /// The state is dropped with open checkpoints. Only reported in debug
+    /// builds.
+    Leak,
+}
+
+/// Callback invoked on each checkpoint lifecycle event with the depth of the
+/// checkpoint stack after the event.
+pub type CheckpointObserver =
+    Box<dyn Fn(CheckpointEvent, usize) + Send + Sync>;
+
+/// A checkpoint of `State` which is reverted when the guard is dropped,
+/// unless `commit` is called. The guard dereferences to the state, so nested
+/// scopes are created from the guard itself.
+pub struct CheckpointGuard<'a> {
+    state: &'a mut State,
+    index: usize,
+    committed: bool,
+}
+
+impl<'a> CheckpointGuard<'a> {
+    /// Discard the checkpoint and keep the changes made in this scope.
+    pub fn commit(mut self) -> DbResult<()> {
+        debug_assert_eq!(self.state.checkpoint_depth(), self.index + 1);
+        self.committed = true;
+        self.state.discard_checkpoint().map(|_| ())
+    }
+}
+
+impl<'a> Deref for CheckpointGuard<'a> {
+    type Target = State;
+
+    fn deref(&self) -> &State { self.state }
+}
+
+impl<'a> DerefMut for CheckpointGuard<'a> {
+    fn deref_mut(&mut self) -> &mut State { self.state }
+}
+
+impl<'a> Drop for CheckpointGuard<'a> {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+        // The checkpoints to revert exist, so the revert never fails.
+        let _ = self.state.revert_to_checkpoint_index(self.index);
+    }
+}
+
+// Dropping a state with open checkpoints means that the checkpoints are not
+// used in pairs, which is a logic bug.
+#[cfg(debug_assertions)]
+impl Drop for State {
+    fn drop(&mut self) {
+        if self.has_open_checkpoints() {
+            error!(
+                "State dropped with {} open checkpoints",
+                self.checkpoint_depth()
+            );
+            self.notify_checkpoint_observer(CheckpointEvent::Leak);
+        }
+    }
+}
+
+lazy_static! {
+    static ref STATE_CACHE_ENTRIES_GAUGE: Arc<dyn Gauge<usize>> =
+        GaugeUsize::register_with_group("state", "cache_entries");
+    static ref STATE_CACHE_DIRTY_ENTRIES_GAUGE: Arc<dyn Gauge<usize>> =
+        GaugeUsize::register_with_group("state", "cache_dirty_entries");
+    static ref STATE_CACHE_CODE_BYTES_GAUGE: Arc<dyn Gauge<usize>> =
+        GaugeUsize::register_with_group("state", "cache_code_bytes");
+    /// The genesis contracts which hold the locked genesis tokens.
+    static ref GENESIS_LOCK_ADDRESSES: [AddressWithSpace; 2] = [
+        genesis_contract_address_four_year(),
+        genesis_contract_address_two_year(),
+    ];
+}
+
+/// Size of the account cache of `State`, see `State::cache_stats`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of cached account entries.
+    pub entries: usize,
+    /// Number of cached entries modified since the last commit.
+    pub dirty_entries: usize,
+    /// Total size of the contract code held by the cached entries.
+    pub code_bytes: usize,
+    /// Number of checkpoints currently open.
+    pub checkpoint_depth: usize,
+}
+
+/// An account with its Merkle proof, see `State::prove_account`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountProof {
+    /// The account, `None` if it does not exist.
+    pub account: Option<Account>,
+    /// The rlp encoded account, as it is stored in the state trie.
+    pub value: Option<Vec<u8>>,
+    pub proof: StateProof,
+}
+
+/// A storage entry with its Merkle proof, see `State::prove_storage`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StorageProof {
+    /// The storage entry, `None` if it does not exist.
+    pub storage_value: Option<StorageValue>,
+    /// The rlp encoded storage entry, as it is stored in the state trie.
+    pub value: Option<Vec<u8>>,
+    pub proof: StateProof,
+}
+
+/// The result of `State::replace_sponsor`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SponsorReplaceOutcome {
+    /// The sponsor balances refunded to the previous sponsors.
+    pub refunds: Vec<(Address, U256)>,
+    /// The storage points converted from the new collateral balance.
+    pub converted_storage_points: U256,
+}
+
+/// The token figures of the world statistics, returned by
+/// `State::supply_breakdown`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SupplyBreakdown {
+    pub total_issued_tokens: U256,
+    pub total_staking_tokens: U256,
+    pub total_storage_tokens: U256,
+    pub total_espace_tokens: U256,
+    pub total_pos_staking_tokens: U256,
+    pub distributable_pos_interest: U256,
+    pub used_storage_points: U256,
+    pub converted_storage_points: U256,
+}
+
+/// The estimated collateral settlement of a `Substate`, returned by
+/// `State::estimate_collateral_change`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CollateralEstimation {
+    /// The owners whose collateral changes, sorted by address.
+    pub owners: Vec<OwnerCollateralEstimation>,
+}
+
+impl CollateralEstimation {
+    /// Whether every owner can pay the collateral it is charged.
+    pub fn is_covered(&self) -> bool {
+        self.owners.iter().all(|owner| owner.is_covered())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OwnerCollateralEstimation {
+    pub address: Address,
+    /// The collateral charged, in Drip.
+    pub charge: U256,
+    /// The collateral released, in Drip.
+    pub release: U256,
+    /// Whether the settlement initializes CIP-107 for the owner.
+    pub initializes_cip107: bool,
+    /// The funds which pay the charge after CIP-107 is initialized: the
+    /// sponsor balance and the unused storage points of a contract, or the
+    /// balance of other accounts.
+    pub available: U256,
+    /// The part of the charge paid with storage points, zero if the charge
+    /// is not covered.
+    pub charged_storage_points: U256,
+    /// The sponsor balance converted to storage points when CIP-107 is
+    /// initialized.
+    pub converted_from_balance: U256,
+    /// The collateral converted to storage points when CIP-107 is
+    /// initialized.
+    pub converted_from_collateral: U256,
+    /// The collateral for storage of the owner after the settlement, the used
+    /// storage points included. The charge is only added if it is covered.
+    pub collateral_for_storage: U256,
+}
+
+impl OwnerCollateralEstimation {
+    pub fn is_covered(&self) -> bool { self.charge <= self.available }
+}
+
+/// The storage points of a contract, returned by
+/// `State::storage_points_info`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StoragePointsInfo {
+    /// The storage points used as collateral for storage.
+    pub used: U256,
+    /// The storage points which are available for collateral.
+    pub unused: U256,
+    /// The tokens used as collateral for storage.
+    pub token_collateral: U256,
+    /// Whether CIP-107 is initialized for the contract.
+    pub cip107_initialized: bool,
+}
+
+/// The parameters set by
+/// `State::initialize_or_update_dao_voted_params_detailed`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DaoVotedParams {
+    pub interest_rate_per_block: U256,
+    pub pow_base_reward: U256,
+    /// `None` if the storage point proportion is not set, i.e. before CIP-107
+    /// is enabled.
+    pub storage_point_prop: Option<U256>,
+}
+
+/// The aggregate of the rewards of a PoS interest distribution, see
+/// `State::distribute_pos_interest_with_summary`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PosDistributionSummary {
+    /// The interest credited to the rewarded accounts.
+    pub total: U256,
+    /// Number of rewarded PoS identifiers.
+    pub recipients: usize,
+    /// The largest reward, zero if there is no recipient.
+    pub max: U256,
+    /// The smallest reward, zero if there is no recipient.
+    pub min: U256,
+}
+
+impl PosDistributionSummary {
+    pub fn from_rewards(account_rewards: &[(Address, H256, U256)]) -> Self {
+        let rewards = account_rewards.iter().map(|(_, _, reward)| *reward);
+        PosDistributionSummary {
+            total: rewards.clone().fold(U256::zero(), |a, b| a + b),
+            recipients: account_rewards.len(),
+            max: rewards.clone().max().unwrap_or_default(),
+            min: rewards.min().unwrap_or_default(),
+        }
+    }
+
+    /// The average reward rounded down, zero if there is no recipient.
+    pub fn average(&self) -> U256 {
+        if self.recipients == 0 {
+            U256::zero()
+        } else {
+            self.total / U256::from(self.recipients)
+        }
+    }
+}
+
+// Counters behind `CacheStats`. They are updated whenever an entry is added
+// to, removed from or changed in the cache, always under the cache write lock,
+// and are atomic only because accounts are loaded through `&State`.
+#[derive(Default)]
+struct CacheCounters {
+    entries: AtomicUsize,
+    dirty_entries: AtomicUsize,
+    code_bytes: AtomicUsize,
+}
+
+impl CacheCounters {
+    fn add(&self, entry: &AccountEntry) {
+        self.entries.fetch_add(1, Ordering::Relaxed);
+        if entry.is_dirty() {
+            self.dirty_entries.fetch_add(1, Ordering::Relaxed);
+        }
+        self.code_bytes
+            .fetch_add(Self::code_bytes_of(entry), Ordering::Relaxed);
+    }
+
+    fn remove(&self, entry: &AccountEntry) {
+        self.entries.fetch_sub(1, Ordering::Relaxed);
+        if entry.is_dirty() {
+            self.dirty_entries.fetch_sub(1, Ordering::Relaxed);
+        }
+        self.code_bytes
+            .fetch_sub(Self::code_bytes_of(entry), Ordering::Relaxed);
+    }
+
+    /// Called before `entry` is marked dirty.
+    fn mark_dirty(&self, entry: &AccountEntry) {
+        if !entry.is_dirty() {
+            self.dirty_entries.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn update_code_bytes(&self, before: usize, after: usize) {
+        if after >= before {
+            self.code_bytes.fetch_add(after - before, Ordering::Relaxed);
+        } else {
+            self.code_bytes.fetch_sub(before - after, Ordering::Relaxed);
+        }
+    }
+
+    fn reset(&self) {
+        self.entries.store(0, Ordering::Relaxed);
+        self.dirty_entries.store(0, Ordering::Relaxed);
+        self.code_bytes.store(0, Ordering::Relaxed);
+    }
+
+    fn code_bytes_of(entry: &AccountEntry) -> usize {
+        entry
+            .account
+            .as_ref()
+            .map_or(0, OverlayAccount::cached_code_size)
+    }
+}
+
+// Recency of the clean entries of `State::cache`, only maintained when a byte
+// budget is set by `State::set_cache_byte_budget`.
+#[derive(Default)]
+struct CacheLru {
+    next_tick: u64,
+    // The last access tick and the estimated size of each tracked entry.
+    entries: HashMap<AddressWithSpace, (u64, usize)>,
+    order: BTreeMap<u64, AddressWithSpace>,
+    bytes: usize,
+}
+
+impl CacheLru {
+    fn touch(&mut self, address: &AddressWithSpace, bytes: usize) {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        self.untrack(address);
+        self.entries.insert(*address, (tick, bytes));
+        self.order.insert(tick, *address);
+        self.bytes += bytes;
+    }
+
+    fn untrack(&mut self, address: &AddressWithSpace) {
+        if let Some((tick, bytes)) = self.entries.remove(address) {
+            self.order.remove(&tick);
+            self.bytes -= bytes;
+        }
+    }
+
+    fn pop_oldest(&mut self) -> Option<(AddressWithSpace, usize)> {
+        let (_, address) = self.order.pop_first()?;
+        let (_, bytes) = self.entries.remove(&address)?;
+        self.bytes -= bytes;
+        Some((address, bytes))
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.bytes = 0;
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 struct WorldStatistics {
     // This is the total number of CFX issued.
@@ -124,24 +484,94 @@ struct WorldStatistics {
     used_storage_points: U256,
     // This is the amount of converted storage points (in terms of Drip)
     converted_storage_points: U256,
+    // This is the number of blocks after the last distribution in which PoS
+    // interest accumulates. It is taken from the spec and not persisted.
+    distribute_window_blocks: u64,
+}
+
+impl Encodable for WorldStatistics {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(11)
+            .append(&self.total_issued_tokens)
+            .append(&self.total_staking_tokens)
+            .append(&self.total_storage_tokens)
+            .append(&self.interest_rate_per_block)
+            .append(&self.accumulate_interest_rate)
+            .append(&self.total_pos_staking_tokens)
+            .append(&self.distributable_pos_interest)
+            .append(&self.last_distribute_block)
+            .append(&self.total_evm_tokens)
+            .append(&self.used_storage_points)
+            .append(&self.converted_storage_points);
+    }
 }
 
 pub struct State {
     db: StateDb,
 
-    // Only created once for txpool notification.
+    // The accounts committed by the last `commit`, for txpool notification.
     // Each element is an Ok(Account) for updated account, or
     // Err(AddressWithSpace) for deleted account.
     accounts_to_notify: Vec<Result<Account, AddressWithSpace>>,
+    // The same for the accounts written by `compute_state_root` since the
+    // last commit, moved to `accounts_to_notify` on commit.
+    uncommitted_notifications: Vec<Result<Account, AddressWithSpace>>,
+    // Accounts removed by the last `compute_state_root`, kept until taken by
+    // `take_killed_addresses`.
+    killed_addresses: Vec<AddressWithSpace>,
+    // Accounts removed by `compute_state_root` since the last `commit`.
+    uncommitted_kills: Vec<AddressWithSpace>,
+    // Accounts removed by the last `commit`, sorted by address.
+    last_committed_kills: Vec<AddressWithSpace>,
 
     // Contains the changes to the states and some unchanged state entries.
     cache: RwLock<HashMap<AddressWithSpace, AccountEntry>>,
+    // Sizes of `cache`, maintained along with it.
+    cache_counters: CacheCounters,
+    // Byte budget of the clean entries in `cache`, unlimited if `None`.
+    cache_byte_budget: Option<usize>,
+    cache_lru: Mutex<CacheLru>,
     // TODO: try not to make it special?
     world_statistics: WorldStatistics,
 
     // Checkpoint to the changes.
     world_statistics_checkpoints: RwLock<Vec<WorldStatistics>>,
     checkpoints: RwLock<Vec<HashMap<AddressWithSpace, Option<AccountEntry>>>>,
+
+    // Optional hook for tracing the checkpoint tree, only used by tooling.
+    checkpoint_observer: Option<CheckpointObserver>,
+
+    // Credits to accounts which increased `total_issued_tokens` since the last
+    // commit, in the order they are made.
+    issuance_ledger: Vec<(AddressWithSpace, U256)>,
+    // The length of `issuance_ledger` at the creation of each checkpoint.
+    issuance_ledger_checkpoints: Vec<usize>,
+
+    // Addresses confirmed to have no code since the last commit. Only the
+    // negative result is cached, so it is removed once code is initialized.
+    known_no_code: RwLock<HashSet<AddressWithSpace>>,
+
+    // The value of `storage_point_prop` in the system storage, cleared when
+    // the system storage is written or a checkpoint is reverted.
+    storage_point_prop_cache: RwLock<Option<U256>>,
+
+    // The total balance of `GENESIS_LOCK_ADDRESSES`, cleared when either
+    // account is modified or a checkpoint is reverted.
+    genesis_locked_tokens_cache: RwLock<Option<U256>>,
+
+    // Whether `compute_state_root` checks that the balance changes of the
+    // eSpace accounts match the change of `total_evm_tokens`.
+    check_evm_tokens: bool,
+
+    // The world statistics as of the last commit, and their changes made by
+    // the last committed epoch.
+    committed_world_statistics: WorldStatistics,
+    last_statistics_delta: Option<WorldStatisticsDelta>,
+
+    // Whether an underflow of the world statistics panics instead of
+    // returning an error, and `compute_state_root` checks that the staking
+    // balance changes match the change of `total_staking_tokens`.
+    strict_world_statistics: bool,
 }
 
 impl State {
@@ -173,25 +603,41 @@ impl State {
     /// Charge and refund all the storage collaterals.
     /// The suicided addresses are skimmed because their collateral have been
     /// checked out. This function should only be called in post-processing
-    /// of a transaction.
+    /// of a transaction. A failed settlement leaves no partial charge, in the
+    /// state or in the traces.
     pub fn settle_collateral_for_all(
-        &mut self, substate: &Substate, tracer: &mut dyn StateTracer,
+        &mut self, substate: &mut Substate, tracer: &mut dyn StateTracer,
         spec: &Spec, dry_run_no_charge: bool,
     ) -> DbResult<CollateralCheckResult>
     {
-        for address in substate.keys_for_collateral_changed().iter() {
-            match self.settle_collateral_for_address(
+        let addresses: Vec<Address> = substate
+            .keys_for_collateral_changed()
+            .into_iter()
+            .cloned()
+            .collect();
+        let mut scope = self.checkpoint_scope();
+        tracer.checkpoint();
+        let mut result = Ok(CollateralCheckResult::Valid);
+        for address in addresses.iter() {
+            result = scope.settle_collateral_for_address(
                 &address,
                 substate,
                 tracer,
                 spec,
                 dry_run_no_charge,
-            )? {
-                CollateralCheckResult::Valid => {}
-                res => return Ok(res),
+            );
+            if !matches!(result, Ok(CollateralCheckResult::Valid)) {
+                break;
             }
         }
-        Ok(CollateralCheckResult::Valid)
+        if matches!(result, Ok(CollateralCheckResult::Valid)) {
+            tracer.discard_checkpoint();
+            scope.commit()?;
+        } else {
+            // The state is reverted when the scope is dropped.
+            tracer.revert_to_checkpoint();
+        }
+        result
     }
 
     // TODO: This function can only be called after VM execution. There are some
@@ -219,6 +665,23 @@ impl State {
         Ok(res)
     }
 
+    /// Same as `collect_and_settle_collateral` without a tracer, for
+    /// simulations.
+    pub fn collect_and_settle_collateral_untraced(
+        &mut self, original_sender: &Address, storage_limit: &U256,
+        substate: &mut Substate, spec: &Spec, dry_run_no_charge: bool,
+    ) -> DbResult<CollateralCheckResult>
+    {
+        self.collect_and_settle_collateral(
+            original_sender,
+            storage_limit,
+            substate,
+            &mut (),
+            spec,
+            dry_run_no_charge,
+        )
+    }
+
     pub fn record_storage_and_whitelist_entries_release(
         &mut self, address: &Address, substate: &mut Substate,
     ) -> DbResult<()> {
@@ -299,7 +762,18 @@ impl State {
 
         let mut sorted_dirty_accounts =
             self.cache.get_mut().drain().collect::<Vec<_>>();
+        self.cache_counters.reset();
+        self.cache_lru.get_mut().clear();
         sorted_dirty_accounts.sort_by(|a, b| a.0.cmp(&b.0));
+        if self.check_evm_tokens {
+            self.reconcile_evm_tokens(&sorted_dirty_accounts)?;
+        }
+        #[cfg(any(test, feature = "testonly_code"))]
+        let recomputed_staking_tokens = if self.strict_world_statistics {
+            Some(self.recompute_total_staking_tokens(&sorted_dirty_accounts)?)
+        } else {
+            None
+        };
 
         let mut killed_addresses = Vec::new();
         for (address, entry) in sorted_dirty_accounts.iter_mut() {
@@ -308,7 +782,7 @@ impl State {
                 None => {}
                 Some(account) if account.removed_without_update() => {
                     killed_addresses.push(*address);
-                    self.accounts_to_notify.push(Err(*address));
+                    self.uncommitted_notifications.push(Err(*address));
                 }
                 Some(account) => {
                     account.commit(
@@ -316,22 +790,220 @@ impl State {
                         address,
                         debug_record.as_deref_mut(),
                     )?;
-                    self.accounts_to_notify.push(Ok(account.as_account()));
+                    self.uncommitted_notifications
+                        .push(Ok(account.as_account()));
                 }
             }
         }
+        self.killed_addresses.extend_from_slice(&killed_addresses);
+        self.uncommitted_kills.extend_from_slice(&killed_addresses);
         self.recycle_storage(killed_addresses, debug_record.as_deref_mut())?;
+        #[cfg(any(test, feature = "testonly_code"))]
+        if let Some(recomputed) = recomputed_staking_tokens {
+            assert_eq!(
+                self.world_statistics.total_staking_tokens, recomputed,
+                "total_staking_tokens mismatches the staking balances"
+            );
+        }
         self.commit_world_statistics(debug_record.as_deref_mut())?;
+        self.issuance_ledger.clear();
+        self.known_no_code.get_mut().clear();
+        self.storage_point_prop_cache.get_mut().take();
         self.db.compute_state_root(debug_record)
     }
 
+    /// Computes the state root which the next `commit` yields if nothing is
+    /// changed before, without committing. The dirty accounts, the world
+    /// statistics and the notifications of the next commit are restored
+    /// afterwards, so the state can still be changed and committed.
+    ///
+    /// This is more expensive than the real commit: the dirty accounts are
+    /// cloned, and their changes are written into the db layer here and
+    /// again by the following commit.
+    pub fn compute_state_root_preview(
+        &mut self,
+    ) -> DbResult<StateRootWithAuxInfo> {
+        let dirty_accounts = self
+            .cache
+            .get_mut()
+            .iter()
+            .filter(|(_, entry)| entry.is_dirty())
+            .map(|(address, entry)| (*address, entry.clone_owned()))
+            .collect::<Vec<_>>();
+        let world_statistics = self.world_statistics;
+        let issuance_ledger = self.issuance_ledger.clone();
+        let notifications_len = self.uncommitted_notifications.len();
+        let uncommitted_kills_len = self.uncommitted_kills.len();
+        let killed_addresses_len = self.killed_addresses.len();
+
+        let result = self.compute_state_root(/* debug_record = */ None);
+
+        self.world_statistics = world_statistics;
+        self.issuance_ledger = issuance_ledger;
+        self.uncommitted_notifications.truncate(notifications_len);
+        self.uncommitted_kills.truncate(uncommitted_kills_len);
+        self.killed_addresses.truncate(killed_addresses_len);
+        for (address, entry) in dirty_accounts {
+            Self::update_cache(
+                self.cache.get_mut(),
+                &self.cache_counters,
+                self.checkpoints.get_mut(),
+                &address,
+                entry,
+            );
+        }
+        result
+    }
+
+    /// Enable or disable the check in `compute_state_root` that the net
+    /// balance change of the committed eSpace accounts equals the change of
+    /// `total_evm_tokens` since the last commit.
+    #[cfg(any(test, feature = "testonly_code"))]
+    pub fn set_check_evm_tokens(&mut self, enabled: bool) {
+        self.check_evm_tokens = enabled;
+    }
+
+    fn reconcile_evm_tokens(
+        &self, dirty_accounts: &[(AddressWithSpace, AccountEntry)],
+    ) -> DbResult<()> {
+        let mut balances_before = U256::zero();
+        let mut balances_after = U256::zero();
+        for (address, entry) in dirty_accounts {
+            if address.space != Space::Ethereum {
+                continue;
+            }
+            // An entry without account is not written.
+            if let Some(account) = &entry.account {
+                balances_before += self
+                    .db
+                    .get_account(address)?
+                    .map_or(U256::zero(), |acc| acc.balance);
+                balances_after += *account.balance();
+            }
+        }
+        let tokens_before = self.db.get_total_evm_tokens()?;
+        let tokens_after = self.world_statistics.total_evm_tokens;
+        if tokens_after + balances_before != tokens_before + balances_after {
+            bail!(DbErrorKind::EvmTokensMismatch(
+                balances_before,
+                balances_after,
+                tokens_before,
+                tokens_after
+            ));
+        }
+        Ok(())
+    }
+
+    /// Make an underflow of the world statistics panic instead of returning
+    /// an error, and check in `compute_state_root` that the net staking
+    /// balance change of the committed accounts equals the change of
+    /// `total_staking_tokens` since the last commit.
+    #[cfg(any(test, feature = "testonly_code"))]
+    pub fn set_strict_world_statistics(&mut self, enabled: bool) {
+        self.strict_world_statistics = enabled;
+    }
+
+    #[cfg(any(test, feature = "testonly_code"))]
+    fn recompute_total_staking_tokens(
+        &self, dirty_accounts: &[(AddressWithSpace, AccountEntry)],
+    ) -> DbResult<U256> {
+        let mut staking_before = U256::zero();
+        let mut staking_after = U256::zero();
+        for (address, entry) in dirty_accounts {
+            if let Some(account) = &entry.account {
+                staking_before += self
+                    .db
+                    .get_account(address)?
+                    .map_or(U256::zero(), |acc| acc.staking_balance);
+                staking_after += *account.staking_balance();
+            }
+        }
+        Ok(self.db.get_total_staking_tokens()? + staking_after
+            - staking_before)
+    }
+
+    /// Asserts that `accounts_to_notify` holds each account committed by the
+    /// last commit once, and that it matches the committed value.
+    #[cfg(test)]
+    fn assert_accounts_to_notify_committed(&self) -> DbResult<()> {
+        let mut addresses = HashSet::new();
+        for updated_or_deleted in &self.accounts_to_notify {
+            match updated_or_deleted {
+                Ok(account) => {
+                    assert!(addresses.insert(*account.address()));
+                    let committed = self
+                        .db
+                        .get_account(account.address())?
+                        .expect("updated account is committed");
+                    assert_eq!(committed.balance, account.balance);
+                    assert_eq!(committed.nonce, account.nonce);
+                    assert_eq!(committed.code_hash, account.code_hash);
+                }
+                Err(address) => {
+                    assert!(addresses.insert(*address));
+                    assert!(self.db.get_account(address)?.is_none());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the changes of the world statistics made by the epoch of the
+    /// last `commit`, `None` before the first commit.
+    pub fn last_statistics_delta(&self) -> Option<&WorldStatisticsDelta> {
+        self.last_statistics_delta.as_ref()
+    }
+
+    /// Returns the changes of the world statistics from `base` to this
+    /// state, e.g. from the state of the parent epoch.
+    pub fn statistics_delta_since(&self, base: &State) -> WorldStatisticsDelta {
+        WorldStatisticsDelta::new(
+            &base.world_statistics,
+            &self.world_statistics,
+        )
+    }
+
+    /// Returns the accounts removed by `commit` or `compute_state_root`,
+    /// sorted by address, and clears the list.
+    pub fn take_killed_addresses(&mut self) -> Vec<AddressWithSpace> {
+        std::mem::take(&mut self.killed_addresses)
+    }
+
+    /// Returns the accounts removed by the last `commit`, sorted by address.
+    /// Unlike `take_killed_addresses`, the list is kept until the next
+    /// commit.
+    pub fn last_committed_kills(&self) -> &[AddressWithSpace] {
+        &self.last_committed_kills
+    }
+
     pub fn commit(
         &mut self, epoch_id: EpochId,
         mut debug_record: Option<&mut ComputeEpochDebugRecord>,
     ) -> DbResult<StateRootWithAuxInfo>
     {
         debug!("Commit epoch[{}]", epoch_id);
+        self.assert_no_open_checkpoints();
+        let cache_stats = self.cache_stats();
+        STATE_CACHE_ENTRIES_GAUGE.update(cache_stats.entries);
+        STATE_CACHE_DIRTY_ENTRIES_GAUGE.update(cache_stats.dirty_entries);
+        STATE_CACHE_CODE_BYTES_GAUGE.update(cache_stats.code_bytes);
         self.compute_state_root(debug_record.as_deref_mut())?;
+        debug_assert!(
+            !self.has_dirty_entries(),
+            "dirty account entries remain after computing state root"
+        );
+        self.last_committed_kills = std::mem::take(&mut self.uncommitted_kills);
+        self.accounts_to_notify =
+            std::mem::take(&mut self.uncommitted_notifications);
+        #[cfg(test)]
+        self.assert_accounts_to_notify_committed()?;
+        let delta = WorldStatisticsDelta::new(
+            &self.committed_world_statistics,
+            &self.world_statistics,
+        );
+        delta.report_metrics();
+        self.last_statistics_delta = Some(delta);
+        self.committed_world_statistics = self.world_statistics;
         Ok(self.db.commit(epoch_id, debug_record)?)
     }
 }
@@ -357,19 +1029,71 @@ impl State {
         secondary_reward
     }
 
+    /// Return the secondary reward for the next block number together with
+    /// the remainder truncated by the division. The first value always
+    /// equals `secondary_reward()`, and the remainder (in units of
+    /// `1 / INTEREST_RATE_PER_BLOCK_SCALE` Drip) allows the caller to carry
+    /// the fractional part forward.
+    pub fn secondary_reward_with_remainder(&self) -> (U256, U256) {
+        assert!(self.world_statistics_checkpoints.read().is_empty());
+        (self.world_statistics.total_storage_tokens
+            * self.world_statistics.interest_rate_per_block)
+            .div_mod(*INTEREST_RATE_PER_BLOCK_SCALE)
+    }
+
+    /// The PoW base reward plus the secondary reward, computed from the
+    /// current world statistics. The base reward is zero before it is
+    /// initialized in the db.
+    pub fn total_block_reward(&self) -> DbResult<U256> {
+        let pow_base_reward = self.pow_base_reward_opt()?.unwrap_or_default();
+        Ok(pow_base_reward + self.secondary_reward())
+    }
+
+    /// Whether the world statistics have been written to the db, i.e. the
+    /// state is not a fresh genesis state.
+    pub fn is_db_initialized(&self) -> DbResult<bool> {
+        self.db.is_initialized()
+    }
+
+    /// Panics if the base reward is not initialized, use
+    /// `pow_base_reward_opt` if that is possible.
     pub fn pow_base_reward(&self) -> U256 {
-        self.db
-            .get_pow_base_reward()
+        self.pow_base_reward_opt()
             .expect("no db error")
             .expect("initialized")
     }
 
+    /// The PoW base reward, `None` before it is initialized in the db.
+    pub fn pow_base_reward_opt(&self) -> DbResult<Option<U256>> {
+        self.db.get_pow_base_reward()
+    }
+
     /// Maintain `total_issued_tokens`.
     pub fn add_total_issued(&mut self, v: U256) {
         assert!(self.world_statistics_checkpoints.get_mut().is_empty());
         self.world_statistics.total_issued_tokens += v;
     }
 
+    /// Return the total amount credited to each account which increased
+    /// `total_issued_tokens` since the last commit, sorted by address. The
+    /// issuance not attributed to an account through `add_total_issued`
+    /// is not included.
+    pub fn issuance_ledger(&self) -> Vec<(AddressWithSpace, U256)> {
+        let mut ledger: HashMap<AddressWithSpace, U256> = HashMap::new();
+        for (address, v) in &self.issuance_ledger {
+            *ledger.entry(*address).or_default() += *v;
+        }
+        let mut ledger = ledger.into_iter().collect::<Vec<_>>();
+        ledger.sort_by(|a, b| a.0.cmp(&b.0));
+        ledger
+    }
+
+    fn record_issuance(&mut self, address: &AddressWithSpace, v: &U256) {
+        if !v.is_zero() {
+            self.issuance_ledger.push((*address, *v));
+        }
+    }
+
     /// Maintain `total_issued_tokens`. This is only used in the extremely
     /// unlikely case that there are a lot of partial invalid blocks.
     pub fn subtract_total_issued(&mut self, v: U256) {
@@ -394,13 +1118,16 @@ impl State {
         }
     }
 
+    /// Accumulate the PoS interest of a block, unless `current_block_number`
+    /// is more than the distribution window after the last distribution.
     pub fn inc_distributable_pos_interest(
         &mut self, current_block_number: u64,
     ) -> DbResult<()> {
         assert!(self.world_statistics_checkpoints.get_mut().is_empty());
 
         if current_block_number
-            > self.world_statistics.last_distribute_block + BLOCKS_PER_HOUR
+            > self.world_statistics.last_distribute_block
+                + self.world_statistics.distribute_window_blocks
         {
             return Ok(());
         }
@@ -409,23 +1136,12 @@ impl State {
             return Ok(());
         }
 
-        let total_circulating_tokens = self.total_issued_tokens()
-            - self.balance(&Address::zero().with_native_space())?
-            - self.balance(&genesis_contract_address_four_year())?
-            - self.balance(&genesis_contract_address_two_year())?;
-        let total_pos_staking_tokens =
-            self.world_statistics.total_pos_staking_tokens;
-
-        // The `interest_amount` exactly equals to the floor of
-        // pos_amount * 4% / blocks_per_year / sqrt(pos_amount/total_issued)
-        let interest_amount = sqrt_u256(
-            total_circulating_tokens
-                * total_pos_staking_tokens
-                * self.world_statistics.interest_rate_per_block
-                * self.world_statistics.interest_rate_per_block,
-        ) / (BLOCKS_PER_YEAR
-            * INVERSE_INTEREST_RATE
-            * INITIAL_INTEREST_RATE_PER_BLOCK.as_u64());
+        let interest_amount = pos_interest_increment(
+            self.total_circulating_tokens()?,
+            self.world_statistics.total_pos_staking_tokens,
+            self.world_statistics.interest_rate_per_block,
+            BLOCKS_PER_YEAR,
+        );
         self.world_statistics.distributable_pos_interest += interest_amount;
 
         Ok(())
@@ -433,26 +1149,51 @@ impl State {
 
     /// Distribute PoS interest to the PoS committee according to their reward
     /// points. Return the rewarded PoW accounts and their rewarded
-    /// interest.
+    /// interest, together with the total interest forfeited by the
+    /// identifiers which resolve to the zero address.
+    ///
+    /// Before `skip_unregistered` is enabled, the zero address is rewarded
+    /// like any other account and nothing is forfeited. After it, the
+    /// forfeited interest is not issued and stays in
+    /// `distributable_pos_interest` for the next distribution.
     pub fn distribute_pos_interest<'a>(
         &mut self, pos_points: Box<dyn Iterator<Item = (&'a H256, u64)> + 'a>,
-        current_block_number: u64,
-    ) -> DbResult<Vec<(Address, H256, U256)>>
+        current_block_number: u64, skip_unregistered: bool,
+    ) -> DbResult<(Vec<(Address, H256, U256)>, U256)>
     {
         assert!(self.world_statistics_checkpoints.get_mut().is_empty());
 
         let distributable_pos_interest =
             self.world_statistics.distributable_pos_interest;
 
+        let pos_points: Vec<_> = pos_points.collect();
+        let address_entries: Vec<_> = pos_points
+            .iter()
+            .map(|(identifier, _)| {
+                pos_internal_entries::address_entry(identifier)
+            })
+            .collect();
+        let address_values = self.storage_at_many(
+            &POS_REGISTER_CONTRACT_ADDRESS.with_native_space(),
+            &address_entries
+                .iter()
+                .map(|key| key.as_slice())
+                .collect::<Vec<_>>(),
+        )?;
+
         let mut account_rewards = Vec::new();
-        for (identifier, points) in pos_points {
-            let address_value = self.storage_at(
-                &POS_REGISTER_CONTRACT_ADDRESS.with_native_space(),
-                &pos_internal_entries::address_entry(&identifier),
-            )?;
+        let mut forfeited = U256::zero();
+        for ((identifier, points), address_value) in
+            pos_points.into_iter().zip(address_values)
+        {
             let address = Address::from(H256::from_uint(&address_value));
             let interest =
                 distributable_pos_interest * points / MAX_TERM_POINTS;
+            if skip_unregistered && address.is_zero() {
+                // The identifier is not registered properly.
+                forfeited += interest;
+                continue;
+            }
             account_rewards.push((address, *identifier, interest));
             self.add_pos_interest(
                 &address,
@@ -461,10 +1202,25 @@ impl State {
                                            * reward. */
             )?;
         }
-        self.world_statistics.distributable_pos_interest = U256::zero();
+        self.world_statistics.distributable_pos_interest = forfeited;
         self.world_statistics.last_distribute_block = current_block_number;
 
-        Ok(account_rewards)
+        Ok((account_rewards, forfeited))
+    }
+
+    /// Same as `distribute_pos_interest`, with the summary of the rewards.
+    pub fn distribute_pos_interest_with_summary<'a>(
+        &mut self, pos_points: Box<dyn Iterator<Item = (&'a H256, u64)> + 'a>,
+        current_block_number: u64, skip_unregistered: bool,
+    ) -> DbResult<(Vec<(Address, H256, U256)>, U256, PosDistributionSummary)>
+    {
+        let (account_rewards, forfeited) = self.distribute_pos_interest(
+            pos_points,
+            current_block_number,
+            skip_unregistered,
+        )?;
+        let summary = PosDistributionSummary::from_rewards(&account_rewards);
+        Ok((account_rewards, forfeited, summary))
     }
 
     pub fn new_contract_with_admin(
@@ -478,8 +1234,10 @@ impl State {
         let invalidated_storage = self
             .read_account(contract)?
             .map_or(false, |overlay| overlay.invalidated_storage());
+        self.invalidate_genesis_locked_cache(contract);
         Self::update_cache(
             self.cache.get_mut(),
+            &self.cache_counters,
             self.checkpoints.get_mut(),
             contract,
             AccountEntry::new_dirty(Some(
@@ -501,6 +1259,61 @@ impl State {
         Ok(*acc.balance())
     }
 
+    /// Return the balances of `addresses` in the input order. The cache lock
+    /// is taken once for the cached accounts, and the other accounts are
+    /// loaded from db and inserted into the cache together. A missing
+    /// account has zero balance.
+    pub fn balances(
+        &self, addresses: &[AddressWithSpace],
+    ) -> DbResult<Vec<U256>> {
+        let mut balances = vec![U256::zero(); addresses.len()];
+        let mut uncached = Vec::new();
+        {
+            let cache = self.cache.read();
+            for (index, address) in addresses.iter().enumerate() {
+                match cache.get(address) {
+                    Some(entry) => {
+                        if let Some(account) = &entry.account {
+                            balances[index] = *account.balance();
+                        }
+                    }
+                    None => uncached.push(index),
+                }
+            }
+        }
+        if uncached.is_empty() {
+            return Ok(balances);
+        }
+
+        let mut loaded = HashMap::new();
+        for index in uncached {
+            let address = &addresses[index];
+            if !loaded.contains_key(address) {
+                let maybe_account = self
+                    .db
+                    .get_account(address)?
+                    .map(|acc| OverlayAccount::from_loaded(address, acc));
+                loaded.insert(*address, maybe_account);
+            }
+            if let Some(account) = &loaded[address] {
+                balances[index] = *account.balance();
+            }
+        }
+        let mut cache = self.cache.write();
+        for (address, maybe_account) in loaded {
+            if Self::insert_cache_if_fresh_account(
+                &mut *cache,
+                &self.cache_counters,
+                &address,
+                maybe_account,
+            ) {
+                self.touch_cache_entry(&address, &cache[&address]);
+            }
+        }
+        self.evict_cache_entries(&mut *cache, None);
+        Ok(balances)
+    }
+
     pub fn is_contract_with_code(
         &self, address: &AddressWithSpace,
     ) -> DbResult<bool> {
@@ -509,9 +1322,16 @@ impl State {
         {
             return Ok(false);
         }
+        if self.known_no_code.read().contains(address) {
+            return Ok(false);
+        }
 
         let acc = try_loaded!(self.read_account(address));
-        Ok(acc.code_hash() != KECCAK_EMPTY)
+        let has_code = acc.code_hash() != KECCAK_EMPTY;
+        if !has_code {
+            self.known_no_code.write().insert(*address);
+        }
+        Ok(has_code)
     }
 
     pub fn sponsor_for_gas(
@@ -562,34 +1382,154 @@ impl State {
         } else {
             U256::zero()
         };
-        let converted_storage_points = self
+        let (converted_storage_points, unused_points_change) = self
             .require_exists(&address.with_native_space(), false)
             .map(|mut x| {
-                x.set_sponsor_for_collateral(sponsor, sponsor_balance, prop)
+                let unused_points = |x: &OverlayAccount| {
+                    x.sponsor_info()
+                        .storage_points
+                        .as_ref()
+                        .map_or(U256::zero(), |points| points.unused)
+                };
+                let unused_before = unused_points(&x);
+                let converted = x.set_sponsor_for_collateral(
+                    sponsor,
+                    sponsor_balance,
+                    prop,
+                );
+                (converted, unused_points(&x) - unused_before)
             })?;
+        // The global counter must grow by the points the contract received.
+        debug_assert_eq!(
+            converted_storage_points, unused_points_change,
+            "converted storage points of {:?} diverge from the counter",
+            address
+        );
         self.world_statistics.total_issued_tokens -= converted_storage_points;
         self.world_statistics.converted_storage_points +=
             converted_storage_points;
         Ok(converted_storage_points)
     }
 
-    pub fn sponsor_info(
-        &self, address: &Address,
-    ) -> DbResult<Option<SponsorInfo>> {
-        let acc = try_loaded!(self.read_native_account(address));
-        Ok(Some(acc.sponsor_info().clone()))
-    }
-
-    pub fn sponsor_gas_bound(&self, address: &Address) -> DbResult<U256> {
-        let acc = try_loaded!(self.read_native_account(address));
-        Ok(acc.sponsor_info().sponsor_gas_bound)
-    }
+    /// Set `new_sponsor` as both the gas and the collateral sponsor of
+    /// `contract` in one account update. The previous sponsor balances are
+    /// refunded to the previous sponsors, and the caller is responsible for
+    /// charging the new balances. Like `set_sponsor_for_collateral`, a part
+    /// of the collateral balance may be converted to storage points.
+    pub fn replace_sponsor(
+        &mut self, contract: &Address, new_sponsor: &Address,
+        gas_balance: &U256, gas_bound: &U256, collateral_balance: &U256,
+        is_cip107: bool, cleanup_mode: &mut CleanupMode,
+    ) -> DbResult<SponsorReplaceOutcome>
+    {
+        let prop = if is_cip107 {
+            self.storage_point_prop()?
+        } else {
+            U256::zero()
+        };
+        let (prev_sponsor_info, converted_storage_points) = self
+            .require_exists(&contract.with_native_space(), false)
+            .map(|mut x| {
+                let prev_sponsor_info = x.sponsor_info().clone();
+                x.set_sponsor_for_gas(new_sponsor, gas_balance, gas_bound);
+                // The previous collateral balance is refunded, so the whole
+                // new balance counts for the conversion.
+                x.set_sponsor_for_collateral(
+                    &Address::zero(),
+                    &U256::zero(),
+                    prop,
+                );
+                let converted = x.set_sponsor_for_collateral(
+                    new_sponsor,
+                    collateral_balance,
+                    prop,
+                );
+                (prev_sponsor_info, converted)
+            })?;
+        self.world_statistics.total_issued_tokens -= converted_storage_points;
+        self.world_statistics.converted_storage_points +=
+            converted_storage_points;
 
-    pub fn sponsor_balance_for_gas(&self, address: &Address) -> DbResult<U256> {
+        let mut refunds = BTreeMap::new();
+        for (sponsor, balance) in &[
+            (
+                prev_sponsor_info.sponsor_for_gas,
+                prev_sponsor_info.sponsor_balance_for_gas,
+            ),
+            (
+                prev_sponsor_info.sponsor_for_collateral,
+                prev_sponsor_info.sponsor_balance_for_collateral,
+            ),
+        ] {
+            if !sponsor.is_zero() && !balance.is_zero() {
+                *refunds.entry(*sponsor).or_insert_with(U256::zero) +=
+                    *balance;
+            }
+        }
+        for (sponsor, balance) in &refunds {
+            let cleanup_mode = match cleanup_mode {
+                CleanupMode::ForceCreate => CleanupMode::ForceCreate,
+                CleanupMode::NoEmpty => CleanupMode::NoEmpty,
+                CleanupMode::TrackTouched(set) => {
+                    CleanupMode::TrackTouched(&mut **set)
+                }
+            };
+            self.add_balance(
+                &sponsor.with_native_space(),
+                balance,
+                cleanup_mode,
+            )?;
+        }
+        Ok(SponsorReplaceOutcome {
+            refunds: refunds.into_iter().collect(),
+            converted_storage_points,
+        })
+    }
+
+    pub fn sponsor_info(
+        &self, address: &Address,
+    ) -> DbResult<Option<SponsorInfo>> {
+        let acc = try_loaded!(self.read_native_account(address));
+        Ok(Some(acc.sponsor_info().clone()))
+    }
+
+    pub fn sponsor_gas_bound(&self, address: &Address) -> DbResult<U256> {
+        let acc = try_loaded!(self.read_native_account(address));
+        Ok(acc.sponsor_info().sponsor_gas_bound)
+    }
+
+    pub fn sponsor_balance_for_gas(&self, address: &Address) -> DbResult<U256> {
         let acc = try_loaded!(self.read_native_account(address));
         Ok(acc.sponsor_info().sponsor_balance_for_gas)
     }
 
+    /// Whether the gas sponsor of `contract` can pay for each of the gas fees
+    /// in `gas_costs`, in sequence. A covered fee is deducted from the
+    /// sponsor balance before checking the next one, and a fee above the
+    /// sponsor gas bound is never covered. The state is not modified.
+    pub fn sponsor_gas_coverage(
+        &self, contract: &Address, gas_costs: &[U256],
+    ) -> DbResult<Vec<bool>> {
+        let sponsor_info = match self.read_native_account(contract)? {
+            Some(acc) if !acc.sponsor_info().sponsor_for_gas.is_zero() => {
+                acc.sponsor_info().clone()
+            }
+            _ => return Ok(vec![false; gas_costs.len()]),
+        };
+        let mut balance = sponsor_info.sponsor_balance_for_gas;
+        Ok(gas_costs
+            .iter()
+            .map(|cost| {
+                let covered = *cost <= sponsor_info.sponsor_gas_bound
+                    && *cost <= balance;
+                if covered {
+                    balance -= *cost;
+                }
+                covered
+            })
+            .collect())
+    }
+
     pub fn sponsor_balance_for_collateral(
         &self, address: &Address,
     ) -> DbResult<U256> {
@@ -597,7 +1537,7 @@ impl State {
         Ok(acc.sponsor_info().sponsor_balance_for_collateral)
     }
 
-    pub fn avaliable_storage_point_for_collateral(
+    pub fn available_storage_point_for_collateral(
         &self, address: &Address,
     ) -> DbResult<U256> {
         let acc = try_loaded!(self.read_native_account(address));
@@ -609,6 +1549,36 @@ impl State {
             .unwrap_or_default())
     }
 
+    #[deprecated(note = "use `available_storage_point_for_collateral`")]
+    pub fn avaliable_storage_point_for_collateral(
+        &self, address: &Address,
+    ) -> DbResult<U256> {
+        self.available_storage_point_for_collateral(address)
+    }
+
+    /// Whether CIP-107 is initialized for `address`, `false` if it does not
+    /// exist.
+    pub fn is_cip107_initialized(&self, address: &Address) -> DbResult<bool> {
+        let acc = try_loaded!(self.read_native_account(address));
+        Ok(acc.is_cip_107_initialized())
+    }
+
+    /// The CIP-107 storage points and the token collateral of `contract`,
+    /// all zero if it does not exist.
+    pub fn storage_points_info(
+        &self, contract: &Address,
+    ) -> DbResult<StoragePointsInfo> {
+        let acc = try_loaded!(self.read_native_account(contract));
+        let storage_points = acc.sponsor_info().storage_points.as_ref();
+        let token_collateral = acc.token_collateral_for_storage();
+        Ok(StoragePointsInfo {
+            used: acc.collateral_for_storage() - token_collateral,
+            unused: storage_points.map_or(U256::zero(), |points| points.unused),
+            token_collateral,
+            cip107_initialized: storage_points.is_some(),
+        })
+    }
+
     pub fn set_admin(
         &mut self, contract_address: &Address, admin: &Address,
     ) -> DbResult<()> {
@@ -657,6 +1627,32 @@ impl State {
         Ok(())
     }
 
+    /// Move `amount` between the gas and the collateral sponsor balances of
+    /// `address`. Fails without changing the state if the source balance is
+    /// insufficient.
+    pub fn move_sponsor_balance(
+        &mut self, address: &Address, from_gas_to_collateral: bool,
+        amount: &U256,
+    ) -> DbResult<()>
+    {
+        let available = if from_gas_to_collateral {
+            self.sponsor_balance_for_gas(address)?
+        } else {
+            self.sponsor_balance_for_collateral(address)?
+        };
+        if available < *amount {
+            bail!(DbErrorKind::NotEnoughBalance(*address, *amount, available));
+        }
+        if from_gas_to_collateral {
+            self.sub_sponsor_balance_for_gas(address, amount)?;
+            self.add_sponsor_balance_for_collateral(address, amount)?;
+        } else {
+            self.sub_sponsor_balance_for_collateral(address, amount)?;
+            self.add_sponsor_balance_for_gas(address, amount)?;
+        }
+        Ok(())
+    }
+
     pub fn check_commission_privilege(
         &self, contract_address: &Address, user: &Address,
     ) -> DbResult<bool> {
@@ -699,6 +1695,105 @@ impl State {
         ))
     }
 
+    /// Return the sponsor whitelist entries changed in the current execution
+    /// as `(contract_address, user, added)`. An entry which is set back to
+    /// its value in db is not reported.
+    pub fn pending_whitelist_changes(
+        &self,
+    ) -> DbResult<Vec<(Address, Address, bool)>> {
+        let acc = match self
+            .read_native_account(&*SPONSOR_WHITELIST_CONTROL_CONTRACT_ADDRESS)?
+        {
+            Some(acc) => acc,
+            None => return Ok(vec![]),
+        };
+        let mut changes = Vec::new();
+        for (key, value) in acc.storage_value_write_cache().iter() {
+            if key.len() != Address::len_bytes() * 2 {
+                continue;
+            }
+            let original_value = self
+                .db
+                .get::<StorageValue>(
+                    StorageKey::new_storage_key(
+                        &SPONSOR_WHITELIST_CONTROL_CONTRACT_ADDRESS,
+                        key,
+                    )
+                    .with_native_space(),
+                )?
+                .map_or(U256::zero(), |v| v.value);
+            if original_value.is_zero() == value.is_zero() {
+                continue;
+            }
+            let (contract_address, user) = key.split_at(Address::len_bytes());
+            changes.push((
+                Address::from_slice(contract_address),
+                Address::from_slice(user),
+                !value.is_zero(),
+            ));
+        }
+        changes.sort();
+        Ok(changes)
+    }
+
+    /// Return the users whitelisted by `contract_address`, sorted and
+    /// including the uncommitted changes. If all users are whitelisted, the
+    /// list starts with `COMMISSION_PRIVILEGE_SPECIAL_KEY`.
+    pub fn commission_privilege_list(
+        &self, contract_address: &Address,
+    ) -> DbResult<Vec<Address>> {
+        let mut users = BTreeSet::new();
+        let key_prefix = StorageKey::new_storage_key(
+            &SPONSOR_WHITELIST_CONTROL_CONTRACT_ADDRESS,
+            contract_address.as_ref(),
+        )
+        .with_native_space();
+        let key_values = self.db.read_range(
+            key_prefix,
+            &key_prefix.to_key_bytes(),
+            /* limit = */ usize::MAX,
+        )?;
+        for (key, value) in &key_values {
+            if let StorageKeyWithSpace {
+                key: StorageKey::StorageKey { storage_key, .. },
+                ..
+            } =
+                StorageKeyWithSpace::from_key_bytes::<SkipInputCheck>(&key[..])
+            {
+                if storage_key.len() != Address::len_bytes() * 2 {
+                    continue;
+                }
+                let storage_value =
+                    rlp::decode::<StorageValue>(value.as_ref())?;
+                if !storage_value.value.is_zero() {
+                    users.insert(Address::from_slice(
+                        &storage_key[Address::len_bytes()..],
+                    ));
+                }
+            }
+        }
+
+        // Then apply the storage changes in cache.
+        if let Some(acc) = self
+            .read_native_account(&*SPONSOR_WHITELIST_CONTROL_CONTRACT_ADDRESS)?
+        {
+            for (key, value) in acc.storage_value_write_cache().iter() {
+                if key.len() != Address::len_bytes() * 2
+                    || !key.starts_with(contract_address.as_ref())
+                {
+                    continue;
+                }
+                let user = Address::from_slice(&key[Address::len_bytes()..]);
+                if value.is_zero() {
+                    users.remove(&user);
+                } else {
+                    users.insert(user);
+                }
+            }
+        }
+        Ok(users.into_iter().collect())
+    }
+
     // TODO: maybe return error for reserved address? Not sure where is the best
     //  place to do the check.
     pub fn nonce(&self, address: &AddressWithSpace) -> DbResult<U256> {
@@ -709,10 +1804,33 @@ impl State {
     pub fn init_code(
         &mut self, address: &AddressWithSpace, code: Bytes, owner: Address,
     ) -> DbResult<()> {
-        self.require_exists(address, false)?.init_code(code, owner);
+        self.known_no_code.get_mut().remove(address);
+        let mut account = self.require_exists(address, false)?;
+        let code_bytes = account.cached_code_size();
+        account.init_code(code, owner);
+        self.cache_counters
+            .update_code_bytes(code_bytes, account.cached_code_size());
         Ok(())
     }
 
+    /// Same as `init_code`, but fails with `CodeSizeExceeded` if the code is
+    /// longer than `create_data_limit` in `spec`, which is the code size
+    /// limit for both spaces.
+    pub fn init_code_checked(
+        &mut self, address: &AddressWithSpace, code: Bytes, owner: Address,
+        spec: &Spec,
+    ) -> DbResult<()>
+    {
+        if code.len() > spec.create_data_limit {
+            bail!(DbErrorKind::CodeSizeExceeded(
+                address.address,
+                code.len(),
+                spec.create_data_limit
+            ));
+        }
+        self.init_code(address, code, owner)
+    }
+
     pub fn code_hash(
         &self, address: &AddressWithSpace,
     ) -> DbResult<Option<H256>> {
@@ -720,6 +1838,56 @@ impl State {
         Ok(Some(acc.code_hash()))
     }
 
+    /// Returns the code hash of each of `addresses`, `None` if the account
+    /// does not exist. The accounts not cached yet are loaded with their code
+    /// and inserted into the cache under a single write lock.
+    pub fn code_hashes(
+        &self, addresses: &[AddressWithSpace],
+    ) -> DbResult<Vec<Option<H256>>> {
+        let uncached = {
+            let cache = self.cache.read();
+            addresses
+                .iter()
+                .filter(|address| !cache.contains_key(address))
+                .cloned()
+                .collect::<BTreeSet<_>>()
+        };
+        let mut loaded = Vec::with_capacity(uncached.len());
+        for address in &uncached {
+            loaded.push((
+                *address,
+                Self::load_account_with_cache(
+                    &self.db,
+                    address,
+                    &[RequireCache::Code],
+                )?,
+            ));
+        }
+
+        let mut cache = self.cache.write();
+        for (address, maybe_account) in loaded {
+            if Self::insert_cache_if_fresh_account(
+                &mut *cache,
+                &self.cache_counters,
+                &address,
+                maybe_account,
+            ) {
+                self.touch_cache_entry(&address, &cache[&address]);
+            }
+        }
+        let code_hashes = addresses
+            .iter()
+            .map(|address| {
+                cache
+                    .get(address)
+                    .and_then(|entry| entry.account.as_ref())
+                    .map(|account| account.code_hash())
+            })
+            .collect();
+        self.evict_cache_entries(&mut *cache, None);
+        Ok(code_hashes)
+    }
+
     pub fn code_size(
         &self, address: &AddressWithSpace,
     ) -> DbResult<Option<usize>> {
@@ -755,6 +1923,42 @@ impl State {
         Ok(acc.collateral_for_storage())
     }
 
+    /// The balance which can be spent by `address`. The staking balance and
+    /// the collateral for storage are tracked separately and are never part
+    /// of it.
+    pub fn liquid_balance(&self, address: &Address) -> DbResult<U256> {
+        self.balance(&address.with_native_space())
+    }
+
+    /// The sum of the balance, the staking balance and the collateral for
+    /// storage of `address`.
+    pub fn total_holdings(&self, address: &Address) -> DbResult<U256> {
+        let acc = try_loaded!(self.read_native_account(address));
+        Ok(*acc.balance()
+            + *acc.staking_balance()
+            + acc.collateral_for_storage())
+    }
+
+    /// Return `collateral_for_storage / staking_balance` of the account, or
+    /// `None` if it has no staking balance.
+    pub fn collateral_utilization(
+        &self, address: &Address,
+    ) -> DbResult<Option<f64>> {
+        let staking_balance = self.staking_balance(address)?;
+        if staking_balance.is_zero() {
+            return Ok(None);
+        }
+        let collateral = self.collateral_for_storage(address)?;
+        let to_f64 = |value: U256| {
+            value
+                .0
+                .iter()
+                .rev()
+                .fold(0f64, |acc, limb| acc * 2f64.powi(64) + *limb as f64)
+        };
+        Ok(Some(to_f64(collateral) / to_f64(staking_balance)))
+    }
+
     pub fn token_collateral_for_storage(
         &self, address: &Address,
     ) -> DbResult<U256> {
@@ -788,6 +1992,21 @@ impl State {
             - acc.withdrawable_staking_balance(block_number))
     }
 
+    /// The staking balance of `address` which can be withdrawn at
+    /// `current_block_number`. The same tokens can be locked by the votes and
+    /// for PoS at the same time, so only the larger lock is subtracted.
+    pub fn available_staking_balance(
+        &self, address: &Address, current_block_number: u64,
+    ) -> DbResult<U256> {
+        let staking_balance = self.staking_balance(address)?;
+        let vote_locked = self.locked_staking_balance_at_block_number(
+            address,
+            current_block_number,
+        )?;
+        let pos_locked = self.pos_locked_staking(address)?;
+        Ok(staking_balance.saturating_sub(vote_locked.max(pos_locked)))
+    }
+
     pub fn deposit_list_length(&self, address: &Address) -> DbResult<usize> {
         let acc = try_loaded!(self.read_account_ext(
             &address.with_native_space(),
@@ -804,6 +2023,60 @@ impl State {
         Ok(acc.vote_stake_list().map_or(0, |l| l.len()))
     }
 
+    /// Return all the deposits of `address` in order. The result is empty if
+    /// the account does not exist.
+    pub fn deposit_list(
+        &self, address: &Address,
+    ) -> DbResult<Vec<DepositInfo>> {
+        let acc = try_loaded!(self.read_account_ext(
+            &address.with_native_space(),
+            RequireCache::DepositList
+        ));
+        Ok(acc.deposit_list().map_or(vec![], |l| l.0.clone()))
+    }
+
+    /// Return at most `limit` deposits of `address` starting from `offset`.
+    /// The result is empty if the account does not exist or `offset` is out
+    /// of range.
+    pub fn deposit_list_slice(
+        &self, address: &Address, offset: usize, limit: usize,
+    ) -> DbResult<Vec<DepositInfo>> {
+        let acc = try_loaded!(self.read_account_ext(
+            &address.with_native_space(),
+            RequireCache::DepositList
+        ));
+        Ok(acc.deposit_list().map_or(vec![], |l| {
+            l.iter().skip(offset).take(limit).cloned().collect()
+        }))
+    }
+
+    /// Return all the vote stakes of `address`, sorted by the unlock block
+    /// number. The result is empty if the account does not exist.
+    pub fn vote_stake_list(
+        &self, address: &Address,
+    ) -> DbResult<Vec<VoteStakeInfo>> {
+        let acc = try_loaded!(self.read_account_ext(
+            &address.with_native_space(),
+            RequireCache::VoteStakeList
+        ));
+        Ok(acc.vote_stake_list().map_or(vec![], |l| l.0.clone()))
+    }
+
+    /// Return at most `limit` vote stakes of `address` starting from
+    /// `offset`. The result is empty if the account does not exist or
+    /// `offset` is out of range.
+    pub fn vote_stake_list_slice(
+        &self, address: &Address, offset: usize, limit: usize,
+    ) -> DbResult<Vec<VoteStakeInfo>> {
+        let acc = try_loaded!(self.read_account_ext(
+            &address.with_native_space(),
+            RequireCache::VoteStakeList
+        ));
+        Ok(acc.vote_stake_list().map_or(vec![], |l| {
+            l.iter().skip(offset).take(limit).cloned().collect()
+        }))
+    }
+
     // This is a special implementation to fix the bug in function
     // `clean_account` while not changing the genesis result.
     pub fn genesis_special_clean_account(
@@ -830,6 +2103,20 @@ impl State {
             .map(|mut x| x.inc_nonce())
     }
 
+    /// Same as calling `inc_nonce` `count` times, but only acquires the
+    /// account once.
+    pub fn inc_nonce_by(
+        &mut self, address: &AddressWithSpace, count: u64,
+    ) -> DbResult<()> {
+        if count == 0 {
+            return Ok(());
+        }
+        self.require_or_new_basic_account(address).map(|mut x| {
+            let nonce = *x.nonce() + U256::from(count);
+            x.set_nonce(&nonce)
+        })
+    }
+
     // TODO: This implementation will fail
     // tests::load_chain_tests::test_load_chain. We need to figure out why.
     //
@@ -897,6 +2184,7 @@ impl State {
     {
         let address = address.with_native_space();
         self.add_total_issued(*interest);
+        self.record_issuance(&address, interest);
         self.add_balance(&address, interest, cleanup_mode)?;
         self.require_or_new_basic_account(&address)?
             .record_interest_receive(interest);
@@ -913,6 +2201,33 @@ impl State {
         Ok(())
     }
 
+    /// Same as `transfer_balance`, but fails if `from` and `to` are in
+    /// different spaces.
+    pub fn transfer_balance_same_space(
+        &mut self, from: &AddressWithSpace, to: &AddressWithSpace, by: &U256,
+        cleanup_mode: CleanupMode,
+    ) -> DbResult<()>
+    {
+        if from.space != to.space {
+            bail!(DbErrorKind::CrossSpaceTransfer(*from, *to));
+        }
+        self.transfer_balance(from, to, by, cleanup_mode)
+    }
+
+    /// Whether `deposit` of `amount` takes effect, i.e. the amount is not
+    /// zero and the account exists with enough balance. The state is not
+    /// changed. The minimal deposit of the staking contract is not checked.
+    pub fn can_deposit(
+        &self, address: &Address, amount: &U256,
+    ) -> DbResult<bool> {
+        if amount.is_zero() {
+            return Ok(false);
+        }
+        Ok(self
+            .read_native_account(address)?
+            .map_or(false, |acc| acc.balance() >= amount))
+    }
+
     pub fn deposit(
         &mut self, address: &Address, amount: &U256, current_block_number: u64,
         cip_97: bool,
@@ -944,6 +2259,12 @@ impl State {
     ) -> DbResult<U256> {
         let address = address.with_native_space();
         if !amount.is_zero() {
+            let total_staking_tokens = checked_sub_statistic(
+                "total_staking_tokens",
+                self.world_statistics.total_staking_tokens,
+                *amount,
+                self.strict_world_statistics,
+            )?;
             let interest;
             {
                 let mut account = self.require_exists(&address, false)?;
@@ -958,15 +2279,55 @@ impl State {
                     cip_97,
                 );
             }
+            self.world_statistics.total_staking_tokens = total_staking_tokens;
             // the interest will be put in balance.
             self.world_statistics.total_issued_tokens += interest;
-            self.world_statistics.total_staking_tokens -= *amount;
+            self.record_issuance(&address, &interest);
             Ok(interest)
         } else {
             Ok(U256::zero())
         }
     }
 
+    /// Returns the interest that `withdraw` would pay for the same arguments,
+    /// without changing the state.
+    pub fn preview_withdraw_interest(
+        &self, address: &Address, amount: &U256, cip_97: bool,
+    ) -> DbResult<U256> {
+        if amount.is_zero() {
+            return Ok(U256::zero());
+        }
+        let acc = try_loaded!(self.read_account_ext(
+            &address.with_native_space(),
+            RequireCache::DepositList,
+        ));
+        Ok(acc.clone_basic().withdraw(
+            *amount,
+            self.world_statistics.accumulate_interest_rate,
+            cip_97,
+        ))
+    }
+
+    /// Returns the interest accrued by all the deposits of `address` at
+    /// `current_accumulate_rate`, which is what withdrawing the whole deposit
+    /// list would pay at that rate. The state is not changed.
+    pub fn deposit_accrued_interest(
+        &self, address: &Address, current_accumulate_rate: U256,
+    ) -> DbResult<U256> {
+        let acc = try_loaded!(self.read_account_ext(
+            &address.with_native_space(),
+            RequireCache::DepositList,
+        ));
+        Ok(acc.deposit_list().map_or(U256::zero(), |deposit_list| {
+            deposit_list.iter().fold(U256::zero(), |interest, deposit| {
+                interest
+                    + deposit.amount * current_accumulate_rate
+                        / deposit.accumulated_interest_rate
+                    - deposit.amount
+            })
+        }))
+    }
+
     pub fn vote_lock(
         &mut self, address: &Address, amount: &U256, unlock_block_number: u64,
     ) -> DbResult<()> {
@@ -997,10 +2358,87 @@ impl State {
         Ok(())
     }
 
+    /// Same as calling `remove_expired_vote_stake_info` for each of
+    /// `addresses`, except that the addresses which do not exist are skipped.
+    pub fn remove_expired_vote_stake_info_many(
+        &mut self, addresses: &[Address], current_block_number: u64,
+    ) -> DbResult<()> {
+        for address in addresses {
+            if !self.exists(&address.with_native_space())? {
+                continue;
+            }
+            self.remove_expired_vote_stake_info(address, current_block_number)?;
+        }
+        Ok(())
+    }
+
+    /// Remove the expired vote stakes of `addresses` and return the number of
+    /// removed entries. Only the accounts which actually have expired vote
+    /// stakes are marked dirty.
+    pub fn sweep_expired_vote_stakes(
+        &mut self, addresses: &[Address], current_block_number: u64,
+    ) -> DbResult<usize> {
+        assert!(self.checkpoints.get_mut().is_empty());
+        assert!(self.world_statistics_checkpoints.get_mut().is_empty());
+        let block_number = U256::from(current_block_number);
+        let mut removed = 0;
+        for address in addresses {
+            let address = address.with_native_space();
+            let expired = match self
+                .read_account_ext(&address, RequireCache::VoteStakeList)?
+            {
+                Some(acc) => acc.vote_stake_list().map_or(0, |l| {
+                    l.iter()
+                        .take_while(|v| v.unlock_block_number <= block_number)
+                        .count()
+                }),
+                None => 0,
+            };
+            if expired == 0 {
+                continue;
+            }
+            let mut account = self.require_exists(&address, false)?;
+            account.cache_staking_info(
+                false, /* cache_deposit_list */
+                true,  /* cache_vote_list */
+                &self.db,
+            )?;
+            account.remove_expired_vote_stake_info(current_block_number);
+            removed += expired;
+        }
+        Ok(removed)
+    }
+
     pub fn total_issued_tokens(&self) -> U256 {
         self.world_statistics.total_issued_tokens
     }
 
+    /// The issued tokens minus the balance of the zero address and the
+    /// locked genesis tokens.
+    pub fn total_circulating_tokens(&self) -> DbResult<U256> {
+        Ok(self.total_issued_tokens()
+            - self.balance(&Address::zero().with_native_space())?
+            - self.genesis_locked_tokens()?)
+    }
+
+    fn genesis_locked_tokens(&self) -> DbResult<U256> {
+        if let Some(locked) = *self.genesis_locked_tokens_cache.read() {
+            return Ok(locked);
+        }
+        let mut locked = U256::zero();
+        for address in GENESIS_LOCK_ADDRESSES.iter() {
+            locked += self.balance(address)?;
+        }
+        *self.genesis_locked_tokens_cache.write() = Some(locked);
+        Ok(locked)
+    }
+
+    fn invalidate_genesis_locked_cache(&self, address: &AddressWithSpace) {
+        if GENESIS_LOCK_ADDRESSES.contains(address) {
+            self.genesis_locked_tokens_cache.write().take();
+        }
+    }
+
     pub fn total_staking_tokens(&self) -> U256 {
         self.world_statistics.total_staking_tokens
     }
@@ -1021,6 +2459,15 @@ impl State {
         self.world_statistics.converted_storage_points
     }
 
+    /// Storage points converted by CIP-107 but not yet used for storage
+    /// collateral, i.e. `converted_storage_points - used_storage_points`
+    /// saturating at zero.
+    pub fn unused_storage_points(&self) -> U256 {
+        self.world_statistics
+            .converted_storage_points
+            .saturating_sub(self.world_statistics.used_storage_points)
+    }
+
     pub fn total_pos_staking_tokens(&self) -> U256 {
         self.world_statistics.total_pos_staking_tokens
     }
@@ -1029,10 +2476,31 @@ impl State {
         self.world_statistics.distributable_pos_interest
     }
 
+    /// All the token figures of the world statistics, read at once.
+    pub fn supply_breakdown(&self) -> SupplyBreakdown {
+        let stat = &self.world_statistics;
+        SupplyBreakdown {
+            total_issued_tokens: stat.total_issued_tokens,
+            total_staking_tokens: stat.total_staking_tokens,
+            total_storage_tokens: stat.total_storage_tokens,
+            total_espace_tokens: stat.total_evm_tokens,
+            total_pos_staking_tokens: stat.total_pos_staking_tokens,
+            distributable_pos_interest: stat.distributable_pos_interest,
+            used_storage_points: stat.used_storage_points,
+            converted_storage_points: stat.converted_storage_points,
+        }
+    }
+
     pub fn last_distribute_block(&self) -> u64 {
         self.world_statistics.last_distribute_block
     }
 
+    /// Set the number of blocks after the last distribution in which PoS
+    /// interest accumulates, `BLOCKS_PER_HOUR` by default.
+    pub fn set_distribute_window_blocks(&mut self, blocks: u64) {
+        self.world_statistics.distribute_window_blocks = blocks;
+    }
+
     pub fn remove_contract(
         &mut self, address: &AddressWithSpace,
     ) -> DbResult<()> {
@@ -1049,8 +2517,10 @@ impl State {
             }
         }
 
+        self.invalidate_genesis_locked_cache(address);
         Self::update_cache(
             self.cache.get_mut(),
+            &self.cache_counters,
             self.checkpoints.get_mut(),
             address,
             AccountEntry::new_dirty(Some(OverlayAccount::new_removed(address))),
@@ -1070,6 +2540,157 @@ impl State {
         Ok(!acc.is_null())
     }
 
+    /// Whether the account is null, or `None` if it does not exist.
+    pub fn is_null(
+        &self, address: &AddressWithSpace,
+    ) -> DbResult<Option<bool>> {
+        Ok(self.read_account(address)?.map(|acc| acc.is_null()))
+    }
+
+    /// Return the account at `address` with its Merkle proof against the
+    /// state root of the last commit. Returns `None` if the account has
+    /// changes not committed yet, which the proof can not cover.
+    pub fn prove_account(
+        &self, address: &AddressWithSpace,
+    ) -> DbResult<Option<AccountProof>> {
+        assert!(self.checkpoints.read().is_empty());
+        if self.cache.read().get(address).map_or(false, |e| e.is_dirty()) {
+            return Ok(None);
+        }
+        let key = StorageKey::new_account_key(&address.address)
+            .with_space(address.space);
+        let (value, proof) = match self.db.get_raw_with_proof(key)? {
+            Some(value_and_proof) => value_and_proof,
+            None => return Ok(None),
+        };
+        let account = match &value {
+            Some(raw) => Some(Account::new_from_rlp(
+                address.address,
+                &Rlp::new(raw),
+            )?),
+            None => None,
+        };
+        Ok(Some(AccountProof {
+            account,
+            value: value.map(|raw| raw.to_vec()),
+            proof,
+        }))
+    }
+
+    /// Return the storage entry under `key` of `address` with its Merkle
+    /// proof against the state root of the last commit. Returns `None` if
+    /// the account has changes not committed yet.
+    pub fn prove_storage(
+        &self, address: &AddressWithSpace, key: &[u8],
+    ) -> DbResult<Option<StorageProof>> {
+        assert!(self.checkpoints.read().is_empty());
+        if self.cache.read().get(address).map_or(false, |e| e.is_dirty()) {
+            return Ok(None);
+        }
+        let key = StorageKey::new_storage_key(&address.address, key)
+            .with_space(address.space);
+        let (value, proof) = match self.db.get_raw_with_proof(key)? {
+            Some(value_and_proof) => value_and_proof,
+            None => return Ok(None),
+        };
+        let storage_value = match &value {
+            Some(raw) => Some(rlp::decode::<StorageValue>(raw)?),
+            None => None,
+        };
+        Ok(Some(StorageProof {
+            storage_value,
+            value: value.map(|raw| raw.to_vec()),
+            proof,
+        }))
+    }
+
+    /// Return the root of the storage of `address` as of the last commit,
+    /// or `None` if the account does not exist. The root holds the merkle
+    /// roots of the storage in the delta, intermediate and snapshot tries, as
+    /// verified by storage proofs. Fails if the account has uncommitted
+    /// storage changes, which the root would not reflect.
+    pub fn storage_root(
+        &self, address: &AddressWithSpace,
+    ) -> DbResult<Option<StorageRoot>> {
+        let dirty_storage = self.cache.read().get(address).map_or(false, |e| {
+            e.account.as_ref().map_or(false, |acc| acc.has_dirty_storage())
+        });
+        if dirty_storage {
+            bail!(DbErrorKind::DirtyStorage(address.address));
+        }
+        if !self.exists(address)? {
+            return Ok(None);
+        }
+        Ok(Some(self.db.get_storage_root(address)?))
+    }
+
+    /// Return snapshots of the accounts currently marked dirty in the cache.
+    /// Neither the cache nor the checkpoints are modified.
+    pub fn dirty_accounts(&self) -> Vec<(AddressWithSpace, Account)> {
+        self.cache
+            .read()
+            .iter()
+            .filter(|(_, entry)| entry.is_dirty())
+            .filter_map(|(address, entry)| {
+                entry
+                    .account
+                    .as_ref()
+                    .map(|account| (*address, account.as_account()))
+            })
+            .collect()
+    }
+
+    /// Whether any account entry in the cache has uncommitted changes.
+    pub fn has_dirty_entries(&self) -> bool {
+        self.cache.read().values().any(|entry| entry.is_dirty())
+    }
+
+    /// Number of accounts in the cache with uncommitted changes, i.e. the
+    /// accounts written by the next `compute_state_root`.
+    pub fn dirty_account_count(&self) -> usize {
+        self.cache_counters.dirty_entries.load(Ordering::Relaxed)
+    }
+
+    /// Return a digest of the dirty accounts, including their uncommitted
+    /// storage writes, and the world statistics. Two states with the same
+    /// pending changes have the same fingerprint.
+    pub fn pending_fingerprint(&self) -> H256 {
+        let cache = self.cache.read();
+        let mut dirty_entries = cache
+            .iter()
+            .filter(|(_, entry)| entry.is_dirty())
+            .collect::<Vec<_>>();
+        dirty_entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut stream = RlpStream::new_list(2);
+        stream.append(&self.world_statistics);
+        stream.begin_list(dirty_entries.len());
+        for (address, entry) in dirty_entries {
+            stream
+                .begin_list(4)
+                .append(&address.address)
+                .append(&String::from(address.space));
+            match &entry.account {
+                None => {
+                    stream.append_empty_data().begin_list(0);
+                }
+                Some(account) => {
+                    let mut storage = account
+                        .storage_value_write_cache()
+                        .iter()
+                        .collect::<Vec<_>>();
+                    storage.sort();
+                    stream.append(&account.as_account());
+                    stream.begin_list(storage.len());
+                    for (key, value) in storage {
+                        stream.begin_list(2).append(key).append(value);
+                    }
+                }
+            }
+        }
+        keccak(stream.out())
+    }
+
     pub fn storage_at(
         &self, address: &AddressWithSpace, key: &[u8],
     ) -> DbResult<U256> {
@@ -1077,30 +2698,223 @@ impl State {
         acc.storage_at(&self.db, key)
     }
 
+    /// Return at most `limit` storage entries of `address` ordered by key,
+    /// starting from `start_key` (inclusive). The uncommitted writes in cache
+    /// are merged with the entries in db, and only as many entries as needed
+    /// are read from db.
+    pub fn storage_entries(
+        &self, address: &AddressWithSpace, start_key: Option<&[u8]>,
+        limit: usize,
+    ) -> DbResult<Vec<(Vec<u8>, StorageValue)>>
+    {
+        let start_key = start_key.unwrap_or_default();
+        let mut changes = BTreeMap::new();
+        let mut read_db = true;
+        match self.cache.read().get(address) {
+            // The account is killed in this execution.
+            Some(entry) if entry.account.is_none() => read_db = false,
+            Some(entry) => {
+                let account = entry.account.as_ref().unwrap();
+                read_db = !account.invalidated_storage();
+                for (key, value) in account.storage_value_write_cache() {
+                    if key.as_slice() < start_key {
+                        continue;
+                    }
+                    let owner = match account.cached_storage_owner(key) {
+                        Some(Some(owner)) if owner != address.address => {
+                            Some(Some(owner))
+                        }
+                        Some(_) => Some(None),
+                        // The owner is unchanged.
+                        None => None,
+                    };
+                    changes.insert(key.clone(), (*value, owner));
+                }
+            }
+            None => {}
+        }
+
+        let mut entries = BTreeMap::new();
+        if read_db {
+            // Each change in cache hides at most one entry in db, and the
+            // storage layout is stored under the storage root key.
+            let db_limit = limit.saturating_add(changes.len() + 1);
+            let key_values = self.db.read_range(
+                StorageKey::new_storage_root_key(&address.address)
+                    .with_space(address.space),
+                &StorageKey::new_storage_key(&address.address, start_key)
+                    .with_space(address.space)
+                    .to_key_bytes(),
+                db_limit,
+            )?;
+            for (key, value) in &key_values {
+                if let StorageKeyWithSpace {
+                    key: StorageKey::StorageKey { storage_key, .. },
+                    ..
+                } = StorageKeyWithSpace::from_key_bytes::<SkipInputCheck>(
+                    &key[..],
+                ) {
+                    entries.insert(
+                        storage_key.to_vec(),
+                        rlp::decode::<StorageValue>(value.as_ref())?,
+                    );
+                }
+            }
+        }
+
+        for (key, (value, owner)) in changes {
+            if value.is_zero() {
+                entries.remove(&key);
+                continue;
+            }
+            let owner = match owner {
+                Some(owner) => owner,
+                None => entries.get(&key).and_then(|v| v.owner),
+            };
+            entries.insert(key, StorageValue { value, owner });
+        }
+
+        Ok(entries.into_iter().take(limit).collect())
+    }
+
+    /// Read several storage slots of `address` while holding the account read
+    /// guard once. The values are returned in the order of `keys`.
+    pub fn storage_at_many(
+        &self, address: &AddressWithSpace, keys: &[&[u8]],
+    ) -> DbResult<Vec<U256>> {
+        let acc = match self.read_account(address)? {
+            Some(acc) => acc,
+            None => return Ok(vec![U256::zero(); keys.len()]),
+        };
+        keys.iter().map(|key| acc.storage_at(&self.db, key)).collect()
+    }
+
+    /// Same as `storage_at_many`, for owned keys.
+    pub fn storage_entries_at(
+        &self, address: &AddressWithSpace, keys: &[Vec<u8>],
+    ) -> DbResult<Vec<U256>> {
+        let key_refs = keys.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        self.storage_at_many(address, &key_refs)
+    }
+
+    /// The net number of storage entries `address` gained since the last
+    /// commit, before the changes are settled.
+    pub fn account_storage_delta(
+        &self, address: &AddressWithSpace,
+    ) -> DbResult<i64> {
+        let acc = try_loaded!(self.read_account(address));
+        acc.storage_delta(&self.db)
+    }
+
     pub fn set_storage(
         &mut self, address: &AddressWithSpace, key: Vec<u8>, value: U256,
         owner: Address,
     ) -> DbResult<()>
     {
         if self.storage_at(address, &key)? != value {
+            self.invalidate_system_storage_cache(address);
             self.require_exists(address, false)?
                 .set_storage(key, value, owner)
         }
         Ok(())
     }
 
-    pub fn update_pos_status(
-        &mut self, identifier: H256, number: u64,
-    ) -> DbResult<()> {
-        let old_value = self.storage_at(
+    fn invalidate_system_storage_cache(&mut self, address: &AddressWithSpace) {
+        if *address == SYSTEM_STORAGE_ADDRESS.with_native_space() {
+            self.storage_point_prop_cache.get_mut().take();
+        }
+    }
+
+    /// Same as `storage_at`, with the big-endian bytes of `key` as the key.
+    pub fn storage_at_h256(
+        &self, address: &AddressWithSpace, key: H256,
+    ) -> DbResult<U256> {
+        self.storage_at(address, key.as_bytes())
+    }
+
+    /// Same as `set_storage`, with the big-endian bytes of `key` as the key.
+    pub fn set_storage_h256(
+        &mut self, address: &AddressWithSpace, key: H256, value: U256,
+        owner: Address,
+    ) -> DbResult<()>
+    {
+        self.set_storage(address, key.as_bytes().to_vec(), value, owner)
+    }
+
+    /// Apply the entries in order as `set_storage` does, but only acquire the
+    /// write guard of the account once.
+    pub fn set_storage_entries(
+        &mut self, address: &AddressWithSpace,
+        entries: Vec<(Vec<u8>, U256, Address)>,
+    ) -> DbResult<()>
+    {
+        let mut changed_entries = Vec::with_capacity(entries.len());
+        {
+            let maybe_account = self.read_account(address)?;
+            // The values written by the previous entries.
+            let mut written: HashMap<Vec<u8>, U256> = HashMap::new();
+            for (key, value, owner) in entries {
+                let current_value = match written.get(&key) {
+                    Some(written_value) => *written_value,
+                    None => match &maybe_account {
+                        Some(account) => account.storage_at(&self.db, &key)?,
+                        None => U256::zero(),
+                    },
+                };
+                if current_value != value {
+                    written.insert(key.clone(), value);
+                    changed_entries.push((key, value, owner));
+                }
+            }
+        }
+        if !changed_entries.is_empty() {
+            self.invalidate_system_storage_cache(address);
+            let mut account = self.require_exists(address, false)?;
+            for (key, value, owner) in changed_entries {
+                account.set_storage(key, value, owner);
+            }
+        }
+        Ok(())
+    }
+
+    /// Return whether unlocking up to `number` votes of `identifier` is
+    /// valid, i.e. the identifier is registered and its unlocked votes do not
+    /// decrease.
+    pub fn validate_pos_unlock(
+        &self, identifier: H256, number: u64,
+    ) -> DbResult<bool> {
+        let value = self.storage_at(
             &POS_REGISTER_CONTRACT_ADDRESS.with_native_space(),
             &pos_internal_entries::index_entry(&identifier),
         )?;
-        assert!(!old_value.is_zero(), "If an identifier is unlocked, its index information must be non-zero");
-        let mut status: IndexStatus = old_value.into();
+        if value.is_zero() {
+            return Ok(false);
+        }
+        let status: IndexStatus = value.into();
+        Ok(number >= status.unlocked)
+    }
+
+    pub fn update_pos_status(
+        &mut self, identifier: H256, number: u64,
+    ) -> DbResult<()> {
+        if !self.validate_pos_unlock(identifier, number)? {
+            bail!(invalid_pos_unlock(identifier, number));
+        }
+        let mut status: IndexStatus = self
+            .storage_at(
+                &POS_REGISTER_CONTRACT_ADDRESS.with_native_space(),
+                &pos_internal_entries::index_entry(&identifier),
+            )?
+            .into();
+        // Checked by `validate_pos_unlock`.
         let new_unlocked = number - status.unlocked;
         status.set_unlocked(number);
-        // .expect("Incorrect unlock information");
+        let total_pos_staking_tokens = checked_sub_statistic(
+            "total_pos_staking_tokens",
+            self.world_statistics.total_pos_staking_tokens,
+            *POS_VOTE_PRICE * new_unlocked,
+            self.strict_world_statistics,
+        )?;
         self.require_exists(
             &POS_REGISTER_CONTRACT_ADDRESS.with_native_space(),
             false,
@@ -1110,8 +2924,60 @@ impl State {
             &pos_internal_entries::index_entry(&identifier),
             status.into(),
         )?;
-        self.world_statistics.total_pos_staking_tokens -=
-            *POS_VOTE_PRICE * new_unlocked;
+        self.world_statistics.total_pos_staking_tokens =
+            total_pos_staking_tokens;
+        Ok(())
+    }
+
+    /// Same as calling `update_pos_status` for each of `updates` in order, but
+    /// only acquires the PoS register contract once and updates
+    /// `total_pos_staking_tokens` once. Nothing is changed if any update is
+    /// invalid.
+    pub fn update_pos_statuses(
+        &mut self, updates: &[(H256, u64)],
+    ) -> DbResult<()> {
+        let register = POS_REGISTER_CONTRACT_ADDRESS.with_native_space();
+        let mut statuses: HashMap<H256, IndexStatus> = HashMap::new();
+        let mut new_unlocked = 0;
+        for (identifier, number) in updates {
+            let status = match statuses.entry(*identifier) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let value = self.storage_at(
+                        &register,
+                        &pos_internal_entries::index_entry(identifier),
+                    )?;
+                    if value.is_zero() {
+                        bail!(invalid_pos_unlock(*identifier, *number));
+                    }
+                    entry.insert(value.into())
+                }
+            };
+            if *number < status.unlocked {
+                bail!(invalid_pos_unlock(*identifier, *number));
+            }
+            new_unlocked += number - status.unlocked;
+            status.set_unlocked(*number);
+        }
+        let total_pos_staking_tokens = checked_sub_statistic(
+            "total_pos_staking_tokens",
+            self.world_statistics.total_pos_staking_tokens,
+            *POS_VOTE_PRICE * new_unlocked,
+            self.strict_world_statistics,
+        )?;
+
+        {
+            let mut account = self.require_exists(&register, false)?;
+            for (identifier, status) in statuses {
+                account.change_storage_value(
+                    &self.db,
+                    &pos_internal_entries::index_entry(&identifier),
+                    status.into(),
+                )?;
+            }
+        }
+        self.world_statistics.total_pos_staking_tokens =
+            total_pos_staking_tokens;
         Ok(())
     }
 
@@ -1167,60 +3033,306 @@ impl State {
         let checkpoints = self.checkpoints.get_mut();
         let index = checkpoints.len();
         checkpoints.push(HashMap::new());
+        self.issuance_ledger_checkpoints.push(self.issuance_ledger.len());
+        self.notify_checkpoint_observer(CheckpointEvent::Create);
         index
     }
 
-    /// Merge last checkpoint with previous.
+    /// Merge last checkpoint with previous and return the number of entries
+    /// merged, which is 0 if it was the last checkpoint.
     /// Caller should make sure the function
     /// `collect_ownership_changed()` was called before calling
     /// this function.
-    pub fn discard_checkpoint(&mut self) {
+    pub fn discard_checkpoint(&mut self) -> DbResult<usize> {
         // merge with previous checkpoint
-        let last = self.checkpoints.get_mut().pop();
-        if let Some(mut checkpoint) = last {
-            self.world_statistics_checkpoints.get_mut().pop();
-            if let Some(ref mut prev) = self.checkpoints.get_mut().last_mut() {
-                if prev.is_empty() {
-                    **prev = checkpoint;
-                } else {
-                    for (k, v) in checkpoint.drain() {
-                        prev.entry(k).or_insert(v);
-                    }
+        let mut checkpoint = match self.checkpoints.get_mut().pop() {
+            Some(checkpoint) => checkpoint,
+            None => bail!(DbErrorKind::NoOpenCheckpoint),
+        };
+        self.world_statistics_checkpoints.get_mut().pop();
+        self.issuance_ledger_checkpoints.pop();
+        let mut merged = 0;
+        if let Some(ref mut prev) = self.checkpoints.get_mut().last_mut() {
+            merged = checkpoint.len();
+            if prev.is_empty() {
+                **prev = checkpoint;
+            } else {
+                for (k, v) in checkpoint.drain() {
+                    prev.entry(k).or_insert(v);
                 }
             }
         }
+        self.notify_checkpoint_observer(CheckpointEvent::Discard);
+        Ok(merged)
     }
 
     /// Revert to the last checkpoint and discard it.
-    pub fn revert_to_checkpoint(&mut self) {
-        if let Some(mut checkpoint) = self.checkpoints.get_mut().pop() {
-            self.world_statistics = self
-                .world_statistics_checkpoints
-                .get_mut()
-                .pop()
-                .expect("staking_state_checkpoint should exist");
-            for (k, v) in checkpoint.drain() {
-                match v {
-                    Some(v) => match self.cache.get_mut().entry(k) {
-                        Entry::Occupied(mut e) => {
-                            e.get_mut().overwrite_with(v);
-                        }
-                        Entry::Vacant(e) => {
-                            e.insert(v);
-                        }
-                    },
-                    None => {
-                        if let Entry::Occupied(e) =
-                            self.cache.get_mut().entry(k)
-                        {
-                            if e.get().is_dirty() {
-                                e.remove();
-                            }
+    pub fn revert_to_checkpoint(&mut self) -> DbResult<()> {
+        let mut checkpoint = match self.checkpoints.get_mut().pop() {
+            Some(checkpoint) => checkpoint,
+            None => bail!(DbErrorKind::NoOpenCheckpoint),
+        };
+        self.world_statistics = self
+            .world_statistics_checkpoints
+            .get_mut()
+            .pop()
+            .expect("staking_state_checkpoint should exist");
+        let issuance_ledger_len = self
+            .issuance_ledger_checkpoints
+            .pop()
+            .expect("issuance_ledger_checkpoint should exist");
+        self.issuance_ledger.truncate(issuance_ledger_len);
+        self.storage_point_prop_cache.get_mut().take();
+        self.genesis_locked_tokens_cache.get_mut().take();
+        for (k, v) in checkpoint.drain() {
+            // The reverted account may have code again.
+            self.known_no_code.get_mut().remove(&k);
+            let counters = &self.cache_counters;
+            match v {
+                Some(v) => match self.cache.get_mut().entry(k) {
+                    Entry::Occupied(mut e) => {
+                        counters.remove(e.get());
+                        e.get_mut().overwrite_with(v);
+                        counters.add(e.get());
+                    }
+                    Entry::Vacant(e) => {
+                        counters.add(&v);
+                        e.insert(v);
+                    }
+                },
+                None => {
+                    if let Entry::Occupied(e) = self.cache.get_mut().entry(k) {
+                        if e.get().is_dirty() {
+                            counters.remove(e.get());
+                            e.remove();
                         }
                     }
                 }
             }
         }
+        self.notify_checkpoint_observer(CheckpointEvent::Revert);
+        Ok(())
+    }
+
+    /// Revert the checkpoint created with `index` and all checkpoints
+    /// created after it, newest first, leaving `index` checkpoints open.
+    /// Does nothing if no checkpoint has been created with `index`.
+    pub fn revert_to_checkpoint_index(&mut self, index: usize) -> DbResult<()> {
+        while self.checkpoint_depth() > index {
+            self.revert_to_checkpoint()?;
+            self.debug_assert_checkpoints_in_step();
+        }
+        Ok(())
+    }
+
+    /// Merge the checkpoint created with `index` and all checkpoints created
+    /// after it into the previous one, leaving `index` checkpoints open.
+    /// Caller should make sure the function `collect_ownership_changed()`
+    /// was called before calling this function.
+    pub fn discard_checkpoints_above(&mut self, index: usize) -> DbResult<()> {
+        if index >= self.checkpoint_depth() {
+            bail!(DbErrorKind::NoOpenCheckpoint);
+        }
+        while self.checkpoint_depth() > index {
+            self.discard_checkpoint()?;
+            self.debug_assert_checkpoints_in_step();
+        }
+        Ok(())
+    }
+
+    fn debug_assert_checkpoints_in_step(&mut self) {
+        debug_assert_eq!(
+            self.checkpoints.get_mut().len(),
+            self.world_statistics_checkpoints.get_mut().len()
+        );
+    }
+
+    /// Create a checkpoint which is reverted when the returned guard is
+    /// dropped, including on panic, unless the guard is committed.
+    pub fn checkpoint_scope(&mut self) -> CheckpointGuard<'_> {
+        let index = self.checkpoint();
+        CheckpointGuard {
+            state: self,
+            index,
+            committed: false,
+        }
+    }
+
+    /// Number of checkpoints currently open.
+    pub fn checkpoint_depth(&self) -> usize { self.checkpoints.read().len() }
+
+    /// Whether any checkpoint of the accounts or the world statistics is
+    /// still open.
+    pub fn has_open_checkpoints(&self) -> bool {
+        !self.checkpoints.read().is_empty()
+            || !self.world_statistics_checkpoints.read().is_empty()
+    }
+
+    /// Open checkpoints at the end of an epoch mean that the checkpoints are
+    /// not used in pairs, which is a logic bug. Only checked in debug builds.
+    pub fn assert_no_open_checkpoints(&self) {
+        debug_assert!(
+            !self.has_open_checkpoints(),
+            "State has {} open checkpoints",
+            self.checkpoint_depth()
+        );
+    }
+
+    /// Return the accounts and storage changed since the checkpoint created
+    /// with `index`, comparing their values at the checkpoint with the
+    /// current ones. The diff is empty if the checkpoint is not open.
+    pub fn checkpoint_diff(&self, index: usize) -> DbResult<StateDiff> {
+        // The oldest checkpoint entry of an address since `index` holds its
+        // value at the checkpoint. A `None` entry means it was not cached.
+        let mut saved = BTreeMap::new();
+        for checkpoint in self.checkpoints.read().iter().skip(index) {
+            for (address, entry) in checkpoint {
+                saved.entry(*address).or_insert_with(|| {
+                    entry.as_ref().map(|entry| {
+                        entry.account.as_ref().map(AccountSummary::from)
+                    })
+                });
+            }
+        }
+
+        let mut accounts = Vec::new();
+        for (address, saved) in saved {
+            let before = match saved {
+                Some(before) => before,
+                None => self
+                    .db
+                    .get_account(&address)?
+                    .as_ref()
+                    .map(AccountSummary::from),
+            };
+            let (after, mut written_keys) =
+                match self.read_account(&address)? {
+                    Some(account) => (
+                        Some(AccountSummary::from(&*account)),
+                        account
+                            .storage_value_write_cache()
+                            .keys()
+                            .cloned()
+                            .collect::<Vec<_>>(),
+                    ),
+                    None => (None, vec![]),
+                };
+            written_keys.sort();
+            let mut storage_keys = vec![];
+            for key in written_keys {
+                let old_value =
+                    self.checkpoint_storage_at(index, &address, &key)?;
+                if old_value != Some(self.storage_at(&address, &key)?) {
+                    storage_keys.push(key);
+                }
+            }
+            if before != after || !storage_keys.is_empty() {
+                accounts.push(AccountDiff {
+                    space: address.space,
+                    address: address.address,
+                    before,
+                    after,
+                    storage_keys,
+                });
+            }
+        }
+        Ok(StateDiff { accounts })
+    }
+
+    /// Current size of the account cache, which is drained and sorted by
+    /// `compute_state_root`.
+    pub fn cache_stats(&self) -> CacheStats {
+        let counters = &self.cache_counters;
+        CacheStats {
+            entries: counters.entries.load(Ordering::Relaxed),
+            dirty_entries: counters.dirty_entries.load(Ordering::Relaxed),
+            code_bytes: counters.code_bytes.load(Ordering::Relaxed),
+            checkpoint_depth: self.checkpoint_depth(),
+        }
+    }
+
+    /// Limit the approximate memory used by the clean cached accounts to
+    /// `budget` bytes by evicting the least recently used ones, which are
+    /// reloaded from the db on the next access. Dirty accounts and accounts
+    /// recorded in a checkpoint are never evicted. The cache is unbounded
+    /// with `None`, which is the default.
+    pub fn set_cache_byte_budget(&mut self, budget: Option<usize>) {
+        self.cache_byte_budget = budget;
+        if budget.is_none() {
+            self.cache_lru.get_mut().clear();
+        }
+    }
+
+    fn cache_entry_bytes(entry: &AccountEntry) -> usize {
+        std::mem::size_of::<(AddressWithSpace, AccountEntry)>()
+            + CacheCounters::code_bytes_of(entry)
+    }
+
+    /// Record an access to the cached entry of `address`.
+    fn touch_cache_entry(
+        &self, address: &AddressWithSpace, entry: &AccountEntry,
+    ) {
+        if self.cache_byte_budget.is_some() && !entry.is_dirty() {
+            self.cache_lru
+                .lock()
+                .touch(address, Self::cache_entry_bytes(entry));
+        }
+    }
+
+    /// Evict the least recently used clean entries other than `keep` until
+    /// the tracked entries fit in the byte budget.
+    fn evict_cache_entries(
+        &self, cache: &mut HashMap<AddressWithSpace, AccountEntry>,
+        keep: Option<&AddressWithSpace>,
+    )
+    {
+        let budget = match self.cache_byte_budget {
+            Some(budget) => budget,
+            None => return,
+        };
+        let mut lru = self.cache_lru.lock();
+        let checkpoints = self.checkpoints.read();
+        let mut kept = None;
+        while lru.bytes > budget {
+            let (address, bytes) = match lru.pop_oldest() {
+                Some(oldest) => oldest,
+                None => break,
+            };
+            if Some(&address) == keep {
+                kept = Some((address, bytes));
+                continue;
+            }
+            let pinned = match cache.get(&address) {
+                None => continue,
+                Some(entry) => {
+                    entry.is_dirty()
+                        || checkpoints.iter().any(|c| c.contains_key(&address))
+                }
+            };
+            // A pinned entry is no longer tracked, it is tracked again once
+            // accessed while clean.
+            if !pinned {
+                let entry = cache.remove(&address).expect("checked above");
+                self.cache_counters.remove(&entry);
+            }
+        }
+        if let Some((address, bytes)) = kept {
+            lru.touch(&address, bytes);
+        }
+    }
+
+    /// Register an observer for checkpoint lifecycle events, or remove the
+    /// current one with `None`.
+    pub fn set_checkpoint_observer(
+        &mut self, observer: Option<CheckpointObserver>,
+    ) {
+        self.checkpoint_observer = observer;
+    }
+
+    fn notify_checkpoint_observer(&mut self, event: CheckpointEvent) {
+        if let Some(observer) = &self.checkpoint_observer {
+            observer(event, self.checkpoints.get_mut().len());
+        }
     }
 }
 
@@ -1243,8 +3355,9 @@ impl State {
                 total_issued_tokens,
                 total_staking_tokens,
                 total_storage_tokens,
-                interest_rate_per_block: annual_interest_rate
-                    / U256::from(BLOCKS_PER_YEAR),
+                interest_rate_per_block: annual_to_per_block(
+                    annual_interest_rate,
+                ),
                 accumulate_interest_rate,
                 total_pos_staking_tokens,
                 distributable_pos_interest,
@@ -1252,6 +3365,7 @@ impl State {
                 total_evm_tokens,
                 used_storage_points,
                 converted_storage_points,
+                distribute_window_blocks: BLOCKS_PER_HOUR,
             }
         } else {
             // If db is not initialized, all the loaded value should be zero.
@@ -1300,32 +3414,153 @@ impl State {
                 total_evm_tokens: U256::default(),
                 used_storage_points: U256::default(),
                 converted_storage_points: U256::default(),
+                distribute_window_blocks: BLOCKS_PER_HOUR,
             }
         };
 
         Ok(State {
             db,
             cache: Default::default(),
+            cache_counters: Default::default(),
+            cache_byte_budget: None,
+            cache_lru: Default::default(),
             world_statistics_checkpoints: Default::default(),
             checkpoints: Default::default(),
             world_statistics: world_stat,
+            committed_world_statistics: world_stat,
+            last_statistics_delta: None,
             accounts_to_notify: Default::default(),
+            uncommitted_notifications: Default::default(),
+            killed_addresses: Default::default(),
+            uncommitted_kills: Default::default(),
+            last_committed_kills: Default::default(),
+            checkpoint_observer: None,
+            issuance_ledger: Default::default(),
+            issuance_ledger_checkpoints: Default::default(),
+            known_no_code: Default::default(),
+            storage_point_prop_cache: Default::default(),
+            genesis_locked_tokens_cache: Default::default(),
+            check_evm_tokens: false,
+            strict_world_statistics: false,
         })
     }
 
+    /// Return the collateral change of `address` in `substate` in drip, as
+    /// `(increase, decrease)`. At most one of them is non-zero. See
+    /// `estimate_collateral_change` for how the changes would be settled.
+    pub fn collateral_change_in_drip(
+        &self, substate: &Substate, address: &Address,
+    ) -> (U256, U256) {
+        let (inc_collaterals, sub_collaterals) =
+            substate.get_collateral_change(address);
+        (
+            *DRIPS_PER_STORAGE_COLLATERAL_UNIT * inc_collaterals,
+            *DRIPS_PER_STORAGE_COLLATERAL_UNIT * sub_collaterals,
+        )
+    }
+
+    /// Estimates how `settle_collateral_for_all` would settle the collateral
+    /// changes of `substate`, including the initialization of CIP-107 and the
+    /// resulting collateral for storage of each owner, without changing the
+    /// state.
+    pub fn estimate_collateral_change(
+        &self, substate: &Substate, spec: &Spec,
+    ) -> DbResult<CollateralEstimation> {
+        let mut addresses: Vec<Address> = substate
+            .keys_for_collateral_changed()
+            .into_iter()
+            .filter(|address| {
+                self.has_pending_collateral_change(substate, address)
+            })
+            .cloned()
+            .collect();
+        addresses.sort();
+
+        let mut owners = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            let (charge, release) =
+                self.collateral_change_in_drip(substate, &address);
+            let is_contract =
+                self.is_contract_with_code(&address.with_native_space())?;
+            // Settle on a copy of the account.
+            let mut account = match self.read_native_account(&address)? {
+                Some(account) => account.clone_basic(),
+                None => {
+                    owners.push(OwnerCollateralEstimation {
+                        address,
+                        charge,
+                        release,
+                        ..Default::default()
+                    });
+                    continue;
+                }
+            };
+
+            let initializes_cip107 = spec.cip107
+                && address.is_contract_address()
+                && account.is_contract()
+                && !account.is_cip_107_initialized();
+            let (converted_from_balance, converted_from_collateral) =
+                if initializes_cip107 {
+                    let (from_balance, from_collateral, _) =
+                        account.initialize_cip107(self.storage_point_prop()?);
+                    (from_balance, from_collateral)
+                } else {
+                    (U256::zero(), U256::zero())
+                };
+            // The collateral exceeding the token collateral is burnt instead
+            // of being refunded.
+            let refundable =
+                std::cmp::min(release, account.token_collateral_for_storage());
+            if !refundable.is_zero() {
+                account.sub_collateral_for_storage(&refundable);
+            }
+            let available = if is_contract {
+                account.sponsor_info().sponsor_balance_for_collateral
+                    + account
+                        .sponsor_info()
+                        .storage_points
+                        .as_ref()
+                        .map_or(U256::zero(), |points| points.unused)
+            } else {
+                *account.balance()
+            };
+            let charged_storage_points = if charge <= available {
+                account.add_collateral_for_storage(&charge)
+            } else {
+                U256::zero()
+            };
+            owners.push(OwnerCollateralEstimation {
+                address,
+                charge,
+                release,
+                initializes_cip107,
+                available,
+                charged_storage_points,
+                converted_from_balance,
+                converted_from_collateral,
+                collateral_for_storage: account.collateral_for_storage(),
+            });
+        }
+        Ok(CollateralEstimation { owners })
+    }
+
+    /// Whether settling `substate` changes the storage collateral of
+    /// `address`. The occupied and released storage may cancel out.
+    pub fn has_pending_collateral_change(
+        &self, substate: &Substate, address: &Address,
+    ) -> bool {
+        substate.get_collateral_change(address) != (0, 0)
+    }
+
     /// Charges or refund storage collateral and update `total_storage_tokens`.
     fn settle_collateral_for_address(
-        &mut self, addr: &Address, substate: &Substate,
+        &mut self, addr: &Address, substate: &mut Substate,
         tracer: &mut dyn StateTracer, spec: &Spec, dry_run_no_charge: bool,
     ) -> DbResult<CollateralCheckResult>
     {
         let addr_with_space = addr.with_native_space();
-        let (inc_collaterals, sub_collaterals) =
-            substate.get_collateral_change(addr);
-        let (inc, sub) = (
-            *DRIPS_PER_STORAGE_COLLATERAL_UNIT * inc_collaterals,
-            *DRIPS_PER_STORAGE_COLLATERAL_UNIT * sub_collaterals,
-        );
+        let (inc, sub) = self.collateral_change_in_drip(substate, addr);
 
         let is_contract = self.is_contract_with_code(&addr_with_space)?;
 
@@ -1356,8 +3591,12 @@ impl State {
         }
 
         if !sub.is_zero() {
-            let storage_point_refund =
+            let (storage_point_refund, burnt) =
                 self.sub_collateral_for_storage(addr, &sub)?;
+            substate.record_collateral_settlement(
+                &(sub - burnt - storage_point_refund),
+                &burnt,
+            );
             tracer.trace_internal_transfer(
                 /* from */ AddressPocket::StorageCollateral(*addr),
                 /* to */
@@ -1370,17 +3609,31 @@ impl State {
             );
         }
         if !inc.is_zero() && !dry_run_no_charge {
-            let balance = if is_contract {
-                self.sponsor_balance_for_collateral(addr)?
-                    + self.avaliable_storage_point_for_collateral(addr)?
+            // Read after the CIP-107 conversion and the refund above, so the
+            // funds match the traced transfers.
+            let source = if is_contract {
+                CollateralSource::Sponsor {
+                    sponsor_balance: self.sponsor_balance_for_collateral(addr)?,
+                    storage_points: self
+                        .available_storage_point_for_collateral(addr)?,
+                }
             } else {
-                self.balance(&addr_with_space)?
+                CollateralSource::Balance
+            };
+            let balance = match source {
+                CollateralSource::Sponsor {
+                    sponsor_balance,
+                    storage_points,
+                } => sponsor_balance + storage_points,
+                CollateralSource::Balance => self.balance(&addr_with_space)?,
             };
             // sponsor_balance is not enough to cover storage incremental.
             if inc > balance {
                 return Ok(CollateralCheckResult::NotEnoughBalance {
                     required: inc,
                     got: balance,
+                    address: *addr,
+                    source,
                 });
             }
 
@@ -1424,8 +3677,10 @@ impl State {
         let invalidated_storage = self
             .read_account(contract)?
             .map_or(false, |acc| acc.invalidated_storage());
+        self.invalidate_genesis_locked_cache(contract);
         Self::update_cache(
             self.cache.get_mut(),
+            &self.cache_counters,
             self.checkpoints.get_mut(),
             contract,
             AccountEntry::new_dirty(Some(OverlayAccount::new_contract(
@@ -1438,6 +3693,44 @@ impl State {
         Ok(())
     }
 
+    /// Install a fully specified account at `address` for the genesis
+    /// builders, replacing the existing one. The storage entries are owned by
+    /// the account itself, and `account.code_hash` is replaced by the hash of
+    /// `code` if given. The account is CIP-107 initialized iff
+    /// `account.sponsor_info.storage_points` is set. The world statistics are
+    /// not updated.
+    pub fn import_account(
+        &mut self, address: &AddressWithSpace, account: Account,
+        storage: Vec<(Vec<u8>, U256)>, code: Option<Bytes>,
+    ) -> DbResult<()>
+    {
+        let invalidated_storage = self
+            .read_account(address)?
+            .map_or(false, |acc| acc.invalidated_storage());
+        let mut overlay_account =
+            OverlayAccount::new_imported(address, account, invalidated_storage);
+        if let Some(code) = code {
+            overlay_account.init_code(code, address.address);
+            overlay_account.set_storage_layout(STORAGE_LAYOUT_REGULAR_V0);
+        }
+        if !storage.is_empty() {
+            self.invalidate_system_storage_cache(address);
+        }
+        for (key, value) in storage {
+            overlay_account.set_storage(key, value, address.address);
+        }
+        self.known_no_code.get_mut().remove(address);
+        self.invalidate_genesis_locked_cache(address);
+        Self::update_cache(
+            self.cache.get_mut(),
+            &self.cache_counters,
+            self.checkpoints.get_mut(),
+            address,
+            AccountEntry::new_dirty(Some(overlay_account)),
+        );
+        Ok(())
+    }
+
     #[cfg(test)]
     pub fn new_contract_with_code(
         &mut self, contract: &AddressWithSpace, balance: U256,
@@ -1465,25 +3758,40 @@ impl State {
         })
     }
 
+    /// Returns the refunded storage points and the burnt collateral.
     fn sub_collateral_for_storage(
         &mut self, address: &Address, by: &U256,
-    ) -> DbResult<U256> {
+    ) -> DbResult<(U256, U256)> {
         let collateral = self.token_collateral_for_storage(address)?;
         let refundable = if by > &collateral { &collateral } else { by };
         let burnt = *by - *refundable;
         let storage_point_refund = if !refundable.is_zero() {
-            self.require_or_new_basic_account(&address.with_native_space())?
-                .sub_collateral_for_storage(refundable)
+            self.read_native_account(address)?
+                .map_or(U256::zero(), |account| {
+                    account.storage_point_refund(refundable)
+                })
         } else {
             U256::zero()
         };
 
-        self.world_statistics.total_storage_tokens -=
-            *by - storage_point_refund;
+        let storage_token_refund = *by - storage_point_refund;
+        // Checked before the account is changed, so that an error leaves the
+        // state unchanged.
+        let total_storage_tokens = checked_sub_statistic(
+            "total_storage_tokens",
+            self.world_statistics.total_storage_tokens,
+            storage_token_refund,
+            self.strict_world_statistics,
+        )?;
+        if !refundable.is_zero() {
+            self.require_or_new_basic_account(&address.with_native_space())?
+                .sub_collateral_for_storage(refundable);
+        }
+        self.world_statistics.total_storage_tokens = total_storage_tokens;
         self.world_statistics.used_storage_points -= storage_point_refund;
         self.world_statistics.total_issued_tokens -= burnt;
 
-        Ok(storage_point_refund)
+        Ok((storage_point_refund, burnt))
     }
 
     fn initialize_cip107(
@@ -1524,6 +3832,30 @@ impl State {
         }
     }
 
+    /// Initialize CIP-107 for each contract in `addresses` and return the
+    /// storage points converted from its sponsor balance and from its
+    /// collateral. Addresses which are not contracts are skipped with zero
+    /// amounts. The world statistics end up the same as after calling
+    /// `initialize_cip107` for each contract in turn.
+    pub fn batch_initialize_cip107(
+        &mut self, addresses: &[Address],
+    ) -> DbResult<Vec<(Address, U256, U256)>> {
+        let mut result = Vec::with_capacity(addresses.len());
+        for address in addresses {
+            let is_contract = self
+                .read_native_account(address)?
+                .map_or(false, |acc| acc.is_contract());
+            if !is_contract {
+                result.push((*address, U256::zero(), U256::zero()));
+                continue;
+            }
+            let (from_balance, from_collateral) =
+                self.initialize_cip107(address)?;
+            result.push((*address, from_balance, from_collateral));
+        }
+        Ok(result)
+    }
+
     #[allow(dead_code)]
     pub fn touch(&mut self, address: &AddressWithSpace) -> DbResult<()> {
         drop(self.require_exists(address, false)?);
@@ -1564,6 +3896,15 @@ impl State {
     pub fn initialize_or_update_dao_voted_params(
         &mut self, set_pos_staking: bool,
     ) -> DbResult<()> {
+        self.initialize_or_update_dao_voted_params_detailed(set_pos_staking)?;
+        Ok(())
+    }
+
+    /// Same as `initialize_or_update_dao_voted_params`, but returns the new
+    /// parameters.
+    pub fn initialize_or_update_dao_voted_params_detailed(
+        &mut self, set_pos_staking: bool,
+    ) -> DbResult<DaoVotedParams> {
         let vote_count = get_settled_param_vote_count(self).expect("db error");
         debug!(
             "initialize_or_update_dao_voted_params: vote_count={:?}",
@@ -1588,37 +3929,30 @@ impl State {
             );
 
         // Initialize or update PoW base reward.
-        match self.db.get_pow_base_reward()? {
+        let pow_base_reward = match self.pow_base_reward_opt()? {
             Some(old_pow_base_reward) => {
-                self.db.set_pow_base_reward(
-                    vote_count.pow_base_reward.compute_next_params(
-                        old_pow_base_reward,
-                        pos_staking_for_votes,
-                    ),
-                    None,
-                )?;
-            }
-            None => {
-                self.db.set_pow_base_reward(
-                    (MINING_REWARD_TANZANITE_IN_UCFX * ONE_UCFX_IN_DRIP).into(),
-                    None,
-                )?;
+                vote_count.pow_base_reward.compute_next_params(
+                    old_pow_base_reward,
+                    pos_staking_for_votes,
+                )
             }
-        }
+            None => (MINING_REWARD_TANZANITE_IN_UCFX * ONE_UCFX_IN_DRIP).into(),
+        };
+        self.db.set_pow_base_reward(pow_base_reward, None)?;
 
         // Only write storage_collateral_refund_ratio if it has been set in the
         // db. This keeps the state unchanged before cip107 is enabled.
+        let mut new_storage_point_prop = None;
         if let Some(old_storage_point_prop) =
             self.get_system_storage_opt(&storage_point_prop())?
         {
             debug!("old_storage_point_prop: {}", old_storage_point_prop);
-            self.set_system_storage(
-                storage_point_prop().to_vec(),
-                vote_count.storage_point_prop.compute_next_params(
-                    old_storage_point_prop,
-                    pos_staking_for_votes,
-                ),
-            )?;
+            let prop = vote_count.storage_point_prop.compute_next_params(
+                old_storage_point_prop,
+                pos_staking_for_votes,
+            );
+            self.set_system_storage(storage_point_prop().to_vec(), prop)?;
+            new_storage_point_prop = Some(prop);
         }
         debug!(
             "pos interest: {} base_reward:{:?}",
@@ -1628,7 +3962,13 @@ impl State {
 
         settle_current_votes(self, set_pos_staking)?;
 
-        Ok(())
+        Ok(DaoVotedParams {
+            interest_rate_per_block: self
+                .world_statistics
+                .interest_rate_per_block,
+            pow_base_reward,
+            storage_point_prop: new_storage_point_prop,
+        })
     }
 
     fn commit_world_statistics(
@@ -1724,7 +4064,7 @@ impl State {
     // creates circular dep.  if it proves impossible to break the loop we
     // use associated types for the tx pool.
     pub fn commit_and_notify(
-        &mut self, epoch_id: EpochId, txpool: &SharedTransactionPool,
+        &mut self, epoch_id: EpochId, notifier: &StateNotifier,
         debug_record: Option<&mut ComputeEpochDebugRecord>,
     ) -> DbResult<StateRootWithAuxInfo>
     {
@@ -1732,25 +4072,25 @@ impl State {
 
         debug!("Notify epoch[{}]", epoch_id);
 
-        let mut accounts_for_txpool = vec![];
+        let (accounts_for_txpool, deleted_for_txpool) =
+            self.txpool_notification();
+        notifier.notify(epoch_id, accounts_for_txpool, deleted_for_txpool);
+
+        Ok(result)
+    }
+
+    /// Splits `accounts_to_notify` into the updated accounts and the
+    /// addresses of the deleted accounts.
+    fn txpool_notification(&self) -> (Vec<Account>, Vec<AddressWithSpace>) {
+        let mut updated = vec![];
+        let mut deleted = vec![];
         for updated_or_deleted in &self.accounts_to_notify {
-            // if the account is updated.
-            if let Ok(account) = updated_or_deleted {
-                accounts_for_txpool.push(account.clone());
+            match updated_or_deleted {
+                Ok(account) => updated.push(account.clone()),
+                Err(address) => deleted.push(*address),
             }
         }
-        {
-            // TODO: use channel to deliver the message.
-            let txpool_clone = txpool.clone();
-            std::thread::Builder::new()
-                .name("txpool_update_state".into())
-                .spawn(move || {
-                    txpool_clone.notify_modified_accounts(accounts_for_txpool);
-                })
-                .expect("can not notify tx pool to start state");
-        }
-
-        Ok(result)
+        (updated, deleted)
     }
 
     fn remove_whitelists_for_contract<AM: access_mode::AccessMode>(
@@ -1827,6 +4167,75 @@ impl State {
         Ok(true)
     }
 
+    /// Load the uncached accounts of `accounts` from the db in parallel on
+    /// `pool`, together with the parts required by each of them, then insert
+    /// them into the cache under a single write lock.
+    pub fn prefetch_accounts(
+        &self, accounts: &[(AddressWithSpace, RequireCache)], pool: &ThreadPool,
+    ) -> DbResult<()> {
+        let uncached = {
+            let cache = self.cache.read();
+            let mut uncached = BTreeMap::<_, Vec<_>>::new();
+            for (address, require) in accounts {
+                if !cache.contains_key(address) {
+                    uncached.entry(*address).or_default().push(*require);
+                }
+            }
+            uncached.into_iter().collect::<Vec<_>>()
+        };
+        if uncached.is_empty() {
+            return Ok(());
+        }
+
+        let db = &self.db;
+        let loaded = pool.install(|| {
+            uncached
+                .par_iter()
+                .map(|(address, requires)| {
+                    (
+                        *address,
+                        Self::load_account_with_cache(db, address, requires),
+                    )
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let mut cache = self.cache.write();
+        let mut result = Ok(());
+        for (address, maybe_account) in loaded {
+            match maybe_account {
+                Ok(maybe_account) => {
+                    if Self::insert_cache_if_fresh_account(
+                        &mut *cache,
+                        &self.cache_counters,
+                        &address,
+                        maybe_account,
+                    ) {
+                        self.touch_cache_entry(&address, &cache[&address]);
+                    }
+                }
+                Err(e) => result = Err(e),
+            }
+        }
+        self.evict_cache_entries(&mut *cache, None);
+        result
+    }
+
+    fn load_account_with_cache(
+        db: &StateDb, address: &AddressWithSpace, requires: &[RequireCache],
+    ) -> DbResult<Option<OverlayAccount>> {
+        let mut maybe_account = db
+            .get_account(address)?
+            .map(|acc| OverlayAccount::from_loaded(address, acc));
+        if let Some(account) = &mut maybe_account {
+            // A part which fails to load is loaded again on access.
+            for require in requires {
+                Self::update_account_cache(*require, account, db)?;
+            }
+        }
+        Ok(maybe_account)
+    }
+
     // FIXME: rewrite this method before enable it for the first time, because
     //  there have been changes to kill_account and collateral processing.
     #[allow(unused)]
@@ -1876,8 +4285,8 @@ impl State {
         Ok(())
     }
 
-    /// Get the value of storage at a specific checkpoint.
-    #[cfg(test)]
+    /// Get the value of storage at the creation of the checkpoint with
+    /// `start_checkpoint_index`, or `None` if the checkpoint is not open.
     pub fn checkpoint_storage_at(
         &self, start_checkpoint_index: usize, address: &AddressWithSpace,
         key: &Vec<u8>,
@@ -1906,7 +4315,7 @@ impl State {
                     })) => {
                         if let Some(value) = account.cached_storage_at(key) {
                             return Ok(Some(value));
-                        } else if account.is_newly_created_contract() {
+                        } else if account.fresh_storage() {
                             return Ok(Some(U256::zero()));
                         } else {
                             kind = Some(ReturnKind::OriginalAt);
@@ -1955,12 +4364,17 @@ impl State {
 
     fn update_cache(
         cache: &mut HashMap<AddressWithSpace, AccountEntry>,
+        counters: &CacheCounters,
         checkpoints: &mut Vec<HashMap<AddressWithSpace, Option<AccountEntry>>>,
         address: &AddressWithSpace, account: AccountEntry,
     )
     {
         let is_dirty = account.is_dirty();
+        counters.add(&account);
         let old_value = cache.insert(*address, account);
+        if let Some(old) = &old_value {
+            counters.remove(old);
+        }
         if is_dirty {
             if let Some(ref mut checkpoint) = checkpoints.last_mut() {
                 checkpoint.entry(*address).or_insert(old_value);
@@ -1970,11 +4384,14 @@ impl State {
 
     fn insert_cache_if_fresh_account(
         cache: &mut HashMap<AddressWithSpace, AccountEntry>,
-        address: &AddressWithSpace, maybe_account: Option<OverlayAccount>,
+        counters: &CacheCounters, address: &AddressWithSpace,
+        maybe_account: Option<OverlayAccount>,
     ) -> bool
     {
         if !cache.contains_key(address) {
-            cache.insert(*address, AccountEntry::new_clean(maybe_account));
+            let entry = AccountEntry::new_clean(maybe_account);
+            counters.add(&entry);
+            cache.insert(*address, entry);
             true
         } else {
             false
@@ -2011,9 +4428,11 @@ impl State {
             if let Some(account) = &guard.account {
                 let needs_update = Self::needs_update(require, account);
                 if !needs_update {
+                    self.touch_cache_entry(address, &guard);
                     return Ok(Some(as_account_guard(guard)));
                 }
             } else {
+                self.touch_cache_entry(address, &guard);
                 return Ok(None);
             }
         }
@@ -2037,6 +4456,7 @@ impl State {
                     RwLockUpgradableReadGuard::upgrade(upgradable_lock);
                 Self::insert_cache_if_fresh_account(
                     &mut *cache_write_lock,
+                    &self.cache_counters,
                     address,
                     maybe_loaded_acc,
                 );
@@ -2048,13 +4468,22 @@ impl State {
         let cache = &mut *cache_write_lock;
         let account = cache.get_mut(address).unwrap();
         if let Some(maybe_acc) = &mut account.account {
-            if !Self::update_account_cache(require, maybe_acc, &self.db)? {
+            let code_bytes = maybe_acc.cached_code_size();
+            let cached =
+                Self::update_account_cache(require, maybe_acc, &self.db)?;
+            self.cache_counters
+                .update_code_bytes(code_bytes, maybe_acc.cached_code_size());
+            if !cached {
                 return Err(DbErrorKind::IncompleteDatabase(
                     maybe_acc.address().address.clone(),
                 )
                 .into());
             }
         }
+        if self.cache_byte_budget.is_some() {
+            self.touch_cache_entry(address, &cache[address]);
+            self.evict_cache_entries(cache, Some(address));
+        }
 
         let entry_guard = RwLockReadGuard::map(
             RwLockWriteGuard::downgrade(cache_write_lock),
@@ -2099,6 +4528,7 @@ impl State {
         &self, address: &AddressWithSpace, require_code: bool, default: F,
     ) -> DbResult<MappedRwLockWriteGuard<OverlayAccount>>
     where F: FnOnce(&AddressWithSpace) -> DbResult<OverlayAccount> {
+        self.invalidate_genesis_locked_cache(address);
         let mut cache;
         if !self.cache.read().contains_key(address) {
             let account = self
@@ -2106,7 +4536,12 @@ impl State {
                 .get_account(address)?
                 .map(|acc| OverlayAccount::from_loaded(address, acc));
             cache = self.cache.write();
-            Self::insert_cache_if_fresh_account(&mut *cache, address, account);
+            Self::insert_cache_if_fresh_account(
+                &mut *cache,
+                &self.cache_counters,
+                address,
+                account,
+            );
         } else {
             cache = self.cache.write();
         };
@@ -2123,21 +4558,34 @@ impl State {
             .expect("entry known to exist in the cache");
 
         // Set the dirty flag.
+        self.cache_counters.mark_dirty(entry);
+        if self.cache_byte_budget.is_some() {
+            self.cache_lru.lock().untrack(address);
+        }
         entry.state = AccountState::Dirty;
 
         if entry.account.is_none() {
             entry.account = Some(default(address)?);
+            self.cache_counters.update_code_bytes(
+                0,
+                CacheCounters::code_bytes_of(entry),
+            );
         }
 
         if require_code {
-            if !Self::update_account_cache(
+            let account = entry
+                .account
+                .as_mut()
+                .expect("Required account must exist.");
+            let code_bytes = account.cached_code_size();
+            let cached = Self::update_account_cache(
                 RequireCache::Code,
-                entry
-                    .account
-                    .as_mut()
-                    .expect("Required account must exist."),
+                account,
                 &self.db,
-            )? {
+            )?;
+            self.cache_counters
+                .update_code_bytes(code_bytes, account.cached_code_size());
+            if !cached {
                 bail!(DbErrorKind::IncompleteDatabase(address.address));
             }
         }
@@ -2152,7 +4600,37 @@ impl State {
     }
 
     fn storage_point_prop(&self) -> DbResult<U256> {
-        Ok(self.get_system_storage(&storage_point_prop())?)
+        if let Some(prop) = *self.storage_point_prop_cache.read() {
+            return Ok(prop);
+        }
+        let prop = self.get_system_storage(&storage_point_prop())?;
+        *self.storage_point_prop_cache.write() = Some(prop);
+        Ok(prop)
+    }
+
+    /// Whether the accounts at `a` and `b` have the same balance, nonce,
+    /// code, storage root, staking balance, collateral and sponsor info.
+    /// Fails if either account has uncommitted storage changes.
+    #[cfg(any(test, feature = "testonly_code"))]
+    pub fn accounts_equal(
+        &self, a: &AddressWithSpace, b: &AddressWithSpace,
+    ) -> DbResult<bool> {
+        let load = |address: &AddressWithSpace| -> DbResult<_> {
+            let account = match self.read_account(address)? {
+                Some(account) => account.as_account(),
+                None => return Ok(None),
+            };
+            Ok(Some((
+                account.balance,
+                account.nonce,
+                self.code(address)?,
+                self.storage_root(address)?,
+                account.staking_balance,
+                account.collateral_for_storage,
+                account.sponsor_info,
+            )))
+        };
+        Ok(load(a)? == load(b)?)
     }
 
     #[cfg(any(test, feature = "testonly_code"))]
@@ -2160,9 +4638,15 @@ impl State {
         assert!(self.checkpoints.get_mut().is_empty());
         assert!(self.world_statistics_checkpoints.get_mut().is_empty());
         self.cache.get_mut().clear();
-        self.world_statistics.interest_rate_per_block =
-            self.db.get_annual_interest_rate().expect("no db error")
-                / U256::from(BLOCKS_PER_YEAR);
+        self.cache_counters.reset();
+        self.cache_lru.get_mut().clear();
+        self.issuance_ledger.clear();
+        self.known_no_code.get_mut().clear();
+        self.storage_point_prop_cache.get_mut().take();
+        self.genesis_locked_tokens_cache.get_mut().take();
+        self.world_statistics.interest_rate_per_block = annual_to_per_block(
+            self.db.get_annual_interest_rate().expect("no db error"),
+        );
         self.world_statistics.accumulate_interest_rate =
             self.db.get_accumulate_interest_rate().expect("no db error");
         self.world_statistics.total_issued_tokens =
@@ -2185,6 +4669,8 @@ impl State {
             self.db.get_used_storage_points().expect("no db error");
         self.world_statistics.converted_storage_points =
             self.db.get_converted_storage_points().expect("no db error");
+        self.committed_world_statistics = self.world_statistics;
+        self.last_statistics_delta = None;
     }
 }
 
@@ -2198,6 +4684,66 @@ trait AccountEntryProtectedMethods {
     fn code_owner(&self) -> Option<Address>;
 }
 
+/// The PoS interest accrued in one block. It exactly equals to the floor of
+/// pos_amount * 4% / blocks_per_year / sqrt(pos_amount/total_circulating),
+/// where the 4% annual rate is scaled by `interest_rate_per_block`.
+pub fn pos_interest_increment(
+    circulating: U256, pos_staking: U256, interest_rate_per_block: U256,
+    blocks_per_year: u64,
+) -> U256
+{
+    sqrt_u256(
+        circulating
+            * pos_staking
+            * interest_rate_per_block
+            * interest_rate_per_block,
+    ) / (U256::from(blocks_per_year)
+        * INVERSE_INTEREST_RATE
+        * *INITIAL_INTEREST_RATE_PER_BLOCK)
+}
+
+/// The interest rate per block of the annual interest rate stored in the db.
+fn annual_to_per_block(annual: U256) -> U256 {
+    annual / U256::from(BLOCKS_PER_YEAR)
+}
+
+fn invalid_pos_unlock(identifier: H256, number: u64) -> DbErrorKind {
+    DbErrorKind::InvalidPosIndexStatus(
+        identifier,
+        format!(
+            "invalid unlock to {} votes, the identifier is not registered or \
+             its unlocked votes decrease",
+            number
+        ),
+    )
+}
+
+/// Returns the world statistic `value` minus `amount`. An underflow means the
+/// state is inconsistent: it panics in strict mode and is otherwise logged
+/// and returned as an error.
+fn checked_sub_statistic(
+    statistic: &'static str, value: U256, amount: U256, strict: bool,
+) -> DbResult<U256> {
+    match value.checked_sub(amount) {
+        Some(result) => Ok(result),
+        None => {
+            if strict {
+                panic!(
+                    "{} {} is less than the subtracted amount {}",
+                    statistic, value, amount
+                );
+            }
+            error!(
+                "{} {} is less than the subtracted amount {}",
+                statistic, value, amount
+            );
+            bail!(DbErrorKind::WorldStatisticsUnderflow(
+                statistic, value, amount
+            ))
+        }
+    }
+}
+
 fn sqrt_u256(input: U256) -> U256 {
     let bits = input.bits();
     if bits <= 64 {
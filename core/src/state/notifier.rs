@@ -0,0 +1,171 @@
+// Copyright 2020 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+use crate::transaction_pool::SharedTransactionPool;
+use cfx_types::AddressWithSpace;
+use crossbeam_channel::{bounded, Receiver, Sender};
+use parking_lot::Mutex;
+use primitives::{Account, EpochId};
+use std::{
+    collections::HashSet,
+    thread::{self, JoinHandle},
+};
+
+/// The number of pending epochs after which `StateNotifier::notify` blocks.
+pub const STATE_NOTIFIER_CHANNEL_SIZE: usize = 64;
+
+/// Receives the accounts modified by an epoch, together with the latest epoch
+/// id they were collected from.
+pub type StateNotificationHandler =
+    Box<dyn FnMut(EpochId, Vec<Account>, Vec<AddressWithSpace>) + Send>;
+
+/// Delivers the accounts modified by committed epochs on a single long-lived
+/// worker thread, in the order of the commits. When the worker falls behind,
+/// the pending epochs are merged into one notification.
+pub struct StateNotifier {
+    sender: Mutex<Option<Sender<StateNotification>>>,
+    join_handle: Mutex<Option<JoinHandle<()>>>,
+}
+
+enum StateNotification {
+    Modified {
+        epoch_id: EpochId,
+        accounts: Vec<Account>,
+        deleted: Vec<AddressWithSpace>,
+    },
+    Flush(Sender<()>),
+}
+
+/// The modifications of consecutive epochs merged together.
+struct PendingNotification {
+    epoch_id: EpochId,
+    accounts: Vec<Account>,
+    deleted: Vec<AddressWithSpace>,
+}
+
+impl PendingNotification {
+    fn merge(
+        &mut self, epoch_id: EpochId, accounts: Vec<Account>,
+        deleted: Vec<AddressWithSpace>,
+    )
+    {
+        // The later epoch decides whether an account is updated or deleted.
+        let updated: HashSet<_> =
+            accounts.iter().map(|account| *account.address()).collect();
+        let deleted_set: HashSet<_> = deleted.iter().cloned().collect();
+        self.accounts
+            .retain(|account| !deleted_set.contains(account.address()));
+        self.deleted.retain(|address| !updated.contains(address));
+        self.epoch_id = epoch_id;
+        self.accounts.extend(accounts);
+        self.deleted.extend(deleted);
+    }
+}
+
+impl StateNotifier {
+    pub fn new(handler: StateNotificationHandler) -> Self {
+        let (sender, receiver) = bounded(STATE_NOTIFIER_CHANNEL_SIZE);
+        let join_handle = thread::Builder::new()
+            .name("txpool_update_state".into())
+            .spawn(move || Self::run(receiver, handler))
+            .expect("can not start state notifier");
+        Self {
+            sender: Mutex::new(Some(sender)),
+            join_handle: Mutex::new(Some(join_handle)),
+        }
+    }
+
+    pub fn new_for_txpool(txpool: SharedTransactionPool) -> Self {
+        Self::new(Box::new(move |_epoch_id, accounts, deleted| {
+            txpool.notify_modified_accounts(accounts, deleted)
+        }))
+    }
+
+    /// Queues the modifications of `epoch_id`. Blocks only if the channel is
+    /// full.
+    pub fn notify(
+        &self, epoch_id: EpochId, accounts: Vec<Account>,
+        deleted: Vec<AddressWithSpace>,
+    )
+    {
+        if let Some(sender) = &*self.sender.lock() {
+            // ignore error, the worker only stops on shutdown.
+            let _ = sender.send(StateNotification::Modified {
+                epoch_id,
+                accounts,
+                deleted,
+            });
+        }
+    }
+
+    /// Waits until all the queued modifications are delivered.
+    pub fn flush(&self) {
+        let (done_sender, done_receiver) = bounded(1);
+        let sent = match &*self.sender.lock() {
+            Some(sender) => {
+                sender.send(StateNotification::Flush(done_sender)).is_ok()
+            }
+            None => false,
+        };
+        if sent {
+            let _ = done_receiver.recv();
+        }
+    }
+
+    /// Delivers the queued modifications and stops the worker.
+    pub fn stop(&self) {
+        // Dropping the sender disconnects the channel after the queued
+        // notifications.
+        self.sender.lock().take();
+        if let Some(join_handle) = self.join_handle.lock().take() {
+            join_handle.join().ok();
+        }
+    }
+
+    fn run(
+        receiver: Receiver<StateNotification>,
+        mut handler: StateNotificationHandler,
+    )
+    {
+        while let Ok(notification) = receiver.recv() {
+            let mut pending: Option<PendingNotification> = None;
+            let mut next = Some(notification);
+            while let Some(notification) = next {
+                match notification {
+                    StateNotification::Modified {
+                        epoch_id,
+                        accounts,
+                        deleted,
+                    } => match &mut pending {
+                        Some(pending) => {
+                            pending.merge(epoch_id, accounts, deleted)
+                        }
+                        None => {
+                            pending = Some(PendingNotification {
+                                epoch_id,
+                                accounts,
+                                deleted,
+                            })
+                        }
+                    },
+                    StateNotification::Flush(done) => {
+                        if let Some(p) = pending.take() {
+                            handler(p.epoch_id, p.accounts, p.deleted);
+                        }
+                        let _ = done.send(());
+                    }
+                }
+                // Merge whatever has been queued in the meantime.
+                next = receiver.try_recv().ok();
+            }
+            if let Some(p) = pending {
+                handler(p.epoch_id, p.accounts, p.deleted);
+            }
+        }
+    }
+}
+
+impl Drop for StateNotifier {
+    fn drop(&mut self) { self.stop(); }
+}
@@ -0,0 +1,63 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+/// The changes made to the state since a checkpoint, returned by
+/// `State::checkpoint_diff`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StateDiff {
+    /// The changed accounts, sorted by address.
+    pub accounts: Vec<AccountDiff>,
+}
+
+impl StateDiff {
+    pub fn is_empty(&self) -> bool { self.accounts.is_empty() }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDiff {
+    pub space: Space,
+    pub address: Address,
+    /// The account at the checkpoint, `None` if it did not exist.
+    pub before: Option<AccountSummary>,
+    /// The current account, `None` if it does not exist.
+    pub after: Option<AccountSummary>,
+    /// The storage keys whose values changed, sorted.
+    pub storage_keys: Vec<Bytes>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountSummary {
+    pub balance: U256,
+    pub nonce: U256,
+    pub code_hash: H256,
+}
+
+impl From<&OverlayAccount> for AccountSummary {
+    fn from(account: &OverlayAccount) -> Self {
+        AccountSummary {
+            balance: *account.balance(),
+            nonce: *account.nonce(),
+            code_hash: account.code_hash(),
+        }
+    }
+}
+
+impl From<&Account> for AccountSummary {
+    fn from(account: &Account) -> Self {
+        AccountSummary {
+            balance: account.balance,
+            nonce: account.nonce,
+            code_hash: account.code_hash,
+        }
+    }
+}
+
+use super::OverlayAccount;
+use crate::bytes::Bytes;
+use cfx_types::{Address, Space, H256, U256};
+use primitives::Account;
+use serde::Serialize;
@@ -2,22 +2,44 @@
 // Conflux is free software and distributed under GNU General Public License.
 // See http://www.gnu.org/licenses/
 
-use super::{CleanupMode, CollateralCheckResult, State, Substate};
+use super::{
+    annual_to_per_block, pos_interest_increment, AccountEntry, CacheCounters,
+    CacheStats, CheckpointEvent, CleanupMode, CollateralCheckResult,
+    CollateralSource, PosDistributionSummary, State, StateNotifier,
+    StatisticChange, StoragePointsInfo, Substate,
+    COMMISSION_PRIVILEGE_SPECIAL_KEY,
+};
 use crate::{
-    spec::genesis::DEV_GENESIS_KEY_PAIR,
+    executive::internal_contract::{
+        pos_internal_entries, storage_point_prop, IndexStatus,
+    },
+    observer::ExecutiveTracer,
+    spec::genesis::{
+        genesis_contract_address_four_year, genesis_contract_address_two_year,
+        DEV_GENESIS_KEY_PAIR,
+    },
     test_helpers::get_state_for_genesis_write, vm::Spec,
 };
-use cfx_parameters::{consensus::ONE_CFX_IN_DRIP, staking::*};
-use cfx_statedb::StateDb;
+use cfx_parameters::{
+    consensus::ONE_CFX_IN_DRIP,
+    internal_contract_addresses::POS_REGISTER_CONTRACT_ADDRESS, staking::*,
+};
+use cfx_statedb::{ErrorKind as DbErrorKind, StateDb, StateDbExt};
 use cfx_storage::{
-    tests::new_state_manager_for_unit_test, StateIndex, StorageManager,
-    StorageManagerTrait,
+    tests::new_state_manager_for_unit_test, StateIndex, StateProof,
+    StorageManager, StorageManagerTrait,
 };
 use cfx_types::{
-    address_util::AddressUtil, Address, AddressSpaceUtil, BigEndianHash, U256,
+    address_util::AddressUtil, Address, AddressSpaceUtil, AddressWithSpace,
+    BigEndianHash, H256, U256,
 };
+use diem_types::term_state::MAX_TERM_POINTS;
 use keccak_hash::{keccak, KECCAK_EMPTY};
-use primitives::{EpochId, StorageKey, StorageLayout};
+use parking_lot::Mutex;
+use primitives::{
+    account::StoragePoints, storage::STORAGE_LAYOUT_REGULAR_V0, Account,
+    EpochId, StorageKey, StorageKeyWithSpace, StorageLayout,
+};
 use std::sync::Arc;
 
 #[cfg(test)]
@@ -68,7 +90,7 @@ fn checkpoint_basic() {
         U256::from(1000)
     );
     assert_eq!(state.total_storage_tokens(), U256::from(1000));
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     assert_eq!(
         state.balance(&address_with_space).unwrap(),
         U256::from(69u64)
@@ -93,7 +115,7 @@ fn checkpoint_basic() {
         state.balance(&address_with_space).unwrap(),
         U256::from(1070u64)
     );
-    state.revert_to_checkpoint();
+    state.revert_to_checkpoint().unwrap();
     assert_eq!(
         state.balance(&address_with_space).unwrap(),
         U256::from(69u64)
@@ -139,7 +161,7 @@ fn checkpoint_nested() {
         state.balance(&address_with_space).unwrap(),
         U256::from(69u64)
     );
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     assert_eq!(state.total_storage_tokens(), U256::from(1000));
     assert_eq!(
         state.collateral_for_storage(&address).unwrap(),
@@ -149,7 +171,7 @@ fn checkpoint_nested() {
         state.balance(&address_with_space).unwrap(),
         U256::from(69u64)
     );
-    state.revert_to_checkpoint();
+    state.revert_to_checkpoint().unwrap();
     assert_eq!(state.balance(&address_with_space).unwrap(), U256::from(0));
     assert_eq!(state.total_storage_tokens(), U256::from(0));
     assert_eq!(
@@ -158,6 +180,41 @@ fn checkpoint_nested() {
     );
 }
 
+#[test]
+fn checkpoint_observer_events() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events_clone = events.clone();
+    state.set_checkpoint_observer(Some(Box::new(move |event, depth| {
+        events_clone.lock().push((event, depth))
+    })));
+    state.checkpoint();
+    state.checkpoint();
+    state.discard_checkpoint().unwrap();
+    state.checkpoint();
+    state.revert_to_checkpoint().unwrap();
+    state.revert_to_checkpoint().unwrap();
+    // No checkpoint left, so nothing is reported.
+    assert!(state.discard_checkpoint().is_err());
+    assert_eq!(
+        *events.lock(),
+        vec![
+            (CheckpointEvent::Create, 1),
+            (CheckpointEvent::Create, 2),
+            (CheckpointEvent::Discard, 1),
+            (CheckpointEvent::Create, 2),
+            (CheckpointEvent::Revert, 1),
+            (CheckpointEvent::Revert, 0),
+        ]
+    );
+
+    state.set_checkpoint_observer(None);
+    state.checkpoint();
+    state.discard_checkpoint().unwrap();
+    assert_eq!(events.lock().len(), 6);
+}
+
 #[test]
 fn checkpoint_revert_to_get_storage_at() {
     let storage_manager = new_state_manager_for_unit_test();
@@ -192,7 +249,7 @@ fn checkpoint_revert_to_get_storage_at() {
         U256::one()
     );
 
-    state.revert_to_checkpoint();
+    state.revert_to_checkpoint().unwrap();
     assert_eq!(
         state
             .checkpoint_storage_at(c0, &address_with_space, &key)
@@ -307,7 +364,7 @@ fn checkpoint_from_empty_get_storage_at() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint(); // Commit/discard c5.
+    state.discard_checkpoint().unwrap(); // Commit/discard c5.
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
     assert_eq!(state.total_storage_tokens(), U256::from(0));
@@ -338,7 +395,7 @@ fn checkpoint_from_empty_get_storage_at() {
         Some(U256::from(3))
     );
 
-    state.revert_to_checkpoint(); // Revert to c4.
+    state.revert_to_checkpoint().unwrap(); // Revert to c4.
     substates.pop();
     assert_eq!(state.total_storage_tokens(), U256::from(0));
     assert_eq!(state.collateral_for_storage(&a).unwrap(), U256::from(0));
@@ -376,7 +433,7 @@ fn checkpoint_from_empty_get_storage_at() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint(); // Commit/discard c3.
+    state.discard_checkpoint().unwrap(); // Commit/discard c3.
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
     assert_eq!(
@@ -400,7 +457,7 @@ fn checkpoint_from_empty_get_storage_at() {
         Some(U256::one())
     );
 
-    state.revert_to_checkpoint(); // Revert to c2.
+    state.revert_to_checkpoint().unwrap(); // Revert to c2.
     substates.pop();
     assert_eq!(state.total_storage_tokens(), U256::from(0));
     assert_eq!(state.collateral_for_storage(&a).unwrap(), U256::from(0));
@@ -429,7 +486,7 @@ fn checkpoint_from_empty_get_storage_at() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint(); // Commit/discard c1.
+    state.discard_checkpoint().unwrap(); // Commit/discard c1.
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
     assert_eq!(
@@ -506,7 +563,7 @@ fn checkpoint_get_storage_at() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
     assert_eq!(
@@ -656,7 +713,7 @@ fn checkpoint_get_storage_at() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint(); // Commit/discard c5.
+    state.discard_checkpoint().unwrap(); // Commit/discard c5.
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
     assert_eq!(state.balance(&contract_a_s).unwrap(), U256::zero());
@@ -697,7 +754,7 @@ fn checkpoint_get_storage_at() {
         Some(U256::from(3))
     );
 
-    state.revert_to_checkpoint(); // Revert to c4.
+    state.revert_to_checkpoint().unwrap(); // Revert to c4.
     substates.pop();
     assert_eq!(state.balance(&contract_a_s).unwrap(), U256::zero());
     assert_eq!(
@@ -749,7 +806,7 @@ fn checkpoint_get_storage_at() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint(); // Commit/discard c3.
+    state.discard_checkpoint().unwrap(); // Commit/discard c3.
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
 
@@ -783,7 +840,7 @@ fn checkpoint_get_storage_at() {
         Some(U256::one())
     );
 
-    state.revert_to_checkpoint(); // Revert to c2.
+    state.revert_to_checkpoint().unwrap(); // Revert to c2.
     substates.pop();
     assert_eq!(state.balance(&contract_a_s).unwrap(), U256::zero());
     assert_eq!(
@@ -827,7 +884,7 @@ fn checkpoint_get_storage_at() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint(); // Commit/discard c1.
+    state.discard_checkpoint().unwrap(); // Commit/discard c1.
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
     assert_eq!(state.balance(&contract_a_s).unwrap(), U256::zero());
@@ -887,7 +944,7 @@ fn kill_account_with_checkpoints() {
     state_0
         .add_collateral_for_storage(&a, &COLLATERAL_DRIPS_PER_STORAGE_KEY)
         .unwrap();
-    state_0.discard_checkpoint();
+    state_0.discard_checkpoint().unwrap();
     let epoch_id_1 = EpochId::from_uint(&U256::from(1));
     state_0
         .commit(epoch_id_1, /* debug_record = */ None)
@@ -918,10 +975,10 @@ fn kill_account_with_checkpoints() {
     state.new_contract_with_code(&a_s, U256::zero()).unwrap();
     // The new contract in the same place should have empty storage.
     assert_eq!(state.storage_at(&a_s, &k).unwrap(), U256::zero());
-    state.revert_to_checkpoint();
+    state.revert_to_checkpoint().unwrap();
     // The account is killed. The storage should be empty.
     // assert_eq!(state.storage_at(&a, &k).unwrap(), U256::zero());
-    state.revert_to_checkpoint();
+    state.revert_to_checkpoint().unwrap();
     // Storage before the account is killed.
     assert_eq!(state.storage_at(&a_s, &k).unwrap(), U256::one());
 }
@@ -965,7 +1022,7 @@ fn check_result_of_simple_payment_to_killed_account() {
             &COLLATERAL_DRIPS_PER_STORAGE_KEY,
         )
         .unwrap();
-    state_0.discard_checkpoint();
+    state_0.discard_checkpoint().unwrap();
     let epoch_id_1 = EpochId::from_uint(&U256::from(1));
     state_0
         .commit(epoch_id_1, /* debug_record = */ None)
@@ -1029,8 +1086,8 @@ fn create_contract_fail() {
             .unwrap(),
         CollateralCheckResult::Valid
     );
-    state.discard_checkpoint(); // discard c2
-    state.revert_to_checkpoint(); // revert to c1
+    state.discard_checkpoint().unwrap(); // discard c2
+    state.revert_to_checkpoint().unwrap(); // revert to c1
     assert_eq!(state.exists(&a_s).unwrap(), false);
 
     state
@@ -1093,7 +1150,7 @@ fn create_contract_fail_previous_storage() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
     assert_eq!(
@@ -1145,7 +1202,7 @@ fn create_contract_fail_previous_storage() {
     state
         .set_storage(&contract_addr_s, k.clone(), U256::from(2), a)
         .unwrap();
-    state.revert_to_checkpoint();
+    state.revert_to_checkpoint().unwrap();
     substates.pop(); // revert to c2
     assert_eq!(
         state.total_storage_tokens(),
@@ -1157,7 +1214,7 @@ fn create_contract_fail_previous_storage() {
         state.storage_at(&contract_addr_s, &k).unwrap(),
         U256::zero()
     );
-    state.revert_to_checkpoint();
+    state.revert_to_checkpoint().unwrap();
     substates.pop(); // revert to c1
     assert_eq!(
         state.storage_at(&contract_addr_s, &k).unwrap(),
@@ -1254,7 +1311,7 @@ fn test_automatic_collateral_normal_account() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
 
@@ -1303,7 +1360,7 @@ fn test_automatic_collateral_normal_account() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
     assert_eq!(
@@ -1356,7 +1413,7 @@ fn test_automatic_collateral_normal_account() {
             .unwrap(),
         CollateralCheckResult::Valid
     );
-    state.revert_to_checkpoint();
+    state.revert_to_checkpoint().unwrap();
     assert_eq!(
         state.total_storage_tokens(),
         *COLLATERAL_DRIPS_PER_STORAGE_KEY
@@ -1402,7 +1459,7 @@ fn test_automatic_collateral_normal_account() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
     assert_eq!(
@@ -1446,7 +1503,7 @@ fn test_automatic_collateral_normal_account() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
     assert_eq!(
@@ -1492,7 +1549,7 @@ fn test_automatic_collateral_normal_account() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
     assert_eq!(state.total_storage_tokens(), U256::from(0));
@@ -1580,7 +1637,7 @@ fn test_automatic_collateral_contract_account() {
             .unwrap(),
         CollateralCheckResult::Valid
     );
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     substates.pop();
     assert_eq!(state.total_storage_tokens(), U256::from(0));
     assert_eq!(
@@ -1622,7 +1679,7 @@ fn test_automatic_collateral_contract_account() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
 
@@ -1675,10 +1732,15 @@ fn test_automatic_collateral_contract_account() {
         CollateralCheckResult::NotEnoughBalance {
             required: *COLLATERAL_DRIPS_PER_STORAGE_KEY * U256::from(2),
             got: *COLLATERAL_DRIPS_PER_STORAGE_KEY,
+            address: contract_account,
+            source: CollateralSource::Sponsor {
+                sponsor_balance: *COLLATERAL_DRIPS_PER_STORAGE_KEY,
+                storage_points: U256::zero(),
+            },
         }
     );
 
-    state.revert_to_checkpoint();
+    state.revert_to_checkpoint().unwrap();
     substates.pop();
 
     assert_eq!(state.balance(&contract_account_s).unwrap(), U256::from(0));
@@ -1725,7 +1787,7 @@ fn test_automatic_collateral_contract_account() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
     assert_eq!(state.balance(&contract_account_s).unwrap(), U256::from(0));
@@ -1771,7 +1833,7 @@ fn test_automatic_collateral_contract_account() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
     assert_eq!(state.balance(&contract_account_s).unwrap(), U256::from(0));
@@ -1818,7 +1880,7 @@ fn test_automatic_collateral_contract_account() {
     state
         .collect_ownership_changed(&mut substates.last_mut().unwrap())
         .unwrap();
-    state.discard_checkpoint();
+    state.discard_checkpoint().unwrap();
     let substate = substates.pop().unwrap();
     substates.last_mut().unwrap().accrue(substate);
     assert_eq!(state.balance(&contract_account_s).unwrap(), U256::from(0));
@@ -1835,3 +1897,4300 @@ fn test_automatic_collateral_contract_account() {
     assert_eq!(state.total_storage_tokens(), U256::from(0));
     assert_eq!(state.secondary_reward(), U256::from(0));
 }
+
+#[test]
+fn dirty_accounts_snapshot() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let mut b = a;
+    b.0[19] = 1;
+    let mut untouched = a;
+    untouched.0[19] = 2;
+    let a = a.with_native_space();
+    let b = b.with_native_space();
+    let untouched = untouched.with_native_space();
+
+    state
+        .add_balance(&untouched, &U256::from(5), CleanupMode::NoEmpty)
+        .unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+    let mut state = get_state(&storage_manager, &epoch_id);
+    // Load the account into the cache without touching it.
+    assert_eq!(state.balance(&untouched).unwrap(), U256::from(5));
+
+    state.checkpoint();
+    state
+        .add_balance(&a, &U256::from(1), CleanupMode::NoEmpty)
+        .unwrap();
+    state.inc_nonce(&b).unwrap();
+
+    let dirty_accounts = state.dirty_accounts();
+    let find = |address| {
+        dirty_accounts
+            .iter()
+            .find(|(addr, _)| *addr == address)
+            .map(|(_, account)| account.clone())
+    };
+    assert_eq!(find(a).unwrap().balance, U256::from(1));
+    assert_eq!(find(b).unwrap().nonce, U256::from(1));
+    assert!(find(untouched).is_none());
+    // The snapshot does not flush anything.
+    assert_eq!(state.balance(&a).unwrap(), U256::from(1));
+    state.revert_to_checkpoint().unwrap();
+    assert_eq!(state.balance(&a).unwrap(), U256::zero());
+}
+
+#[test]
+fn issuance_ledger_matches_total_issued() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let mut b = a;
+    b.0[19] = 1;
+    let a_s = a.with_native_space();
+    let b_s = b.with_native_space();
+    let stake = U256::from(ONE_CFX_IN_DRIP) * U256::from(1000);
+
+    state.add_balance(&a_s, &stake, CleanupMode::NoEmpty).unwrap();
+    state.deposit(&a, &stake, 0, false).unwrap();
+    for _ in 0..100 {
+        state.bump_block_number_accumulate_interest();
+    }
+    let issued_before = state.total_issued_tokens();
+
+    state
+        .add_pos_interest(&b, &U256::from(1000), CleanupMode::ForceCreate)
+        .unwrap();
+    // The interest withdrawn in a reverted checkpoint is not recorded.
+    state.checkpoint();
+    state.withdraw(&a, &stake, false).unwrap();
+    state.revert_to_checkpoint().unwrap();
+    state.checkpoint();
+    let interest = state.withdraw(&a, &stake, false).unwrap();
+    state.discard_checkpoint().unwrap();
+    assert!(!interest.is_zero());
+    state
+        .add_pos_interest(&b, &U256::from(24), CleanupMode::ForceCreate)
+        .unwrap();
+
+    let ledger = state.issuance_ledger();
+    let mut expected = vec![(a_s, interest), (b_s, U256::from(1024))];
+    expected.sort_by(|x, y| x.0.cmp(&y.0));
+    assert_eq!(ledger, expected);
+    let total = ledger.iter().fold(U256::zero(), |acc, (_, v)| acc + *v);
+    assert_eq!(total, state.total_issued_tokens() - issued_before);
+}
+
+#[test]
+fn validate_pos_unlock_rejects_regressive_unlock() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let identifier = H256::from_low_u64_be(1);
+    assert!(!state.validate_pos_unlock(identifier, 1).unwrap());
+
+    let status = IndexStatus {
+        registered: 10,
+        unlocked: 5,
+    };
+    state
+        .set_storage(
+            &POS_REGISTER_CONTRACT_ADDRESS.with_native_space(),
+            pos_internal_entries::index_entry(&identifier),
+            status.into(),
+            Address::zero(),
+        )
+        .unwrap();
+    assert!(state.validate_pos_unlock(identifier, 5).unwrap());
+    assert!(state.validate_pos_unlock(identifier, 7).unwrap());
+    assert!(!state.validate_pos_unlock(identifier, 4).unwrap());
+
+    state.add_total_pos_staking(*POS_VOTE_PRICE * U256::from(10));
+    let pos_staking_before = state.total_pos_staking_tokens();
+    state.update_pos_status(identifier, 7).unwrap();
+    assert_eq!(
+        state.total_pos_staking_tokens(),
+        pos_staking_before - *POS_VOTE_PRICE * U256::from(2)
+    );
+    assert!(!state.validate_pos_unlock(identifier, 6).unwrap());
+    assert!(state.update_pos_status(identifier, 6).is_err());
+}
+
+#[test]
+fn available_staking_balance_with_vote_and_pos_locks() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let cfx = |n: u64| U256::from(ONE_CFX_IN_DRIP) * U256::from(n);
+    // (pos locked votes, vote locked CFX)
+    let locks = [(3u64, 1000u64), (1, 4000)];
+    let mut addresses = vec![];
+    for (i, (pos_votes, vote_locked)) in locks.iter().enumerate() {
+        let mut address = Address::from_low_u64_be(i as u64 + 1);
+        address.set_user_account_type_bits();
+        let identifier = H256::from_low_u64_be(i as u64 + 1);
+        state
+            .add_balance(
+                &address.with_native_space(),
+                &cfx(5000),
+                CleanupMode::NoEmpty,
+            )
+            .unwrap();
+        state.deposit(&address, &cfx(5000), 1, false).unwrap();
+        state.vote_lock(&address, &cfx(*vote_locked), 100).unwrap();
+        state
+            .set_storage(
+                &POS_REGISTER_CONTRACT_ADDRESS.with_native_space(),
+                pos_internal_entries::identifier_entry(&address),
+                identifier.into_uint(),
+                Address::zero(),
+            )
+            .unwrap();
+        let status = IndexStatus {
+            registered: *pos_votes,
+            unlocked: 0,
+        };
+        state
+            .set_storage(
+                &POS_REGISTER_CONTRACT_ADDRESS.with_native_space(),
+                pos_internal_entries::index_entry(&identifier),
+                status.into(),
+                Address::zero(),
+            )
+            .unwrap();
+        addresses.push(address);
+    }
+    let (pos_larger, vote_larger) = (addresses[0], addresses[1]);
+    assert_eq!(state.pos_locked_staking(&pos_larger).unwrap(), cfx(3000));
+    assert_eq!(state.pos_locked_staking(&vote_larger).unwrap(), cfx(1000));
+
+    // The PoS lock exceeds the vote lock.
+    assert_eq!(
+        state.available_staking_balance(&pos_larger, 10).unwrap(),
+        cfx(2000)
+    );
+    // The vote lock exceeds the PoS lock, until it expires.
+    assert_eq!(
+        state.available_staking_balance(&vote_larger, 10).unwrap(),
+        cfx(1000)
+    );
+    assert_eq!(
+        state.available_staking_balance(&vote_larger, 100).unwrap(),
+        cfx(4000)
+    );
+    let mut absent = Address::from_low_u64_be(3);
+    absent.set_user_account_type_bits();
+    assert!(state
+        .available_staking_balance(&absent, 10)
+        .unwrap()
+        .is_zero());
+}
+
+#[test]
+fn update_pos_status_with_invalid_index() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let identifier = H256::from_low_u64_be(1);
+
+    // The index information of an unregistered identifier is zero.
+    assert!(state.update_pos_status(identifier, 1).is_err());
+
+    let status = IndexStatus {
+        registered: 10,
+        unlocked: 5,
+    };
+    state
+        .set_storage(
+            &POS_REGISTER_CONTRACT_ADDRESS.with_native_space(),
+            pos_internal_entries::index_entry(&identifier),
+            status.into(),
+            Address::zero(),
+        )
+        .unwrap();
+    let pos_staking_before = state.total_pos_staking_tokens();
+    assert!(state.update_pos_status(identifier, 3).is_err());
+    assert_eq!(state.total_pos_staking_tokens(), pos_staking_before);
+}
+
+#[test]
+fn update_pos_statuses_matches_sequential_updates() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let register = POS_REGISTER_CONTRACT_ADDRESS.with_native_space();
+    let identifiers: Vec<_> = (1..=5u64).map(H256::from_low_u64_be).collect();
+    for identifier in &identifiers {
+        let status = IndexStatus {
+            registered: 10,
+            unlocked: 0,
+        };
+        state
+            .set_storage(
+                &register,
+                pos_internal_entries::index_entry(identifier),
+                status.into(),
+                Address::zero(),
+            )
+            .unwrap();
+    }
+    state.add_total_pos_staking(*POS_VOTE_PRICE * U256::from(50));
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    // The second identifier is unlocked twice.
+    let updates = vec![
+        (identifiers[0], 3),
+        (identifiers[1], 2),
+        (identifiers[3], 10),
+        (identifiers[1], 6),
+    ];
+    let mut sequential = get_state(&storage_manager, &epoch_id);
+    for (identifier, number) in &updates {
+        sequential.update_pos_status(*identifier, *number).unwrap();
+    }
+    let mut batched = get_state(&storage_manager, &epoch_id);
+    batched.update_pos_statuses(&updates).unwrap();
+
+    assert_eq!(
+        batched.total_pos_staking_tokens(),
+        *POS_VOTE_PRICE * U256::from(50 - 3 - 6 - 10)
+    );
+    assert_eq!(
+        batched.total_pos_staking_tokens(),
+        sequential.total_pos_staking_tokens()
+    );
+    for identifier in &identifiers {
+        let key = pos_internal_entries::index_entry(identifier);
+        assert_eq!(
+            batched.storage_at(&register, &key).unwrap(),
+            sequential.storage_at(&register, &key).unwrap()
+        );
+    }
+
+    // An invalid update leaves the state unchanged.
+    let key = pos_internal_entries::index_entry(&identifiers[2]);
+    let status_before = batched.storage_at(&register, &key).unwrap();
+    let pos_staking_before = batched.total_pos_staking_tokens();
+    let unregistered = H256::from_low_u64_be(6);
+    for invalid in &[(identifiers[1], 5), (unregistered, 1)] {
+        match batched.update_pos_statuses(&[(identifiers[2], 4), *invalid]) {
+            Err(e) => match e.kind() {
+                DbErrorKind::InvalidPosIndexStatus(identifier, _) => {
+                    assert_eq!(*identifier, invalid.0)
+                }
+                kind => panic!("unexpected error: {:?}", kind),
+            },
+            Ok(_) => panic!("the invalid update is not rejected"),
+        }
+        assert_eq!(batched.storage_at(&register, &key).unwrap(), status_before);
+        assert_eq!(batched.total_pos_staking_tokens(), pos_staking_before);
+    }
+}
+
+#[test]
+fn is_contract_with_code_caches_no_code() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let address = Address::from_low_u64_be(1).with_evm_space();
+    state
+        .add_balance(&address, &U256::from(1), CleanupMode::NoEmpty)
+        .unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+    let mut state = get_state(&storage_manager, &epoch_id);
+
+    assert!(!state.is_contract_with_code(&address).unwrap());
+    assert!(state.cache.get_mut().contains_key(&address));
+    // The second query is answered without loading the account.
+    state.cache.get_mut().clear();
+    assert!(!state.is_contract_with_code(&address).unwrap());
+    assert!(!state.cache.get_mut().contains_key(&address));
+
+    // The cached result is dropped once code is initialized.
+    state
+        .init_code(&address, vec![0x12, 0x34], Address::zero())
+        .unwrap();
+    assert!(state.is_contract_with_code(&address).unwrap());
+}
+
+#[test]
+fn pending_fingerprint_of_equivalent_mutations() {
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let mut b = a;
+    b.0[19] = 1;
+    let a_s = a.with_native_space();
+    let b_s = b.with_native_space();
+
+    let storage_manager_1 = new_state_manager_for_unit_test();
+    let mut state_1 = get_state_for_genesis_write(&storage_manager_1);
+    state_1
+        .add_balance(&a_s, &U256::from(10), CleanupMode::NoEmpty)
+        .unwrap();
+    state_1.inc_nonce(&b_s).unwrap();
+    state_1.inc_nonce(&b_s).unwrap();
+
+    let storage_manager_2 = new_state_manager_for_unit_test();
+    let mut state_2 = get_state_for_genesis_write(&storage_manager_2);
+    state_2.set_nonce(&b_s, &U256::from(2)).unwrap();
+    state_2
+        .add_balance(&a_s, &U256::from(3), CleanupMode::NoEmpty)
+        .unwrap();
+    state_2
+        .add_balance(&a_s, &U256::from(7), CleanupMode::NoEmpty)
+        .unwrap();
+    assert_eq!(state_1.pending_fingerprint(), state_2.pending_fingerprint());
+
+    state_2.checkpoint();
+    state_2
+        .add_balance(&a_s, &U256::from(1), CleanupMode::NoEmpty)
+        .unwrap();
+    assert_ne!(state_1.pending_fingerprint(), state_2.pending_fingerprint());
+    state_2.revert_to_checkpoint().unwrap();
+    assert_eq!(state_1.pending_fingerprint(), state_2.pending_fingerprint());
+
+    state_2.add_total_issued(U256::from(1));
+    assert_ne!(state_1.pending_fingerprint(), state_2.pending_fingerprint());
+}
+
+#[test]
+fn secondary_reward_with_remainder_for_small_storage() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut address = Address::zero();
+    address.set_user_account_type_bits();
+    state
+        .add_balance(
+            &address.with_native_space(),
+            &U256::from(1),
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    state
+        .add_collateral_for_storage(&address, &U256::from(1))
+        .unwrap();
+
+    let (reward, remainder) = state.secondary_reward_with_remainder();
+    assert_eq!(reward, U256::zero());
+    assert_eq!(reward, state.secondary_reward());
+    assert!(!remainder.is_zero());
+    assert_eq!(
+        reward * *INTEREST_RATE_PER_BLOCK_SCALE + remainder,
+        state.total_storage_tokens()
+            * state.world_statistics.interest_rate_per_block
+    );
+}
+
+#[test]
+fn init_code_checked_size_limit() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let spec = Spec::new_spec_for_test();
+    let limit = spec.create_data_limit;
+    let mut address = Address::zero();
+    address.set_contract_type_bits();
+    let native = address.with_native_space();
+    let evm = Address::from_low_u64_be(1).with_evm_space();
+    state.new_contract(&native, U256::zero()).unwrap();
+    state
+        .new_contract_with_admin(
+            &evm,
+            &Address::zero(),
+            U256::zero(),
+            None,
+            false,
+        )
+        .unwrap();
+
+    for contract in &[native, evm] {
+        state
+            .init_code_checked(contract, vec![0; limit - 1], address, &spec)
+            .unwrap();
+        assert_eq!(state.code_size(contract).unwrap(), Some(limit - 1));
+        state
+            .init_code_checked(contract, vec![0; limit], address, &spec)
+            .unwrap();
+        assert_eq!(state.code_size(contract).unwrap(), Some(limit));
+        assert!(state
+            .init_code_checked(contract, vec![0; limit + 1], address, &spec)
+            .is_err());
+        assert_eq!(state.code_size(contract).unwrap(), Some(limit));
+    }
+}
+
+#[test]
+fn set_storage_entries_matches_set_storage() {
+    let mut address = Address::zero();
+    address.set_contract_type_bits();
+    let address_with_space = address.with_native_space();
+    let entries = (0..100u64)
+        .map(|i| {
+            // Some entries write zero to an empty slot, which is a no-op.
+            (u256_to_vec(&U256::from(i)), U256::from(i % 3), address)
+        })
+        .collect::<Vec<_>>();
+
+    let storage_manager_1 = new_state_manager_for_unit_test();
+    let mut state_1 = get_state_for_genesis_write(&storage_manager_1);
+    state_1
+        .new_contract_with_code(&address_with_space, U256::zero())
+        .unwrap();
+    for (key, value, owner) in entries.clone() {
+        state_1
+            .set_storage(&address_with_space, key, value, owner)
+            .unwrap();
+    }
+
+    let storage_manager_2 = new_state_manager_for_unit_test();
+    let mut state_2 = get_state_for_genesis_write(&storage_manager_2);
+    state_2
+        .new_contract_with_code(&address_with_space, U256::zero())
+        .unwrap();
+    state_2
+        .set_storage_entries(&address_with_space, entries.clone())
+        .unwrap();
+
+    for (key, value, _) in &entries {
+        assert_eq!(
+            state_2.storage_at(&address_with_space, key).unwrap(),
+            *value
+        );
+    }
+    state_1
+        .collect_ownership_changed(&mut Substate::new())
+        .unwrap();
+    state_2
+        .collect_ownership_changed(&mut Substate::new())
+        .unwrap();
+    assert_eq!(
+        state_1.compute_state_root(None).unwrap().state_root,
+        state_2.compute_state_root(None).unwrap().state_root
+    );
+}
+
+#[test]
+fn settle_collateral_records_burnt_and_refunded() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut address = Address::zero();
+    address.set_user_account_type_bits();
+    let address_with_space = address.with_native_space();
+    let spec = Spec::new_spec_for_test();
+
+    let initial_balance = *DRIPS_PER_STORAGE_COLLATERAL_UNIT * U256::from(10);
+    state
+        .add_balance(
+            &address_with_space,
+            &initial_balance,
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    state.add_total_issued(initial_balance);
+
+    let mut substate = Substate::new();
+    substate.record_storage_occupy(&address, 10);
+    assert_eq!(
+        state
+            .settle_collateral_for_all(&mut substate, &mut (), &spec, false)
+            .unwrap(),
+        CollateralCheckResult::Valid
+    );
+    assert_eq!(
+        substate.collateral_burnt_and_refunded(),
+        (U256::zero(), U256::zero())
+    );
+    assert_eq!(
+        state.collateral_for_storage(&address).unwrap(),
+        *DRIPS_PER_STORAGE_COLLATERAL_UNIT * U256::from(10)
+    );
+
+    // Release more collateral than the account holds, the exceeding part is
+    // burnt.
+    let mut substate = Substate::new();
+    substate.record_storage_release(&address, 15);
+    assert_eq!(
+        state
+            .settle_collateral_for_all(&mut substate, &mut (), &spec, false)
+            .unwrap(),
+        CollateralCheckResult::Valid
+    );
+    assert_eq!(
+        substate.collateral_burnt_and_refunded(),
+        (
+            *DRIPS_PER_STORAGE_COLLATERAL_UNIT * U256::from(5),
+            *DRIPS_PER_STORAGE_COLLATERAL_UNIT * U256::from(10)
+        )
+    );
+    assert_eq!(state.collateral_for_storage(&address).unwrap(), U256::zero());
+    assert_eq!(
+        state.balance(&address_with_space).unwrap(),
+        *DRIPS_PER_STORAGE_COLLATERAL_UNIT * U256::from(10)
+    );
+
+    let mut total = Substate::new();
+    total.accrue(substate);
+    assert_eq!(
+        total.collateral_burnt,
+        *DRIPS_PER_STORAGE_COLLATERAL_UNIT * U256::from(5)
+    );
+}
+
+#[test]
+fn preview_withdraw_interest_matches_withdraw() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+    let stake = U256::from(ONE_CFX_IN_DRIP) * U256::from(1000);
+
+    state
+        .add_balance(&a_s, &(stake * U256::from(2)), CleanupMode::NoEmpty)
+        .unwrap();
+    state.deposit(&a, &stake, 0, false).unwrap();
+    for _ in 0..100 {
+        state.bump_block_number_accumulate_interest();
+    }
+    state.deposit(&a, &stake, 100, false).unwrap();
+    for _ in 0..100 {
+        state.bump_block_number_accumulate_interest();
+    }
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+    let mut state = get_state(&storage_manager, &epoch_id);
+
+    let amount = stake + stake / U256::from(2);
+    for cip_97 in vec![false, true] {
+        let total_issued = state.total_issued_tokens();
+        let preview =
+            state.preview_withdraw_interest(&a, &amount, cip_97).unwrap();
+        assert!(!preview.is_zero());
+        // Preview does not change the state.
+        assert_eq!(state.total_issued_tokens(), total_issued);
+        assert_eq!(state.deposit_list_length(&a).unwrap(), 2);
+
+        state.checkpoint();
+        let interest = state.withdraw(&a, &amount, cip_97).unwrap();
+        assert_eq!(preview, interest);
+        state.revert_to_checkpoint().unwrap();
+    }
+    assert_eq!(
+        state
+            .preview_withdraw_interest(&a, &U256::zero(), false)
+            .unwrap(),
+        U256::zero()
+    );
+}
+
+#[test]
+fn no_dirty_entries_after_commit() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+
+    assert!(!state.has_dirty_entries());
+    state
+        .add_balance(&a_s, &U256::from(100), CleanupMode::NoEmpty)
+        .unwrap();
+    assert!(state.has_dirty_entries());
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+    assert!(!state.has_dirty_entries());
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    assert_eq!(state.balance(&a_s).unwrap(), U256::from(100));
+    // Reading an account only caches it as clean.
+    assert!(!state.has_dirty_entries());
+    // A mutation after commit is reported as dirty again.
+    state
+        .add_balance(&a_s, &U256::from(1), CleanupMode::NoEmpty)
+        .unwrap();
+    assert!(state.has_dirty_entries());
+    state.compute_state_root(None).unwrap();
+    assert!(!state.has_dirty_entries());
+}
+
+#[test]
+fn state_root_preview_matches_commit() {
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+    let mut contract = Address::from_low_u64_be(1);
+    contract.set_contract_type_bits();
+    let contract_s = contract.with_native_space();
+    let change = |state: &mut State| {
+        state
+            .add_balance(&a_s, &U256::from(100), CleanupMode::NoEmpty)
+            .unwrap();
+        state.new_contract_with_code(&contract_s, U256::zero()).unwrap();
+        state
+            .set_storage(&contract_s, vec![1], U256::from(2), a)
+            .unwrap();
+        state.add_total_issued(U256::from(100));
+        state.collect_ownership_changed(&mut Substate::new()).unwrap();
+    };
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    change(&mut state);
+    let dirty_accounts = state.dirty_account_count();
+    let supply = state.supply_breakdown();
+    let preview = state.compute_state_root_preview().unwrap();
+    // The pending changes are kept.
+    assert_eq!(state.dirty_account_count(), dirty_accounts);
+    assert_eq!(state.supply_breakdown(), supply);
+    assert_eq!(state.balance(&a_s).unwrap(), U256::from(100));
+    let root = state.commit(epoch_id, /* debug_record = */ None).unwrap();
+    assert_eq!(preview.state_root, root.state_root);
+
+    // The state can still be changed after a preview.
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    change(&mut state);
+    state.compute_state_root_preview().unwrap();
+    state
+        .set_storage(&contract_s, vec![1], U256::from(3), a)
+        .unwrap();
+    state.collect_ownership_changed(&mut Substate::new()).unwrap();
+    let root = state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut expected_state = get_state_for_genesis_write(&storage_manager);
+    change(&mut expected_state);
+    expected_state
+        .set_storage(&contract_s, vec![1], U256::from(3), a)
+        .unwrap();
+    expected_state
+        .collect_ownership_changed(&mut Substate::new())
+        .unwrap();
+    let expected_root = expected_state
+        .commit(epoch_id, /* debug_record = */ None)
+        .unwrap();
+    assert_eq!(root.state_root, expected_root.state_root);
+}
+
+#[test]
+fn unbalanced_checkpoint_discard_and_revert() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+
+    assert_eq!(state.checkpoint_depth(), 0);
+    assert!(state.discard_checkpoint().is_err());
+    assert!(state.revert_to_checkpoint().is_err());
+
+    state.checkpoint();
+    state.checkpoint();
+    assert_eq!(state.checkpoint_depth(), 2);
+    state
+        .add_balance(&a_s, &U256::from(10), CleanupMode::NoEmpty)
+        .unwrap();
+    state.discard_checkpoint().unwrap();
+    assert_eq!(state.checkpoint_depth(), 1);
+    state.revert_to_checkpoint().unwrap();
+    assert_eq!(state.checkpoint_depth(), 0);
+    assert_eq!(state.balance(&a_s).unwrap(), U256::zero());
+
+    // The failed calls leave the state untouched.
+    assert!(state.revert_to_checkpoint().is_err());
+    assert!(state.discard_checkpoint().is_err());
+    assert_eq!(state.checkpoint_depth(), 0);
+    state.checkpoint();
+    state
+        .add_balance(&a_s, &U256::from(10), CleanupMode::NoEmpty)
+        .unwrap();
+    state.discard_checkpoint().unwrap();
+    assert_eq!(state.balance(&a_s).unwrap(), U256::from(10));
+}
+
+#[test]
+fn revert_and_discard_to_checkpoint_index() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+
+    let outer = state.checkpoint();
+    state
+        .add_balance(&a_s, &U256::from(1), CleanupMode::NoEmpty)
+        .unwrap();
+    let middle = state.checkpoint();
+    state
+        .add_balance(&a_s, &U256::from(2), CleanupMode::NoEmpty)
+        .unwrap();
+    state.checkpoint();
+    state
+        .add_balance(&a_s, &U256::from(4), CleanupMode::NoEmpty)
+        .unwrap();
+    assert_eq!(state.checkpoint_depth(), 3);
+    assert_eq!(state.balance(&a_s).unwrap(), U256::from(7));
+
+    state.revert_to_checkpoint_index(middle).unwrap();
+    assert_eq!(state.checkpoint_depth(), 1);
+    assert_eq!(state.balance(&a_s).unwrap(), U256::from(1));
+    // Reverting to an index without checkpoint is a no-op.
+    state.revert_to_checkpoint_index(middle).unwrap();
+    assert_eq!(state.checkpoint_depth(), 1);
+    assert_eq!(state.balance(&a_s).unwrap(), U256::from(1));
+
+    state.checkpoint();
+    state
+        .add_balance(&a_s, &U256::from(8), CleanupMode::NoEmpty)
+        .unwrap();
+    state.checkpoint();
+    state.discard_checkpoints_above(outer).unwrap();
+    assert_eq!(state.checkpoint_depth(), 0);
+    assert_eq!(state.balance(&a_s).unwrap(), U256::from(9));
+    assert!(state.discard_checkpoints_above(outer).is_err());
+}
+
+#[test]
+fn discard_checkpoint_returns_merge_size() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let addresses: Vec<_> = (1..=3u8)
+        .map(|i| {
+            let mut a = Address::from_low_u64_be(i as u64);
+            a.set_user_account_type_bits();
+            a.with_native_space()
+        })
+        .collect();
+
+    state.checkpoint();
+    state
+        .add_balance(&addresses[0], &U256::from(1), CleanupMode::NoEmpty)
+        .unwrap();
+    state.checkpoint();
+    for address in &addresses {
+        state
+            .add_balance(address, &U256::from(1), CleanupMode::NoEmpty)
+            .unwrap();
+    }
+    // The inner checkpoint recorded the three touched accounts.
+    assert_eq!(state.discard_checkpoint().unwrap(), 3);
+    // Nothing left to merge into.
+    assert_eq!(state.discard_checkpoint().unwrap(), 0);
+}
+
+#[test]
+fn move_sponsor_balance_between_pools() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut contract = Address::zero();
+    contract.set_contract_type_bits();
+    let mut sponsor = Address::from_low_u64_be(1);
+    sponsor.set_user_account_type_bits();
+    state
+        .new_contract_with_code(&contract.with_native_space(), U256::zero())
+        .unwrap();
+    state
+        .set_sponsor_for_gas(
+            &contract,
+            &sponsor,
+            &U256::from(100),
+            &U256::from(10),
+        )
+        .unwrap();
+    state
+        .set_sponsor_for_collateral(
+            &contract,
+            &sponsor,
+            &U256::from(50),
+            false,
+        )
+        .unwrap();
+
+    state
+        .move_sponsor_balance(&contract, true, &U256::from(30))
+        .unwrap();
+    assert_eq!(
+        state.sponsor_balance_for_gas(&contract).unwrap(),
+        U256::from(70)
+    );
+    assert_eq!(
+        state.sponsor_balance_for_collateral(&contract).unwrap(),
+        U256::from(80)
+    );
+
+    state
+        .move_sponsor_balance(&contract, false, &U256::from(80))
+        .unwrap();
+    assert_eq!(
+        state.sponsor_balance_for_gas(&contract).unwrap(),
+        U256::from(150)
+    );
+    assert_eq!(
+        state.sponsor_balance_for_collateral(&contract).unwrap(),
+        U256::zero()
+    );
+
+    // Insufficient source balance leaves both pools untouched.
+    assert!(state
+        .move_sponsor_balance(&contract, true, &U256::from(151))
+        .is_err());
+    assert!(state
+        .move_sponsor_balance(&contract, false, &U256::from(1))
+        .is_err());
+    assert_eq!(
+        state.sponsor_balance_for_gas(&contract).unwrap(),
+        U256::from(150)
+    );
+    assert_eq!(
+        state.sponsor_balance_for_collateral(&contract).unwrap(),
+        U256::zero()
+    );
+}
+
+#[test]
+fn cache_stats_after_writes_and_revert() {
+    fn recount(state: &State) -> CacheStats {
+        let cache = state.cache.read();
+        CacheStats {
+            entries: cache.len(),
+            dirty_entries: cache.values().filter(|e| e.is_dirty()).count(),
+            code_bytes: cache.values().map(CacheCounters::code_bytes_of).sum(),
+            checkpoint_depth: state.checkpoint_depth(),
+        }
+    }
+
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    assert_eq!(state.cache_stats(), recount(&state));
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+    let mut state = get_state(&storage_manager, &epoch_id);
+    let base = state.cache_stats();
+    assert_eq!(base, recount(&state));
+
+    let mut a = Address::from_low_u64_be(1);
+    a.set_user_account_type_bits();
+    let a = a.with_native_space();
+    let mut contract = Address::from_low_u64_be(2);
+    contract.set_contract_type_bits();
+    let contract = contract.with_native_space();
+
+    // A read caches a clean entry.
+    assert_eq!(state.balance(&a).unwrap(), U256::zero());
+    assert_eq!(state.cache_stats().entries, base.entries + 1);
+    assert_eq!(state.cache_stats().dirty_entries, base.dirty_entries);
+
+    state
+        .add_balance(&a, &U256::from(1), CleanupMode::NoEmpty)
+        .unwrap();
+    assert_eq!(state.cache_stats().entries, base.entries + 1);
+    assert_eq!(state.cache_stats().dirty_entries, base.dirty_entries + 1);
+
+    state.checkpoint();
+    state.new_contract(&contract, U256::zero()).unwrap();
+    state
+        .init_code(&contract, vec![0x12, 0x34, 0x56], Address::zero())
+        .unwrap();
+    let stats = state.cache_stats();
+    assert_eq!(stats, recount(&state));
+    assert_eq!(stats.entries, base.entries + 2);
+    assert_eq!(stats.dirty_entries, base.dirty_entries + 2);
+    assert_eq!(stats.code_bytes, base.code_bytes + 3);
+    assert_eq!(stats.checkpoint_depth, 1);
+
+    // The contract entry goes back to the clean non-existent account read
+    // by `new_contract`.
+    state.revert_to_checkpoint().unwrap();
+    let stats = state.cache_stats();
+    assert_eq!(stats, recount(&state));
+    assert_eq!(stats.entries, base.entries + 2);
+    assert_eq!(stats.dirty_entries, base.dirty_entries + 1);
+    assert_eq!(stats.code_bytes, base.code_bytes);
+    assert_eq!(stats.checkpoint_depth, 0);
+
+    state
+        .commit(EpochId::from_uint(&U256::from(2)), None)
+        .unwrap();
+    assert_eq!(state.cache_stats(), CacheStats::default());
+}
+
+#[test]
+fn cache_eviction_keeps_execution_results() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let addresses: Vec<_> = (1..=200u64)
+        .map(|i| {
+            let mut a = Address::from_low_u64_be(i);
+            a.set_user_account_type_bits();
+            a.with_native_space()
+        })
+        .collect();
+    for address in &addresses {
+        state
+            .add_balance(address, &U256::from(1000), CleanupMode::NoEmpty)
+            .unwrap();
+    }
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let run = |budget: Option<usize>| {
+        let mut state = get_state(&storage_manager, &epoch_id);
+        state.set_cache_byte_budget(budget);
+        // A fixed linear congruential sequence, so both runs are identical.
+        let mut seed = 7u64;
+        let mut next = |bound: usize| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+            (seed >> 33) as usize % bound
+        };
+        for _ in 0..2000 {
+            let from = &addresses[next(addresses.len())];
+            let to = &addresses[next(addresses.len())];
+            let reader = &addresses[next(addresses.len())];
+            state.balance(reader).unwrap();
+            state.nonce(reader).unwrap();
+            state.checkpoint();
+            let value = U256::from(next(100));
+            if state.balance(from).unwrap() >= value {
+                state
+                    .transfer_balance(from, to, &value, CleanupMode::NoEmpty)
+                    .unwrap();
+                state.inc_nonce(from).unwrap();
+            }
+            if next(4) == 0 {
+                state.revert_to_checkpoint().unwrap();
+            } else {
+                state.discard_checkpoint().unwrap();
+            }
+        }
+        addresses
+            .iter()
+            .map(|a| (state.balance(a).unwrap(), state.nonce(a).unwrap()))
+            .collect::<Vec<_>>()
+    };
+
+    let entry_bytes = std::mem::size_of::<(AddressWithSpace, AccountEntry)>();
+    assert_eq!(run(None), run(Some(entry_bytes * 10)));
+
+    // With only reads, the clean entries stay within the budget.
+    let mut state = get_state(&storage_manager, &epoch_id);
+    state.set_cache_byte_budget(Some(entry_bytes * 10));
+    for address in &addresses {
+        assert_eq!(state.balance(address).unwrap(), U256::from(1000));
+    }
+    assert_eq!(state.cache_stats().entries, 10);
+    assert_eq!(
+        state.balances(&addresses).unwrap(),
+        vec![U256::from(1000); addresses.len()]
+    );
+    assert_eq!(state.cache_stats().entries, 10);
+}
+
+#[test]
+fn balances_matches_balance() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let addresses = (0..10u8)
+        .map(|i| {
+            let mut address = Address::zero();
+            address.set_user_account_type_bits();
+            address.0[19] = i;
+            address.with_native_space()
+        })
+        .collect::<Vec<_>>();
+    for (i, address) in addresses.iter().enumerate().take(6) {
+        state
+            .add_balance(address, &U256::from(i + 1), CleanupMode::NoEmpty)
+            .unwrap();
+    }
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    // Some accounts are cached and dirty, the others are only in db or
+    // missing.
+    state
+        .add_balance(&addresses[0], &U256::from(100), CleanupMode::NoEmpty)
+        .unwrap();
+    state
+        .add_balance(&addresses[8], &U256::from(100), CleanupMode::NoEmpty)
+        .unwrap();
+    let mut query = addresses.clone();
+    query.push(addresses[3]);
+    query.push(addresses[9]);
+
+    let balances = state.balances(&query).unwrap();
+    let expected = query
+        .iter()
+        .map(|address| state.balance(address).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(balances, expected);
+    assert_eq!(balances[0], U256::from(101));
+    assert_eq!(balances[3], U256::from(4));
+    assert_eq!(balances[7], U256::zero());
+    assert_eq!(balances[8], U256::from(100));
+    assert!(state.balances(&[]).unwrap().is_empty());
+}
+
+#[test]
+fn collateral_utilization_ratio() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+    let stake = U256::from(ONE_CFX_IN_DRIP) * U256::from(1000);
+
+    state
+        .add_balance(&a_s, &(stake * U256::from(2)), CleanupMode::NoEmpty)
+        .unwrap();
+    state
+        .add_collateral_for_storage(&a, &(stake / U256::from(4)))
+        .unwrap();
+    assert_eq!(state.collateral_utilization(&a).unwrap(), None);
+
+    state.deposit(&a, &stake, 0, false).unwrap();
+    assert_eq!(state.collateral_utilization(&a).unwrap(), Some(0.25));
+
+    // A missing account has no staking balance.
+    let mut b = a;
+    b.0[19] = 1;
+    assert_eq!(state.collateral_utilization(&b).unwrap(), None);
+}
+
+#[test]
+fn liquid_balance_and_total_holdings() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+    let unit = U256::from(ONE_CFX_IN_DRIP);
+
+    state
+        .add_balance(&a_s, &(unit * U256::from(100)), CleanupMode::NoEmpty)
+        .unwrap();
+    state
+        .add_collateral_for_storage(&a, &(unit * U256::from(10)))
+        .unwrap();
+    state.deposit(&a, &(unit * U256::from(30)), 0, false).unwrap();
+
+    assert_eq!(state.liquid_balance(&a).unwrap(), unit * U256::from(60));
+    assert_eq!(state.liquid_balance(&a).unwrap(), state.balance(&a_s).unwrap());
+    assert_eq!(state.total_holdings(&a).unwrap(), unit * U256::from(100));
+
+    // A missing account holds nothing.
+    let mut b = a;
+    b.0[19] = 1;
+    assert_eq!(state.liquid_balance(&b).unwrap(), U256::zero());
+    assert_eq!(state.total_holdings(&b).unwrap(), U256::zero());
+}
+
+#[test]
+fn is_null_separates_existence() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+
+    assert_eq!(state.is_null(&a_s).unwrap(), None);
+    state
+        .add_balance(&a_s, &U256::zero(), CleanupMode::ForceCreate)
+        .unwrap();
+    assert_eq!(state.is_null(&a_s).unwrap(), Some(true));
+    assert!(!state.exists_and_not_null(&a_s).unwrap());
+    state
+        .add_balance(&a_s, &U256::from(1), CleanupMode::NoEmpty)
+        .unwrap();
+    assert_eq!(state.is_null(&a_s).unwrap(), Some(false));
+}
+
+#[test]
+fn storage_entries_merges_cache_and_db() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_contract_type_bits();
+    let a_s = a.with_native_space();
+    let mut b = Address::zero();
+    b.set_user_account_type_bits();
+    let key = |i: u64| u256_to_vec(&U256::from(i));
+
+    state.new_contract_with_code(&a_s, U256::zero()).unwrap();
+    for i in 1..=3u64 {
+        state.set_storage(&a_s, key(i), U256::from(i), a).unwrap();
+    }
+    state
+        .collect_ownership_changed(&mut Substate::new())
+        .unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    // A page of db entries only.
+    let state = get_state(&storage_manager, &epoch_id);
+    let db_page = state.storage_entries(&a_s, Some(&key(2)), 1).unwrap();
+    assert_eq!(
+        db_page.iter().map(|(k, _)| k.clone()).collect::<Vec<_>>(),
+        vec![key(2)]
+    );
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    // Deleted in cache but present in db.
+    state.set_storage(&a_s, key(2), U256::zero(), a).unwrap();
+    // Overwritten in cache with a different owner.
+    state.set_storage(&a_s, key(3), U256::from(30), b).unwrap();
+    // Only exists in cache.
+    state.set_storage(&a_s, key(4), U256::from(4), a).unwrap();
+
+    let entries = state.storage_entries(&a_s, None, 10).unwrap();
+    let summary = entries
+        .iter()
+        .map(|(k, v)| (k.clone(), v.value, v.owner))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        summary,
+        vec![
+            (key(1), U256::from(1), None),
+            (key(3), U256::from(30), Some(b)),
+            (key(4), U256::from(4), None),
+        ]
+    );
+
+    // Paginate across the boundary of db and cache entries.
+    let first_page = state.storage_entries(&a_s, None, 2).unwrap();
+    assert_eq!(
+        first_page.iter().map(|(k, _)| k.clone()).collect::<Vec<_>>(),
+        vec![key(1), key(3)]
+    );
+    let second_page = state.storage_entries(&a_s, Some(&key(4)), 2).unwrap();
+    assert_eq!(
+        second_page.iter().map(|(k, _)| k.clone()).collect::<Vec<_>>(),
+        vec![key(4)]
+    );
+    let from_deleted = state.storage_entries(&a_s, Some(&key(2)), 1).unwrap();
+    assert_eq!(from_deleted[0].0, key(3));
+}
+
+#[test]
+fn pending_whitelist_changes_in_execution() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut contract = Address::zero();
+    contract.set_contract_type_bits();
+    let owner = Address::from_low_u64_be(1);
+    let users = (1..=4u64)
+        .map(|i| {
+            let mut user = Address::from_low_u64_be(i);
+            user.set_user_account_type_bits();
+            user
+        })
+        .collect::<Vec<_>>();
+
+    assert!(state.pending_whitelist_changes().unwrap().is_empty());
+    state
+        .add_commission_privilege(contract, owner, users[2])
+        .unwrap();
+    state
+        .add_commission_privilege(contract, owner, users[3])
+        .unwrap();
+    assert_eq!(
+        state.pending_whitelist_changes().unwrap(),
+        vec![(contract, users[2], true), (contract, users[3], true)]
+    );
+    state
+        .collect_ownership_changed(&mut Substate::new())
+        .unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    assert!(state.pending_whitelist_changes().unwrap().is_empty());
+    state
+        .add_commission_privilege(contract, owner, users[0])
+        .unwrap();
+    // Added and removed in the same execution.
+    state
+        .add_commission_privilege(contract, owner, users[1])
+        .unwrap();
+    state
+        .remove_commission_privilege(contract, owner, users[1])
+        .unwrap();
+    state
+        .remove_commission_privilege(contract, owner, users[2])
+        .unwrap();
+    // Removed and added back in the same execution.
+    state
+        .remove_commission_privilege(contract, owner, users[3])
+        .unwrap();
+    state
+        .add_commission_privilege(contract, owner, users[3])
+        .unwrap();
+
+    let mut expected =
+        vec![(contract, users[0], true), (contract, users[2], false)];
+    expected.sort();
+    assert_eq!(state.pending_whitelist_changes().unwrap(), expected);
+}
+
+#[test]
+fn storage_at_many_matches_storage_at() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut address = Address::zero();
+    address.set_contract_type_bits();
+    let address_with_space = address.with_native_space();
+    let keys = (0..5u64)
+        .map(|i| u256_to_vec(&U256::from(i)))
+        .collect::<Vec<_>>();
+    let key_refs = keys.iter().map(|key| key.as_slice()).collect::<Vec<_>>();
+
+    // A missing account has zero storage.
+    assert_eq!(
+        state
+            .storage_at_many(&address_with_space, &key_refs)
+            .unwrap(),
+        vec![U256::zero(); keys.len()]
+    );
+
+    state
+        .new_contract_with_code(&address_with_space, U256::zero())
+        .unwrap();
+    state
+        .set_storage(&address_with_space, keys[1].clone(), 1.into(), address)
+        .unwrap();
+    state
+        .collect_ownership_changed(&mut Substate::new())
+        .unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    state
+        .set_storage(&address_with_space, keys[3].clone(), 3.into(), address)
+        .unwrap();
+    let values = state
+        .storage_at_many(&address_with_space, &key_refs)
+        .unwrap();
+    let expected = keys
+        .iter()
+        .map(|key| state.storage_at(&address_with_space, key).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(values, expected);
+    assert_eq!(
+        state.storage_entries_at(&address_with_space, &keys).unwrap(),
+        expected
+    );
+    assert_eq!(values[1], U256::from(1));
+    assert_eq!(values[3], U256::from(3));
+    assert!(state
+        .storage_at_many(&address_with_space, &[])
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn unused_storage_points_after_conversion_and_usage() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut contract = Address::zero();
+    contract.set_contract_type_bits();
+    let mut sponsor = Address::zero();
+    sponsor.set_user_account_type_bits();
+
+    assert_eq!(state.unused_storage_points(), U256::zero());
+    // Half of the sponsored collateral is converted to storage points.
+    state
+        .set_system_storage(
+            storage_point_prop().to_vec(),
+            U256::from(ONE_CFX_IN_DRIP),
+        )
+        .unwrap();
+    state
+        .new_contract_with_admin(
+            &contract.with_native_space(),
+            &sponsor,
+            U256::zero(),
+            Some(STORAGE_LAYOUT_REGULAR_V0),
+            /* cip107 = */ true,
+        )
+        .unwrap();
+    state.add_total_issued(U256::from(1000));
+    state
+        .set_sponsor_for_collateral(
+            &contract,
+            &sponsor,
+            &U256::from(1000),
+            /* is_cip107 = */ true,
+        )
+        .unwrap();
+    assert_eq!(state.converted_storage_points(), U256::from(500));
+    assert_eq!(state.unused_storage_points(), U256::from(500));
+
+    // The sponsor balance is charged first, then the storage points.
+    state
+        .add_collateral_for_storage(&contract, &U256::from(700))
+        .unwrap();
+    assert_eq!(state.used_storage_points(), U256::from(200));
+    assert_eq!(state.unused_storage_points(), U256::from(300));
+
+    state.world_statistics.used_storage_points = U256::from(600);
+    assert_eq!(state.unused_storage_points(), U256::zero());
+}
+
+#[test]
+fn checkpoint_scope_reverts_on_drop() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+
+    // Inner scope commits while outer scope reverts.
+    {
+        let mut outer = state.checkpoint_scope();
+        outer
+            .add_balance(&a_s, &U256::from(1), CleanupMode::NoEmpty)
+            .unwrap();
+        let mut inner = outer.checkpoint_scope();
+        inner
+            .add_balance(&a_s, &U256::from(10), CleanupMode::NoEmpty)
+            .unwrap();
+        assert_eq!(inner.checkpoint_depth(), 2);
+        inner.commit().unwrap();
+        assert_eq!(outer.checkpoint_depth(), 1);
+        assert_eq!(outer.balance(&a_s).unwrap(), U256::from(11));
+    }
+    assert_eq!(state.checkpoint_depth(), 0);
+    assert_eq!(state.balance(&a_s).unwrap(), U256::zero());
+
+    // Inner scope reverts while outer scope commits.
+    {
+        let mut outer = state.checkpoint_scope();
+        outer
+            .add_balance(&a_s, &U256::from(1), CleanupMode::NoEmpty)
+            .unwrap();
+        {
+            let mut inner = outer.checkpoint_scope();
+            inner
+                .add_balance(&a_s, &U256::from(10), CleanupMode::NoEmpty)
+                .unwrap();
+        }
+        assert_eq!(outer.checkpoint_depth(), 1);
+        outer.commit().unwrap();
+    }
+    assert_eq!(state.checkpoint_depth(), 0);
+    assert_eq!(state.balance(&a_s).unwrap(), U256::from(1));
+}
+
+#[test]
+fn checkpoint_scope_reverts_on_panic() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+    let total_issued = state.total_issued_tokens();
+
+    let result =
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let mut outer = state.checkpoint_scope();
+            outer
+                .add_balance(&a_s, &U256::from(1), CleanupMode::NoEmpty)
+                .unwrap();
+            let mut inner = outer.checkpoint_scope();
+            inner.add_total_issued(U256::from(10));
+            panic!("unwind through the checkpoint scopes");
+        }));
+    assert!(result.is_err());
+    assert_eq!(state.checkpoint_depth(), 0);
+    assert_eq!(state.balance(&a_s).unwrap(), U256::zero());
+    assert_eq!(state.total_issued_tokens(), total_issued);
+    // The state is usable after unwinding.
+    state.compute_state_root(None).unwrap();
+}
+
+#[test]
+fn sub_collateral_rejects_inconsistent_total_storage_tokens() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+
+    state
+        .add_balance(&a_s, &U256::from(1000), CleanupMode::NoEmpty)
+        .unwrap();
+    state
+        .add_collateral_for_storage(&a, &U256::from(1000))
+        .unwrap();
+    assert_eq!(state.total_storage_tokens(), U256::from(1000));
+
+    // Make the global counter inconsistent with the account collateral.
+    state.world_statistics.total_storage_tokens = U256::from(100);
+    let balance = state.balance(&a_s).unwrap();
+    match state.sub_collateral_for_storage(&a, &U256::from(1000)) {
+        Err(e) => match e.kind() {
+            DbErrorKind::WorldStatisticsUnderflow(statistic, value, amount) => {
+                assert_eq!(*statistic, "total_storage_tokens");
+                assert_eq!(*value, U256::from(100));
+                assert_eq!(*amount, U256::from(1000));
+            }
+            kind => panic!("unexpected error {:?}", kind),
+        },
+        Ok(_) => panic!("the underflow is not detected"),
+    }
+    assert_eq!(state.total_storage_tokens(), U256::from(100));
+    // The account is unchanged.
+    assert_eq!(state.balance(&a_s).unwrap(), balance);
+    assert_eq!(state.collateral_for_storage(&a).unwrap(), U256::from(1000));
+}
+
+#[test]
+fn sub_collateral_underflow_leaves_sponsored_contract_unchanged() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut sponsor = Address::zero();
+    sponsor.set_user_account_type_bits();
+    let mut contract = Address::from_low_u64_be(1);
+    contract.set_contract_type_bits();
+
+    // Half of the collateral is converted to storage points.
+    state
+        .set_system_storage(
+            storage_point_prop().to_vec(),
+            U256::from(ONE_CFX_IN_DRIP),
+        )
+        .unwrap();
+    state.add_total_issued(U256::from(10000));
+    state
+        .new_contract_with_code(&contract.with_native_space(), U256::zero())
+        .unwrap();
+    state
+        .set_sponsor_for_collateral(
+            &contract,
+            &sponsor,
+            &U256::from(100),
+            /* is_cip107 = */ false,
+        )
+        .unwrap();
+    state
+        .add_collateral_for_storage(&contract, &U256::from(300))
+        .unwrap();
+    state.initialize_cip107(&contract).unwrap();
+
+    let info = state.storage_points_info(&contract).unwrap();
+    let sponsor_balance =
+        state.sponsor_balance_for_collateral(&contract).unwrap();
+    let used_storage_points = state.world_statistics.used_storage_points;
+    let total_issued = state.total_issued_tokens();
+    state.world_statistics.total_storage_tokens = U256::from(50);
+    match state.sub_collateral_for_storage(&contract, &U256::from(300)) {
+        Err(e) => match e.kind() {
+            DbErrorKind::WorldStatisticsUnderflow(statistic, ..) => {
+                assert_eq!(*statistic, "total_storage_tokens");
+            }
+            kind => panic!("unexpected error {:?}", kind),
+        },
+        Ok(_) => panic!("the underflow is not detected"),
+    }
+
+    assert_eq!(state.storage_points_info(&contract).unwrap(), info);
+    assert_eq!(
+        state.sponsor_balance_for_collateral(&contract).unwrap(),
+        sponsor_balance
+    );
+    assert_eq!(state.world_statistics.used_storage_points, used_storage_points);
+    assert_eq!(state.total_issued_tokens(), total_issued);
+    assert_eq!(state.total_storage_tokens(), U256::from(50));
+}
+
+/// A state with three contracts sponsored by the same address, where only
+/// the third one is created with CIP-107 enabled, and a user account.
+fn cip107_batch_fixture(
+    storage_manager: &Arc<StorageManager>,
+) -> (State, Vec<Address>, Address) {
+    let mut state = get_state_for_genesis_write(storage_manager);
+    let mut sponsor = Address::zero();
+    sponsor.set_user_account_type_bits();
+    let contracts: Vec<_> = (1..=3u64)
+        .map(|i| {
+            let mut a = Address::from_low_u64_be(i);
+            a.set_contract_type_bits();
+            a
+        })
+        .collect();
+    let mut user = Address::from_low_u64_be(4);
+    user.set_user_account_type_bits();
+
+    // Half of the collateral is converted to storage points.
+    state
+        .set_system_storage(
+            storage_point_prop().to_vec(),
+            U256::from(ONE_CFX_IN_DRIP),
+        )
+        .unwrap();
+    state.add_total_issued(U256::from(10000));
+    for (contract, cip107) in contracts.iter().zip([false, false, true]) {
+        state
+            .new_contract_with_admin(
+                &contract.with_native_space(),
+                &sponsor,
+                U256::zero(),
+                Some(STORAGE_LAYOUT_REGULAR_V0),
+                cip107,
+            )
+            .unwrap();
+    }
+    state
+        .set_sponsor_for_collateral(
+            &contracts[0],
+            &sponsor,
+            &U256::from(1000),
+            /* is_cip107 = */ false,
+        )
+        .unwrap();
+    state
+        .set_sponsor_for_collateral(
+            &contracts[1],
+            &sponsor,
+            &U256::from(400),
+            /* is_cip107 = */ false,
+        )
+        .unwrap();
+    state
+        .add_collateral_for_storage(&contracts[1], &U256::from(300))
+        .unwrap();
+
+    (state, contracts, user)
+}
+
+#[test]
+fn batch_initialize_cip107_accumulates_world_statistics() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let (mut state, contracts, user) = cip107_batch_fixture(&storage_manager);
+    let issued_before = state.total_issued_tokens();
+    let storage_tokens_before = state.total_storage_tokens();
+    let used_before = state.used_storage_points();
+    let mut addresses = contracts.clone();
+    addresses.push(user);
+    let result = state.batch_initialize_cip107(&addresses).unwrap();
+    assert_eq!(
+        result,
+        vec![
+            (contracts[0], U256::from(500), U256::zero()),
+            (contracts[1], U256::from(100), U256::from(100)),
+            (contracts[2], U256::zero(), U256::zero()),
+            (user, U256::zero(), U256::zero()),
+        ]
+    );
+    assert_eq!(state.total_issued_tokens(), issued_before - U256::from(700));
+    assert_eq!(
+        state.total_storage_tokens(),
+        storage_tokens_before - U256::from(100)
+    );
+    assert_eq!(state.used_storage_points(), used_before + U256::from(100));
+    // As in `initialize_cip107`, the statistic holds the points converted for
+    // the last initialized contract.
+    assert_eq!(state.converted_storage_points(), U256::from(200));
+
+    // Initialized contracts are not converted again.
+    let result = state.batch_initialize_cip107(&contracts[..2]).unwrap();
+    assert!(result.iter().all(|(_, b, c)| b.is_zero() && c.is_zero()));
+    assert_eq!(state.converted_storage_points(), U256::from(200));
+}
+
+#[test]
+fn batch_initialize_cip107_matches_sequential_initialization() {
+    let batch_storage_manager = new_state_manager_for_unit_test();
+    let sequential_storage_manager = new_state_manager_for_unit_test();
+    let (mut batch_state, contracts, user) =
+        cip107_batch_fixture(&batch_storage_manager);
+    let (mut sequential_state, _, _) =
+        cip107_batch_fixture(&sequential_storage_manager);
+    let mut addresses = contracts.clone();
+    addresses.push(user);
+
+    let batch_result = batch_state.batch_initialize_cip107(&addresses).unwrap();
+    let mut sequential_result = vec![];
+    for address in &addresses {
+        let (from_balance, from_collateral) = if address.is_contract_address()
+        {
+            sequential_state.initialize_cip107(address).unwrap()
+        } else {
+            (U256::zero(), U256::zero())
+        };
+        sequential_result.push((*address, from_balance, from_collateral));
+    }
+
+    assert_eq!(batch_result, sequential_result);
+    assert_eq!(
+        batch_state.total_issued_tokens(),
+        sequential_state.total_issued_tokens()
+    );
+    assert_eq!(
+        batch_state.total_storage_tokens(),
+        sequential_state.total_storage_tokens()
+    );
+    assert_eq!(
+        batch_state.used_storage_points(),
+        sequential_state.used_storage_points()
+    );
+    assert_eq!(
+        batch_state.converted_storage_points(),
+        sequential_state.converted_storage_points()
+    );
+    for contract in &contracts {
+        assert_eq!(
+            batch_state.sponsor_info(contract).unwrap(),
+            sequential_state.sponsor_info(contract).unwrap()
+        );
+        assert_eq!(
+            batch_state.collateral_for_storage(contract).unwrap(),
+            sequential_state.collateral_for_storage(contract).unwrap()
+        );
+    }
+}
+
+#[test]
+fn pos_interest_increment_known_values() {
+    let rate = *INITIAL_INTEREST_RATE_PER_BLOCK;
+    // sqrt(100 * 25 * 40000^2) / (1 * 25 * 40000) = 2000000 / 1000000.
+    assert_eq!(
+        pos_interest_increment(100.into(), 25.into(), rate, 1),
+        U256::from(2)
+    );
+    // sqrt(100 * 36 * 40000^2) / (1 * 25 * 40000) = floor(2.4).
+    assert_eq!(
+        pos_interest_increment(100.into(), 36.into(), rate, 1),
+        U256::from(2)
+    );
+    // With equal circulating and staking tokens, the annual interest is 4%.
+    let pos_staking =
+        U256::from(ONE_CFX_IN_DRIP) * U256::from(25 * BLOCKS_PER_YEAR);
+    assert_eq!(
+        pos_interest_increment(pos_staking, pos_staking, rate, BLOCKS_PER_YEAR),
+        U256::from(ONE_CFX_IN_DRIP)
+    );
+    assert!(
+        pos_interest_increment(U256::zero(), pos_staking, rate, 1).is_zero()
+    );
+
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    state.add_total_issued(U256::from(ONE_CFX_IN_DRIP) * U256::from(1000000));
+    state.add_total_pos_staking(*POS_VOTE_PRICE * U256::from(10));
+    let expected = pos_interest_increment(
+        state.total_issued_tokens(),
+        state.total_pos_staking_tokens(),
+        state.world_statistics.interest_rate_per_block,
+        BLOCKS_PER_YEAR,
+    );
+    assert!(!expected.is_zero());
+    state.inc_distributable_pos_interest(1).unwrap();
+    assert_eq!(state.distributable_pos_interest(), expected);
+}
+
+#[test]
+fn shorter_distribute_window_skips_pos_interest() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    state.add_total_issued(U256::from(ONE_CFX_IN_DRIP) * U256::from(1000000));
+    state.add_total_pos_staking(*POS_VOTE_PRICE * U256::from(10));
+    assert_eq!(
+        Spec::new_spec_for_test().distribute_window_blocks,
+        BLOCKS_PER_HOUR
+    );
+
+    assert_eq!(
+        state.world_statistics.distribute_window_blocks,
+        BLOCKS_PER_HOUR
+    );
+
+    state.inc_distributable_pos_interest(100).unwrap();
+    let accrued = state.distributable_pos_interest();
+    assert!(!accrued.is_zero());
+
+    // Block 100 is out of a window of 50 blocks after the last distribution.
+    state.set_distribute_window_blocks(50);
+    state.inc_distributable_pos_interest(100).unwrap();
+    assert_eq!(state.distributable_pos_interest(), accrued);
+    state.inc_distributable_pos_interest(50).unwrap();
+    assert!(state.distributable_pos_interest() > accrued);
+}
+
+#[test]
+fn storage_at_h256_matches_storage_at() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut address = Address::zero();
+    address.set_contract_type_bits();
+    let address_with_space = address.with_native_space();
+    state
+        .new_contract_with_code(&address_with_space, U256::zero())
+        .unwrap();
+
+    let key = H256::from_low_u64_be(42);
+    state
+        .set_storage_h256(&address_with_space, key, 7.into(), address)
+        .unwrap();
+    assert_eq!(
+        state.storage_at_h256(&address_with_space, key).unwrap(),
+        U256::from(7)
+    );
+    assert_eq!(
+        state
+            .storage_at(&address_with_space, &u256_to_vec(&U256::from(42)))
+            .unwrap(),
+        U256::from(7)
+    );
+    let other = H256::from_low_u64_be(43);
+    assert_eq!(
+        state.storage_at_h256(&address_with_space, other).unwrap(),
+        state.storage_at(&address_with_space, other.as_bytes()).unwrap()
+    );
+}
+
+#[test]
+fn untraced_collateral_settlement_matches_traced() {
+    let run = |untraced: bool| {
+        let storage_manager = new_state_manager_for_unit_test();
+        let mut state = get_state_for_genesis_write(&storage_manager);
+        let mut a = Address::zero();
+        a.set_user_account_type_bits();
+        let a_s = a.with_native_space();
+        let mut c = Address::zero();
+        c.set_contract_type_bits();
+        let c_s = c.with_native_space();
+        state
+            .add_balance(
+                &a_s,
+                &(*COLLATERAL_DRIPS_PER_STORAGE_KEY * U256::from(10)),
+                CleanupMode::NoEmpty,
+            )
+            .unwrap();
+        state.new_contract_with_code(&c_s, U256::zero()).unwrap();
+
+        state.checkpoint();
+        let mut substate = Substate::new();
+        state.set_storage(&c_s, vec![1], U256::one(), a).unwrap();
+        state.set_storage(&c_s, vec![2], U256::one(), a).unwrap();
+        let spec = Spec::new_spec_for_test();
+        let result = if untraced {
+            state.collect_and_settle_collateral_untraced(
+                &a,
+                &U256::MAX,
+                &mut substate,
+                &spec,
+                false,
+            )
+        } else {
+            state.collect_and_settle_collateral(
+                &a,
+                &U256::MAX,
+                &mut substate,
+                &mut (),
+                &spec,
+                false,
+            )
+        }
+        .unwrap();
+        state.discard_checkpoint().unwrap();
+        (
+            result,
+            state.balance(&a_s).unwrap(),
+            state.collateral_for_storage(&a).unwrap(),
+            state.total_storage_tokens(),
+            substate.get_collateral_change(&a),
+        )
+    };
+
+    let untraced = run(true);
+    assert_eq!(untraced, run(false));
+    assert_eq!(untraced.0, CollateralCheckResult::Valid);
+    assert_eq!(untraced.2, *COLLATERAL_DRIPS_PER_STORAGE_KEY * U256::from(2));
+}
+
+#[test]
+fn killed_contract_is_notified_as_deleted() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_contract_type_bits();
+    let a_s = a.with_native_space();
+    state.new_contract_with_code(&a_s, U256::zero()).unwrap();
+    let epoch_id_1 = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id_1, /* debug_record = */ None).unwrap();
+    assert!(state.take_killed_addresses().is_empty());
+
+    let mut state = get_state(&storage_manager, &epoch_id_1);
+    state.remove_contract(&a_s).unwrap();
+    let epoch_id_2 = EpochId::from_uint(&U256::from(2));
+    state.commit(epoch_id_2, /* debug_record = */ None).unwrap();
+
+    assert!(state.accounts_to_notify.contains(&Err(a_s)));
+    let (updated, deleted) = state.txpool_notification();
+    assert!(updated.iter().all(|account| account.address() != &a_s));
+    assert_eq!(deleted, vec![a_s]);
+    assert_eq!(state.take_killed_addresses(), vec![a_s]);
+    assert!(state.take_killed_addresses().is_empty());
+}
+
+#[test]
+fn last_committed_kills_reports_killed_contract() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_contract_type_bits();
+    let a_s = a.with_native_space();
+    state.new_contract_with_code(&a_s, U256::zero()).unwrap();
+    let epoch_id_1 = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id_1, /* debug_record = */ None).unwrap();
+    assert!(state.last_committed_kills().is_empty());
+
+    let mut state = get_state(&storage_manager, &epoch_id_1);
+    state.remove_contract(&a_s).unwrap();
+    // The kill is reported by the commit after the state root is computed.
+    state.compute_state_root(/* debug_record = */ None).unwrap();
+    assert!(state.last_committed_kills().is_empty());
+    let epoch_id_2 = EpochId::from_uint(&U256::from(2));
+    state.commit(epoch_id_2, /* debug_record = */ None).unwrap();
+    assert_eq!(state.last_committed_kills(), &[a_s]);
+    // Taking the killed addresses does not clear the list.
+    assert_eq!(state.take_killed_addresses(), vec![a_s]);
+    assert_eq!(state.last_committed_kills(), &[a_s]);
+}
+
+#[test]
+fn sponsor_gas_coverage_drains_sequentially() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut contract = Address::zero();
+    contract.set_contract_type_bits();
+    let mut sponsor = Address::from_low_u64_be(1);
+    sponsor.set_user_account_type_bits();
+    state
+        .new_contract_with_code(&contract.with_native_space(), U256::zero())
+        .unwrap();
+    let costs: Vec<U256> =
+        vec![40.into(), 40.into(), 60.into(), 15.into(), 30.into()];
+    assert_eq!(
+        state.sponsor_gas_coverage(&contract, &costs).unwrap(),
+        vec![false; 5]
+    );
+
+    state
+        .set_sponsor_for_gas(
+            &contract,
+            &sponsor,
+            &U256::from(100),
+            &U256::from(50),
+        )
+        .unwrap();
+    // 60 exceeds the bound and 30 exceeds the remaining balance of 5.
+    assert_eq!(
+        state.sponsor_gas_coverage(&contract, &costs).unwrap(),
+        vec![true, true, false, true, false]
+    );
+    assert_eq!(
+        state.sponsor_balance_for_gas(&contract).unwrap(),
+        U256::from(100)
+    );
+}
+
+#[test]
+fn state_notifier_delivers_epochs_in_order() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_clone = received.clone();
+    let notifier = StateNotifier::new(Box::new(
+        move |epoch_id: EpochId,
+              accounts: Vec<Account>,
+              _deleted: Vec<AddressWithSpace>| {
+            received_clone.lock().push((
+                std::thread::current().id(),
+                epoch_id,
+                accounts,
+            ));
+        },
+    ));
+
+    let a = Address::from_low_u64_be(1).with_native_space();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    for i in 1..=100u64 {
+        state.add_balance(&a, &U256::one(), CleanupMode::NoEmpty).unwrap();
+        let epoch_id = EpochId::from_uint(&U256::from(i));
+        state
+            .commit_and_notify(epoch_id, &notifier, /* debug_record = */ None)
+            .unwrap();
+        state = get_state(&storage_manager, &epoch_id);
+    }
+    notifier.flush();
+
+    let received = received.lock();
+    assert!(!received.is_empty());
+    // All the notifications are delivered by the same worker thread.
+    let worker = received[0].0;
+    assert_ne!(worker, std::thread::current().id());
+    assert!(received.iter().all(|(thread, ..)| *thread == worker));
+    // The epochs and the balances of `a` are delivered in commit order.
+    let epochs: Vec<_> = received.iter().map(|(_, epoch, _)| *epoch).collect();
+    let mut sorted_epochs = epochs.clone();
+    sorted_epochs.sort();
+    assert_eq!(epochs, sorted_epochs);
+    assert_eq!(
+        received.last().unwrap().1,
+        EpochId::from_uint(&U256::from(100))
+    );
+    let balances: Vec<U256> = received
+        .iter()
+        .flat_map(|(_, _, accounts)| accounts.iter())
+        .filter(|account| account.address() == &a)
+        .map(|account| account.balance)
+        .collect();
+    assert_eq!(*balances.last().unwrap(), U256::from(100));
+    assert!(balances.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn settle_collateral_for_all_reports_failing_address() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let spec = Spec::new_spec_for_test();
+    let mut rich = Address::from_low_u64_be(1);
+    rich.set_user_account_type_bits();
+    let mut poor = Address::from_low_u64_be(2);
+    poor.set_user_account_type_bits();
+    state
+        .add_balance(
+            &rich.with_native_space(),
+            &(*DRIPS_PER_STORAGE_COLLATERAL_UNIT * U256::from(10)),
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+
+    let mut substate = Substate::new();
+    substate.record_storage_occupy(&rich, 1);
+    substate.record_storage_occupy(&poor, 1);
+    let mut tracer = ExecutiveTracer::default();
+    assert_eq!(
+        state
+            .settle_collateral_for_all(&mut substate, &mut tracer, &spec, false)
+            .unwrap(),
+        CollateralCheckResult::NotEnoughBalance {
+            required: *DRIPS_PER_STORAGE_COLLATERAL_UNIT,
+            got: U256::zero(),
+            address: poor,
+            source: CollateralSource::Balance,
+        }
+    );
+    // The failed settlement charges nobody, and its traces are invalid.
+    assert_eq!(state.collateral_for_storage(&rich).unwrap(), U256::zero());
+    assert!(tracer.drain().iter().all(|trace| !trace.valid));
+
+    let mut substate = Substate::new();
+    substate.record_storage_occupy(&rich, 1);
+    let mut tracer = ExecutiveTracer::default();
+    assert_eq!(
+        state
+            .settle_collateral_for_all(&mut substate, &mut tracer, &spec, false)
+            .unwrap(),
+        CollateralCheckResult::Valid
+    );
+    let traces = tracer.drain();
+    assert!(!traces.is_empty());
+    assert!(traces.iter().all(|trace| trace.valid));
+}
+
+#[test]
+fn prove_account_and_storage_against_state_root() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+    let mut c = Address::from_low_u64_be(1);
+    c.set_contract_type_bits();
+    let c_s = c.with_native_space();
+    let mut b = Address::from_low_u64_be(2);
+    b.set_user_account_type_bits();
+    let b_s = b.with_native_space();
+
+    state.add_balance(&a_s, &U256::from(42), CleanupMode::NoEmpty).unwrap();
+    state.new_contract_with_code(&c_s, U256::zero()).unwrap();
+    state.set_storage(&c_s, vec![1], U256::from(7), a).unwrap();
+    let mut substate = Substate::new();
+    state.collect_ownership_changed(&mut substate).unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    let root = state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    let is_valid = |key: StorageKeyWithSpace,
+                    value: &Option<Vec<u8>>,
+                    proof: &StateProof| {
+        proof.is_valid_kv(
+            &key.to_key_bytes(),
+            value.as_deref(),
+            root.state_root.clone(),
+            root.aux_info.maybe_intermediate_mpt_key_padding.clone(),
+        )
+    };
+
+    let account_key = |address: &Address| {
+        StorageKey::new_account_key(address).with_native_space()
+    };
+    let proof = state.prove_account(&a_s).unwrap().unwrap();
+    assert_eq!(proof.account.as_ref().unwrap().balance, U256::from(42));
+    assert!(is_valid(account_key(&a), &proof.value, &proof.proof));
+    // The proof does not hold for another balance.
+    let mut forged = proof.account.unwrap();
+    forged.balance = U256::from(43);
+    assert!(!is_valid(
+        account_key(&a),
+        &Some(rlp::encode(&forged)),
+        &proof.proof
+    ));
+
+    // Non-existence is provable too.
+    let proof = state.prove_account(&b_s).unwrap().unwrap();
+    assert_eq!(proof.account, None);
+    assert!(is_valid(account_key(&b), &proof.value, &proof.proof));
+
+    let proof = state.prove_storage(&c_s, &[1]).unwrap().unwrap();
+    assert_eq!(proof.storage_value.unwrap().value, U256::from(7));
+    assert!(is_valid(
+        StorageKey::new_storage_key(&c, &[1]).with_native_space(),
+        &proof.value,
+        &proof.proof
+    ));
+
+    // Uncommitted changes can not be proven.
+    state.add_balance(&a_s, &U256::one(), CleanupMode::NoEmpty).unwrap();
+    assert_eq!(state.prove_account(&a_s).unwrap(), None);
+}
+
+#[test]
+fn deposit_accrued_interest_matches_full_withdraw() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+    let stake = U256::from(ONE_CFX_IN_DRIP) * U256::from(1000);
+
+    state
+        .add_balance(&a_s, &(stake * U256::from(3)), CleanupMode::NoEmpty)
+        .unwrap();
+    assert_eq!(
+        state
+            .deposit_accrued_interest(
+                &a,
+                state.world_statistics.accumulate_interest_rate
+            )
+            .unwrap(),
+        U256::zero()
+    );
+    for i in 0..3u64 {
+        state.deposit(&a, &stake, i * 100, false).unwrap();
+        for _ in 0..100 {
+            state.bump_block_number_accumulate_interest();
+        }
+    }
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+    let mut state = get_state(&storage_manager, &epoch_id);
+
+    let rate = state.world_statistics.accumulate_interest_rate;
+    let accrued = state.deposit_accrued_interest(&a, rate).unwrap();
+    assert!(!accrued.is_zero());
+    assert_eq!(state.deposit_list_length(&a).unwrap(), 3);
+    // A lower rate accrues less.
+    let earlier_rate = rate - rate / U256::from(1000);
+    assert!(
+        state.deposit_accrued_interest(&a, earlier_rate).unwrap() < accrued
+    );
+
+    let staking_balance = state.staking_balance(&a).unwrap();
+    let interest = state.withdraw(&a, &staking_balance, false).unwrap();
+    assert_eq!(accrued, interest);
+}
+
+#[test]
+fn code_hashes_mixes_accounts_and_contracts() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut eoa = Address::from_low_u64_be(1);
+    eoa.set_user_account_type_bits();
+    let eoa = eoa.with_native_space();
+    let mut contract = Address::from_low_u64_be(2);
+    contract.set_contract_type_bits();
+    let contract = contract.with_native_space();
+    let mut missing = Address::from_low_u64_be(3);
+    missing.set_user_account_type_bits();
+    let missing = missing.with_native_space();
+    let code = vec![0x60, 0x00, 0x60, 0x00];
+
+    state.add_balance(&eoa, &U256::from(1), CleanupMode::NoEmpty).unwrap();
+    state.new_contract_with_code(&contract, U256::zero()).unwrap();
+    state.init_code(&contract, code.clone(), Address::zero()).unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let state = get_state(&storage_manager, &epoch_id);
+    let addresses = vec![eoa, contract, missing, contract];
+    let code_hash = keccak(&code);
+    let expected =
+        vec![Some(KECCAK_EMPTY), Some(code_hash), None, Some(code_hash)];
+    assert_eq!(state.code_hashes(&addresses).unwrap(), expected);
+    // The accounts are cached with their code.
+    assert_eq!(state.cache_stats().code_bytes, code.len());
+    assert_eq!(state.code_hashes(&addresses).unwrap(), expected);
+    for (address, code_hash) in addresses.iter().zip(expected) {
+        assert_eq!(state.code_hash(address).unwrap(), code_hash);
+    }
+}
+
+#[test]
+fn is_db_initialized_after_genesis_commit() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = State::new(StateDb::new(
+        storage_manager.get_state_for_genesis_write(),
+    ))
+    .unwrap();
+    assert!(!state.is_db_initialized().unwrap());
+
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+    let state = get_state(&storage_manager, &epoch_id);
+    assert!(state.is_db_initialized().unwrap());
+}
+
+#[test]
+fn storage_root_of_committed_storage() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut c = Address::from_low_u64_be(1);
+    c.set_contract_type_bits();
+    let c_s = c.with_native_space();
+    let mut d = Address::from_low_u64_be(2);
+    d.set_contract_type_bits();
+    let d_s = d.with_native_space();
+    let mut missing = Address::from_low_u64_be(3);
+    missing.set_user_account_type_bits();
+    let missing_s = missing.with_native_space();
+
+    state.new_contract_with_code(&c_s, U256::zero()).unwrap();
+    state.new_contract_with_code(&d_s, U256::zero()).unwrap();
+    state.set_storage(&d_s, vec![1], U256::from(7), d).unwrap();
+    // The storage of newly created contracts is not in the db yet.
+    assert!(state.storage_root(&c_s).is_err());
+    assert!(state.storage_root(&d_s).is_err());
+    let mut substate = Substate::new();
+    state.collect_ownership_changed(&mut substate).unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    let fresh_root = state.storage_root(&c_s).unwrap().unwrap();
+    let root = state.storage_root(&d_s).unwrap().unwrap();
+    assert_ne!(fresh_root, root);
+    // The roots are the ones storage proofs are verified against.
+    let db = StateDb::new(
+        storage_manager
+            .get_state_for_next_epoch(StateIndex::new_for_test_only_delta_mpt(
+                &epoch_id,
+            ))
+            .unwrap()
+            .unwrap(),
+    );
+    assert_eq!(db.get_storage_root(&c_s).unwrap(), fresh_root);
+    assert_eq!(db.get_storage_root(&d_s).unwrap(), root);
+    assert_eq!(state.storage_root(&missing_s).unwrap(), None);
+
+    // Reading the storage doesn't make it dirty.
+    assert_eq!(state.storage_at(&d_s, &[1]).unwrap(), U256::from(7));
+    assert_eq!(state.storage_root(&d_s).unwrap(), Some(root));
+    // Pending writes would make the root stale.
+    state.set_storage(&d_s, vec![1], U256::from(8), d).unwrap();
+    assert!(state.storage_root(&d_s).is_err());
+    assert_eq!(state.storage_root(&c_s).unwrap(), Some(fresh_root));
+}
+
+#[test]
+fn total_block_reward_sums_base_and_secondary_reward() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut address = Address::zero();
+    address.set_user_account_type_bits();
+    let storage_tokens = U256::from(ONE_CFX_IN_DRIP) * U256::from(1000);
+    state
+        .add_balance(
+            &address.with_native_space(),
+            &storage_tokens,
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    state
+        .add_collateral_for_storage(&address, &storage_tokens)
+        .unwrap();
+    // The base reward is not initialized yet.
+    assert_eq!(state.total_block_reward().unwrap(), state.secondary_reward());
+
+    let base_reward = U256::from(ONE_CFX_IN_DRIP) * U256::from(2);
+    state.db.set_pow_base_reward(base_reward, None).unwrap();
+    let secondary_reward = storage_tokens
+        * state.world_statistics.interest_rate_per_block
+        / *INTEREST_RATE_PER_BLOCK_SCALE;
+    assert!(!secondary_reward.is_zero());
+    assert_eq!(
+        state.total_block_reward().unwrap(),
+        base_reward + secondary_reward
+    );
+}
+
+#[test]
+fn pow_base_reward_opt_before_initialization() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    assert_eq!(state.pow_base_reward_opt().unwrap(), None);
+
+    let base_reward = U256::from(ONE_CFX_IN_DRIP) * U256::from(2);
+    state.db.set_pow_base_reward(base_reward, None).unwrap();
+    assert_eq!(state.pow_base_reward_opt().unwrap(), Some(base_reward));
+    assert_eq!(state.pow_base_reward(), base_reward);
+}
+
+#[test]
+fn collateral_change_in_drip_of_substate() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::from_low_u64_be(1);
+    a.set_user_account_type_bits();
+    let mut b = Address::from_low_u64_be(2);
+    b.set_user_account_type_bits();
+    let mut c = Address::from_low_u64_be(3);
+    c.set_user_account_type_bits();
+
+    let mut substate = Substate::new();
+    substate.record_storage_occupy(&a, 5);
+    substate.record_storage_release(&a, 2);
+    substate.record_storage_occupy(&b, 1);
+    substate.record_storage_release(&b, 4);
+    assert_eq!(
+        state.collateral_change_in_drip(&substate, &a),
+        (*DRIPS_PER_STORAGE_COLLATERAL_UNIT * U256::from(3), U256::zero())
+    );
+    assert_eq!(
+        state.collateral_change_in_drip(&substate, &b),
+        (U256::zero(), *DRIPS_PER_STORAGE_COLLATERAL_UNIT * U256::from(3))
+    );
+    assert_eq!(
+        state.collateral_change_in_drip(&substate, &c),
+        (U256::zero(), U256::zero())
+    );
+    // The substate is not settled.
+    assert_eq!(substate.get_collateral_change(&a), (3, 0));
+    assert_eq!(state.collateral_for_storage(&a).unwrap(), U256::zero());
+}
+
+#[test]
+fn pending_collateral_change_of_substate() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let state = get_state_for_genesis_write(&storage_manager);
+    let mut addresses = vec![];
+    for i in 1..=4u64 {
+        let mut address = Address::from_low_u64_be(i);
+        address.set_user_account_type_bits();
+        addresses.push(address);
+    }
+    let (occupied, released, cancelled, untouched) =
+        (addresses[0], addresses[1], addresses[2], addresses[3]);
+
+    let mut substate = Substate::new();
+    substate.record_storage_occupy(&occupied, 2);
+    substate.record_storage_release(&released, 1);
+    substate.record_storage_occupy(&cancelled, 3);
+    substate.record_storage_release(&cancelled, 3);
+    assert!(state.has_pending_collateral_change(&substate, &occupied));
+    assert!(state.has_pending_collateral_change(&substate, &released));
+    // The occupied and released storage cancel out.
+    assert!(!state.has_pending_collateral_change(&substate, &cancelled));
+    assert!(!state.has_pending_collateral_change(&substate, &untouched));
+}
+
+#[test]
+fn checkpoint_diff_after_revert_and_discard() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::from_low_u64_be(1);
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+    let mut b = Address::from_low_u64_be(2);
+    b.set_user_account_type_bits();
+    let b_s = b.with_native_space();
+    let mut c = Address::from_low_u64_be(3);
+    c.set_contract_type_bits();
+    let c_s = c.with_native_space();
+    state.new_contract_with_code(&c_s, U256::zero()).unwrap();
+    assert!(state.checkpoint_diff(0).unwrap().is_empty());
+
+    let outer = state.checkpoint();
+    let inner = state.checkpoint();
+    state.add_balance(&a_s, &U256::from(10), CleanupMode::NoEmpty).unwrap();
+    state.set_storage(&c_s, vec![1], U256::from(7), c).unwrap();
+    let diff = state.checkpoint_diff(outer).unwrap();
+    assert_eq!(diff, state.checkpoint_diff(inner).unwrap());
+    assert_eq!(diff.accounts.len(), 2);
+    assert_eq!(diff.accounts[0].address, a);
+    assert_eq!(diff.accounts[0].before, None);
+    assert_eq!(
+        diff.accounts[0].after.as_ref().unwrap().balance,
+        U256::from(10)
+    );
+    assert!(diff.accounts[0].storage_keys.is_empty());
+    assert_eq!(diff.accounts[1].address, c);
+    assert_eq!(diff.accounts[1].before, diff.accounts[1].after);
+    assert_eq!(diff.accounts[1].storage_keys, vec![vec![1]]);
+
+    // Reverting the changes leaves nothing to report.
+    state.revert_to_checkpoint().unwrap();
+    assert!(state.checkpoint_diff(outer).unwrap().is_empty());
+    assert!(state.checkpoint_diff(inner).unwrap().is_empty());
+
+    state.add_balance(&a_s, &U256::from(1), CleanupMode::NoEmpty).unwrap();
+    let inner = state.checkpoint();
+    state.add_balance(&a_s, &U256::from(2), CleanupMode::NoEmpty).unwrap();
+    state.add_balance(&b_s, &U256::from(4), CleanupMode::NoEmpty).unwrap();
+    let diff = state.checkpoint_diff(inner).unwrap();
+    assert_eq!(diff.accounts.len(), 2);
+    assert_eq!(
+        diff.accounts[0].before.as_ref().unwrap().balance,
+        U256::from(1)
+    );
+    // The discarded changes belong to the parent checkpoint.
+    state.discard_checkpoint().unwrap();
+    assert!(state.checkpoint_diff(inner).unwrap().is_empty());
+    let diff = state.checkpoint_diff(outer).unwrap();
+    assert_eq!(diff.accounts.len(), 2);
+    assert_eq!(diff.accounts[0].address, a);
+    assert_eq!(diff.accounts[0].before, None);
+    assert_eq!(
+        diff.accounts[0].after.as_ref().unwrap().balance,
+        U256::from(3)
+    );
+    assert_eq!(diff.accounts[1].address, b);
+    assert_eq!(
+        diff.accounts[1].after.as_ref().unwrap().balance,
+        U256::from(4)
+    );
+    // The diff is serializable for RPC.
+    assert!(serde_json::to_string(&diff).unwrap().contains("storageKeys"));
+}
+
+#[test]
+fn accounts_equal_compares_all_fields() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let user = |i: u64| {
+        let mut address = Address::from_low_u64_be(i);
+        address.set_user_account_type_bits();
+        address.with_native_space()
+    };
+    let contract = |i: u64| {
+        let mut address = Address::from_low_u64_be(i);
+        address.set_contract_type_bits();
+        address.with_native_space()
+    };
+    let (a, b, c, d) = (user(1), user(2), user(3), user(4));
+    let (e, f) = (contract(5), contract(6));
+    let code = vec![0x60, 0x00];
+
+    for address in &[a, b, c] {
+        state
+            .add_balance(address, &U256::from(10), CleanupMode::NoEmpty)
+            .unwrap();
+    }
+    state.inc_nonce(&c).unwrap();
+    for address in &[e, f] {
+        state.new_contract_with_code(address, U256::zero()).unwrap();
+        state.init_code(address, code.clone(), Address::zero()).unwrap();
+    }
+    state.set_storage(&f, vec![1], U256::from(7), f.address).unwrap();
+    let mut substate = Substate::new();
+    state.collect_ownership_changed(&mut substate).unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let state = get_state(&storage_manager, &epoch_id);
+    assert!(state.accounts_equal(&a, &a).unwrap());
+    assert!(state.accounts_equal(&a, &b).unwrap());
+    // The nonce differs.
+    assert!(!state.accounts_equal(&a, &c).unwrap());
+    // Only one of them exists.
+    assert!(!state.accounts_equal(&a, &d).unwrap());
+    assert!(state.accounts_equal(&d, &user(7)).unwrap());
+    // The code differs.
+    assert!(!state.accounts_equal(&a, &e).unwrap());
+    // The storage differs.
+    assert!(!state.accounts_equal(&e, &f).unwrap());
+}
+
+#[test]
+fn checkpoint_storage_at_kill_then_recreate() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut c = Address::from_low_u64_be(1);
+    c.set_contract_type_bits();
+    let c_s = c.with_native_space();
+    let k = vec![1];
+    state.new_contract_with_code(&c_s, U256::zero()).unwrap();
+    state.set_storage(&c_s, k.clone(), U256::from(5), c).unwrap();
+    let mut substate = Substate::new();
+    state.collect_ownership_changed(&mut substate).unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    let before_kill = state.checkpoint();
+    state.remove_contract(&c_s).unwrap();
+    let after_kill = state.checkpoint();
+    state.new_contract_with_code(&c_s, U256::zero()).unwrap();
+    state.set_storage(&c_s, k.clone(), U256::from(9), c).unwrap();
+
+    assert_eq!(
+        state.checkpoint_storage_at(before_kill, &c_s, &k).unwrap(),
+        Some(U256::from(5))
+    );
+    // The storage on disk is discarded by the kill.
+    assert_eq!(
+        state.checkpoint_storage_at(after_kill, &c_s, &k).unwrap(),
+        Some(U256::zero())
+    );
+    assert_eq!(state.storage_at(&c_s, &k).unwrap(), U256::from(9));
+}
+
+#[test]
+fn checkpoint_storage_at_newly_created_contract() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut c = Address::from_low_u64_be(1);
+    c.set_contract_type_bits();
+    let c_s = c.with_native_space();
+    let k = vec![1];
+
+    let before_creation = state.checkpoint();
+    state.new_contract_with_code(&c_s, U256::zero()).unwrap();
+    let after_creation = state.checkpoint();
+    state.set_storage(&c_s, k.clone(), U256::from(3), c).unwrap();
+
+    assert_eq!(
+        state.checkpoint_storage_at(before_creation, &c_s, &k).unwrap(),
+        Some(U256::zero())
+    );
+    assert_eq!(
+        state.checkpoint_storage_at(after_creation, &c_s, &k).unwrap(),
+        Some(U256::zero())
+    );
+    assert_eq!(
+        state.checkpoint_storage_at(after_creation + 1, &c_s, &k).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn checkpoint_storage_at_write_cache_only_keys() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut c = Address::from_low_u64_be(1);
+    c.set_contract_type_bits();
+    let c_s = c.with_native_space();
+    let (k, k2) = (vec![1], vec![2]);
+    state.new_contract_with_code(&c_s, U256::zero()).unwrap();
+    state.set_storage(&c_s, k.clone(), U256::from(5), c).unwrap();
+    let mut substate = Substate::new();
+    state.collect_ownership_changed(&mut substate).unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    let c0 = state.checkpoint();
+    state.set_storage(&c_s, k.clone(), U256::from(6), c).unwrap();
+    state.set_storage(&c_s, k2.clone(), U256::from(4), c).unwrap();
+    let c1 = state.checkpoint();
+    state.set_storage(&c_s, k2.clone(), U256::from(8), c).unwrap();
+
+    assert_eq!(
+        state.checkpoint_storage_at(c0, &c_s, &k).unwrap(),
+        Some(U256::from(5))
+    );
+    assert_eq!(
+        state.checkpoint_storage_at(c0, &c_s, &k2).unwrap(),
+        Some(U256::zero())
+    );
+    assert_eq!(
+        state.checkpoint_storage_at(c1, &c_s, &k).unwrap(),
+        Some(U256::from(6))
+    );
+    assert_eq!(
+        state.checkpoint_storage_at(c1, &c_s, &k2).unwrap(),
+        Some(U256::from(4))
+    );
+}
+
+#[test]
+fn set_sponsor_for_collateral_keeps_converted_storage_points() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut sponsor = Address::zero();
+    sponsor.set_user_account_type_bits();
+    let contracts: Vec<_> = (1..=3u64)
+        .map(|i| {
+            let mut contract = Address::from_low_u64_be(i);
+            contract.set_contract_type_bits();
+            contract
+        })
+        .collect();
+
+    // Half of the sponsored collateral is converted to storage points.
+    state
+        .set_system_storage(
+            storage_point_prop().to_vec(),
+            U256::from(ONE_CFX_IN_DRIP),
+        )
+        .unwrap();
+    for contract in &contracts {
+        state
+            .new_contract_with_admin(
+                &contract.with_native_space(),
+                &sponsor,
+                U256::zero(),
+                Some(STORAGE_LAYOUT_REGULAR_V0),
+                /* cip107 = */ true,
+            )
+            .unwrap();
+    }
+    state.add_total_issued(U256::from(10000));
+    let converted_before = state.converted_storage_points();
+    let balances = [1000u64, 300, 3000, 100, 2000];
+    for (i, balance) in balances.iter().enumerate() {
+        state
+            .set_sponsor_for_collateral(
+                &contracts[i % contracts.len()],
+                &sponsor,
+                &U256::from(*balance),
+                /* is_cip107 = */ true,
+            )
+            .unwrap();
+    }
+
+    let mut unused = U256::zero();
+    for contract in &contracts {
+        let sponsor_info = state.sponsor_info(contract).unwrap().unwrap();
+        unused += sponsor_info.storage_points.unwrap().unused;
+    }
+    assert!(!unused.is_zero());
+    assert_eq!(state.converted_storage_points() - converted_before, unused);
+    assert_eq!(state.unused_storage_points(), unused);
+}
+
+#[test]
+fn replace_sponsor_with_same_sponsor() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut contract = Address::from_low_u64_be(1);
+    contract.set_contract_type_bits();
+    let mut sponsor = Address::from_low_u64_be(2);
+    sponsor.set_user_account_type_bits();
+    let sponsor_s = sponsor.with_native_space();
+    let mut other = Address::from_low_u64_be(3);
+    other.set_user_account_type_bits();
+
+    state
+        .new_contract_with_code(&contract.with_native_space(), U256::zero())
+        .unwrap();
+    state
+        .set_sponsor_for_gas(
+            &contract,
+            &sponsor,
+            &U256::from(100),
+            &U256::from(10),
+        )
+        .unwrap();
+    state
+        .set_sponsor_for_collateral(
+            &contract,
+            &sponsor,
+            &U256::from(200),
+            /* is_cip107 = */ false,
+        )
+        .unwrap();
+    let sponsor_info = state.sponsor_info(&contract).unwrap().unwrap();
+
+    // A reverted replacement leaves the sponsor untouched.
+    state.checkpoint();
+    state
+        .replace_sponsor(
+            &contract,
+            &other,
+            &U256::from(1),
+            &U256::from(1),
+            &U256::from(1),
+            /* is_cip107 = */ false,
+            &mut CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    state.revert_to_checkpoint().unwrap();
+    assert_eq!(state.sponsor_info(&contract).unwrap().unwrap(), sponsor_info);
+    assert_eq!(state.balance(&sponsor_s).unwrap(), U256::zero());
+
+    let outcome = state
+        .replace_sponsor(
+            &contract,
+            &sponsor,
+            &U256::from(50),
+            &U256::from(5),
+            &U256::from(80),
+            /* is_cip107 = */ false,
+            &mut CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    assert_eq!(outcome.refunds, vec![(sponsor, U256::from(300))]);
+    assert_eq!(outcome.converted_storage_points, U256::zero());
+    assert_eq!(state.balance(&sponsor_s).unwrap(), U256::from(300));
+    assert_eq!(state.sponsor_for_gas(&contract).unwrap(), Some(sponsor));
+    assert_eq!(
+        state.sponsor_for_collateral(&contract).unwrap(),
+        Some(sponsor)
+    );
+    assert_eq!(
+        state.sponsor_balance_for_gas(&contract).unwrap(),
+        U256::from(50)
+    );
+    assert_eq!(state.sponsor_gas_bound(&contract).unwrap(), U256::from(5));
+    assert_eq!(
+        state.sponsor_balance_for_collateral(&contract).unwrap(),
+        U256::from(80)
+    );
+}
+
+#[test]
+fn storage_point_prop_cache_invalidation() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let set_prop = |state: &mut State, prop: u64| {
+        state
+            .set_system_storage(storage_point_prop().to_vec(), prop.into())
+            .unwrap();
+    };
+
+    set_prop(&mut state, 1);
+    assert_eq!(state.storage_point_prop().unwrap(), U256::from(1));
+    assert_eq!(*state.storage_point_prop_cache.read(), Some(U256::from(1)));
+
+    // Updating the prop, e.g. by the DAO vote, invalidates the cache.
+    set_prop(&mut state, 2);
+    assert_eq!(*state.storage_point_prop_cache.read(), None);
+    assert_eq!(state.storage_point_prop().unwrap(), U256::from(2));
+
+    // So does reverting the update.
+    state.checkpoint();
+    set_prop(&mut state, 3);
+    assert_eq!(state.storage_point_prop().unwrap(), U256::from(3));
+    state.revert_to_checkpoint().unwrap();
+    assert_eq!(state.storage_point_prop().unwrap(), U256::from(2));
+
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+    assert_eq!(*state.storage_point_prop_cache.read(), None);
+    let state = get_state(&storage_manager, &epoch_id);
+    assert_eq!(state.storage_point_prop().unwrap(), U256::from(2));
+}
+
+#[test]
+fn supply_breakdown_matches_getters() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let stat = &mut state.world_statistics;
+    stat.total_issued_tokens = 1.into();
+    stat.total_staking_tokens = 2.into();
+    stat.total_storage_tokens = 3.into();
+    stat.total_evm_tokens = 4.into();
+    stat.total_pos_staking_tokens = 5.into();
+    stat.distributable_pos_interest = 6.into();
+    stat.used_storage_points = 7.into();
+    stat.converted_storage_points = 8.into();
+
+    let breakdown = state.supply_breakdown();
+    assert_eq!(breakdown.total_issued_tokens, state.total_issued_tokens());
+    assert_eq!(breakdown.total_staking_tokens, state.total_staking_tokens());
+    assert_eq!(breakdown.total_storage_tokens, state.total_storage_tokens());
+    assert_eq!(breakdown.total_espace_tokens, state.total_espace_tokens());
+    assert_eq!(
+        breakdown.total_pos_staking_tokens,
+        state.total_pos_staking_tokens()
+    );
+    assert_eq!(
+        breakdown.distributable_pos_interest,
+        state.distributable_pos_interest()
+    );
+    assert_eq!(breakdown.used_storage_points, state.used_storage_points());
+    assert_eq!(
+        breakdown.converted_storage_points,
+        state.converted_storage_points()
+    );
+    assert_eq!(breakdown.total_espace_tokens, U256::from(4));
+    assert_eq!(breakdown.converted_storage_points, U256::from(8));
+}
+
+#[test]
+fn commission_privilege_list_merges_pending_changes() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut contract = Address::from_low_u64_be(100);
+    contract.set_contract_type_bits();
+    let mut other_contract = Address::from_low_u64_be(101);
+    other_contract.set_contract_type_bits();
+    let owner = Address::from_low_u64_be(1);
+    let users = (1..=3u64)
+        .map(|i| {
+            let mut user = Address::from_low_u64_be(i);
+            user.set_user_account_type_bits();
+            user
+        })
+        .collect::<Vec<_>>();
+
+    assert!(state.commission_privilege_list(&contract).unwrap().is_empty());
+    state
+        .add_commission_privilege(contract, owner, users[1])
+        .unwrap();
+    state
+        .add_commission_privilege(contract, owner, users[0])
+        .unwrap();
+    state
+        .add_commission_privilege(other_contract, owner, users[2])
+        .unwrap();
+    assert_eq!(
+        state.commission_privilege_list(&contract).unwrap(),
+        vec![users[0], users[1]]
+    );
+    state
+        .collect_ownership_changed(&mut Substate::new())
+        .unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    assert_eq!(
+        state.commission_privilege_list(&contract).unwrap(),
+        vec![users[0], users[1]]
+    );
+    // Removing a committed user and adding a new one.
+    state
+        .remove_commission_privilege(contract, owner, users[0])
+        .unwrap();
+    state
+        .add_commission_privilege(contract, owner, users[2])
+        .unwrap();
+    assert_eq!(
+        state.commission_privilege_list(&contract).unwrap(),
+        vec![users[1], users[2]]
+    );
+
+    // The wildcard whitelisting all users is listed first.
+    state
+        .add_commission_privilege(
+            contract,
+            owner,
+            *COMMISSION_PRIVILEGE_SPECIAL_KEY,
+        )
+        .unwrap();
+    assert_eq!(
+        state.commission_privilege_list(&contract).unwrap(),
+        vec![*COMMISSION_PRIVILEGE_SPECIAL_KEY, users[1], users[2]]
+    );
+    assert!(state
+        .check_commission_privilege(&contract, &users[0])
+        .unwrap());
+    state
+        .remove_commission_privilege(
+            contract,
+            owner,
+            *COMMISSION_PRIVILEGE_SPECIAL_KEY,
+        )
+        .unwrap();
+    assert_eq!(
+        state.commission_privilege_list(&contract).unwrap(),
+        vec![users[1], users[2]]
+    );
+}
+
+#[test]
+fn import_account_and_commit() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut address = Address::from_low_u64_be(7);
+    address.set_contract_type_bits();
+    let address_with_space = address.with_native_space();
+    let evm_address = Address::from_low_u64_be(8).with_evm_space();
+    let code = vec![0x60, 0x00, 0x60, 0x00];
+    let key = u256_to_vec(&U256::from(1));
+    let storage_points = StoragePoints {
+        unused: U256::from(30),
+        used: U256::zero(),
+    };
+
+    let mut account = Account::new_empty_with_balance(
+        &address_with_space,
+        &U256::from(1000),
+        &U256::from(3),
+    );
+    account.staking_balance = U256::from(200);
+    account.admin = Address::from_low_u64_be(9);
+    account.sponsor_info.sponsor_balance_for_collateral = U256::from(50);
+    account.sponsor_info.storage_points = Some(storage_points.clone());
+    state
+        .import_account(
+            &address_with_space,
+            account,
+            vec![(key.clone(), U256::from(42))],
+            Some(code.clone()),
+        )
+        .unwrap();
+    // The CIP-107 storage points do not exist in the eSpace.
+    let mut evm_account = Account::new_empty_with_balance(
+        &evm_address,
+        &U256::from(10),
+        &U256::from(1),
+    );
+    evm_account.sponsor_info.storage_points = Some(storage_points.clone());
+    state
+        .import_account(&evm_address, evm_account, vec![], None)
+        .unwrap();
+
+    state
+        .collect_ownership_changed(&mut Substate::new())
+        .unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let state = get_state(&storage_manager, &epoch_id);
+    assert_eq!(state.balance(&address_with_space).unwrap(), 1000.into());
+    assert_eq!(state.nonce(&address_with_space).unwrap(), 3.into());
+    assert_eq!(state.staking_balance(&address).unwrap(), 200.into());
+    assert_eq!(state.admin(&address).unwrap(), Address::from_low_u64_be(9));
+    assert_eq!(
+        state.code_hash(&address_with_space).unwrap(),
+        Some(keccak(&code))
+    );
+    assert_eq!(
+        state.code(&address_with_space).unwrap().as_deref(),
+        Some(&code)
+    );
+    assert_eq!(
+        state.storage_at(&address_with_space, &key).unwrap(),
+        U256::from(42)
+    );
+    let sponsor_info = state.sponsor_info(&address).unwrap().unwrap();
+    assert_eq!(sponsor_info.sponsor_balance_for_collateral, 50.into());
+    assert_eq!(sponsor_info.storage_points, Some(storage_points));
+    assert_eq!(state.balance(&evm_address).unwrap(), 10.into());
+    assert_eq!(state.nonce(&evm_address).unwrap(), 1.into());
+    assert_eq!(state.code_hash(&evm_address).unwrap(), Some(KECCAK_EMPTY));
+}
+
+#[test]
+fn distribute_pos_interest_skips_zero_address() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let registered = H256::from_low_u64_be(1);
+    let unregistered = H256::from_low_u64_be(2);
+    let mut reward_address = Address::from_low_u64_be(3);
+    reward_address.set_user_account_type_bits();
+    state
+        .set_storage(
+            &POS_REGISTER_CONTRACT_ADDRESS.with_native_space(),
+            pos_internal_entries::address_entry(&registered),
+            H256::from(reward_address).into_uint(),
+            Address::zero(),
+        )
+        .unwrap();
+    state.world_statistics.distributable_pos_interest = U256::from(1000);
+    let issued_before = state.total_issued_tokens();
+
+    let pos_points = vec![
+        (registered, MAX_TERM_POINTS / 2),
+        (unregistered, MAX_TERM_POINTS / 4),
+    ];
+    let (account_rewards, forfeited) = state
+        .distribute_pos_interest(
+            Box::new(pos_points.iter().map(|(id, points)| (id, *points))),
+            /* current_block_number = */ 1,
+            /* skip_unregistered = */ true,
+        )
+        .unwrap();
+
+    assert_eq!(
+        account_rewards,
+        vec![(reward_address, registered, U256::from(500))]
+    );
+    assert_eq!(forfeited, U256::from(250));
+    assert_eq!(
+        state.balance(&reward_address.with_native_space()).unwrap(),
+        U256::from(500)
+    );
+    assert_eq!(
+        state.balance(&Address::zero().with_native_space()).unwrap(),
+        U256::zero()
+    );
+    assert_eq!(state.total_issued_tokens(), issued_before + U256::from(500));
+    // The forfeited interest is kept for the next distribution.
+    assert_eq!(state.distributable_pos_interest(), U256::from(250));
+}
+
+#[test]
+fn distribute_pos_interest_rewards_zero_address_before_transition() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let unregistered = H256::from_low_u64_be(1);
+    state.world_statistics.distributable_pos_interest = U256::from(1000);
+    let issued_before = state.total_issued_tokens();
+
+    let pos_points = vec![(unregistered, MAX_TERM_POINTS / 4)];
+    let (account_rewards, forfeited) = state
+        .distribute_pos_interest(
+            Box::new(pos_points.iter().map(|(id, points)| (id, *points))),
+            /* current_block_number = */ 1,
+            /* skip_unregistered = */ false,
+        )
+        .unwrap();
+
+    assert_eq!(
+        account_rewards,
+        vec![(Address::zero(), unregistered, U256::from(250))]
+    );
+    assert!(forfeited.is_zero());
+    assert_eq!(
+        state.balance(&Address::zero().with_native_space()).unwrap(),
+        U256::from(250)
+    );
+    assert_eq!(state.total_issued_tokens(), issued_before + U256::from(250));
+    assert!(state.distributable_pos_interest().is_zero());
+}
+
+#[test]
+fn distribute_pos_interest_summary_matches_rewards() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let points =
+        [MAX_TERM_POINTS / 2, MAX_TERM_POINTS / 4, MAX_TERM_POINTS / 8];
+    let mut pos_points = Vec::new();
+    for (i, points) in points.iter().enumerate() {
+        let identifier = H256::from_low_u64_be(i as u64 + 1);
+        let mut reward_address = Address::from_low_u64_be(i as u64 + 10);
+        reward_address.set_user_account_type_bits();
+        state
+            .set_storage(
+                &POS_REGISTER_CONTRACT_ADDRESS.with_native_space(),
+                pos_internal_entries::address_entry(&identifier),
+                H256::from(reward_address).into_uint(),
+                Address::zero(),
+            )
+            .unwrap();
+        pos_points.push((identifier, *points));
+    }
+    // Forfeited interest is not part of the summary.
+    pos_points.push((H256::from_low_u64_be(100), MAX_TERM_POINTS / 8));
+    state.world_statistics.distributable_pos_interest = U256::from(1000);
+
+    let (account_rewards, forfeited, summary) = state
+        .distribute_pos_interest_with_summary(
+            Box::new(pos_points.iter().map(|(id, points)| (id, *points))),
+            /* current_block_number = */ 1,
+            /* skip_unregistered = */ true,
+        )
+        .unwrap();
+
+    let rewards: Vec<U256> =
+        account_rewards.iter().map(|(_, _, reward)| *reward).collect();
+    assert_eq!(rewards.len(), 3);
+    let total = rewards.iter().fold(U256::zero(), |a, b| a + b);
+    assert_eq!(summary.total, total);
+    assert_eq!(summary.recipients, 3);
+    assert_eq!(summary.max, rewards[0]);
+    assert_eq!(summary.min, rewards[2]);
+    assert_eq!(summary.average(), total / U256::from(3));
+    assert_eq!(forfeited, rewards[2]);
+
+    assert_eq!(
+        PosDistributionSummary::from_rewards(&[]),
+        PosDistributionSummary::default()
+    );
+    assert!(PosDistributionSummary::default().average().is_zero());
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn checkpoint_leak_detected_on_drop() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let new_observed_state = || {
+        let mut state = get_state_for_genesis_write(&storage_manager);
+        let events = events.clone();
+        state.set_checkpoint_observer(Some(Box::new(move |event, depth| {
+            events.lock().push((event, depth))
+        })));
+        state
+    };
+
+    let mut state = new_observed_state();
+    state.checkpoint();
+    state.checkpoint();
+    state.discard_checkpoint().unwrap();
+    state.revert_to_checkpoint().unwrap();
+    assert!(!state.has_open_checkpoints());
+    drop(state);
+    assert!(events
+        .lock()
+        .iter()
+        .all(|(event, _)| *event != CheckpointEvent::Leak));
+
+    let mut state = new_observed_state();
+    state.checkpoint();
+    state.checkpoint();
+    state.discard_checkpoint().unwrap();
+    assert!(state.has_open_checkpoints());
+    events.lock().clear();
+    drop(state);
+    assert_eq!(*events.lock(), vec![(CheckpointEvent::Leak, 1)]);
+}
+
+#[test]
+fn balanced_checkpoints_commit() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    state.checkpoint();
+    state.checkpoint();
+    state.discard_checkpoint().unwrap();
+    state.revert_to_checkpoint().unwrap();
+    assert!(!state.has_open_checkpoints());
+    state.assert_no_open_checkpoints();
+    state.commit(EpochId::from_uint(&U256::from(1)), None).unwrap();
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "State has 1 open checkpoints")]
+fn commit_with_open_checkpoint_panics() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    state.checkpoint();
+    state.checkpoint();
+    state.discard_checkpoint().unwrap();
+    assert!(state.has_open_checkpoints());
+    state.commit(EpochId::from_uint(&U256::from(1)), None).unwrap();
+}
+
+#[test]
+fn dirty_account_count_after_mutations() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+    let mut state = get_state(&storage_manager, &epoch_id);
+    let addresses = (1..=3u64)
+        .map(|i| {
+            let mut address = Address::from_low_u64_be(i);
+            address.set_user_account_type_bits();
+            address.with_native_space()
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(state.dirty_account_count(), 0);
+    // Reading an account does not make it dirty.
+    assert!(state.balance(&addresses[0]).unwrap().is_zero());
+    assert_eq!(state.dirty_account_count(), 0);
+
+    state
+        .add_balance(&addresses[0], &U256::from(1), CleanupMode::NoEmpty)
+        .unwrap();
+    state
+        .add_balance(&addresses[1], &U256::from(2), CleanupMode::NoEmpty)
+        .unwrap();
+    state.inc_nonce(&addresses[1]).unwrap();
+    assert_eq!(state.dirty_account_count(), 2);
+    state.inc_nonce(&addresses[2]).unwrap();
+    assert_eq!(state.dirty_account_count(), 3);
+    assert_eq!(state.cache_stats().dirty_entries, 3);
+
+    let epoch_id = EpochId::from_uint(&U256::from(2));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+    assert_eq!(state.dirty_account_count(), 0);
+}
+
+#[test]
+fn deposit_and_vote_stake_list_pages() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut address = Address::from_low_u64_be(1);
+    address.set_user_account_type_bits();
+    let mut absent = Address::from_low_u64_be(2);
+    absent.set_user_account_type_bits();
+    let page_size = 2;
+
+    state
+        .add_balance(
+            &address.with_native_space(),
+            &U256::from(1000),
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    for i in 1..=5u64 {
+        state.deposit(&address, &U256::from(i), i, false).unwrap();
+        state
+            .vote_lock(&address, &U256::from(60 - 10 * i), 10 * i)
+            .unwrap();
+    }
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    // The uncommitted deposit is visible as well.
+    state.deposit(&address, &U256::from(6), 6, false).unwrap();
+    assert_eq!(state.deposit_list_length(&address).unwrap(), 6);
+    let mut deposits = vec![];
+    for page in 0..3 {
+        let slice = state
+            .deposit_list_slice(&address, page * page_size, page_size)
+            .unwrap();
+        assert_eq!(slice.len(), page_size);
+        deposits.extend(slice);
+    }
+    assert_eq!(
+        deposits.iter().map(|d| d.amount).collect::<Vec<_>>(),
+        (1..=6u64).map(U256::from).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        state
+            .deposit_list_slice(&address, 5, page_size)
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(state
+        .deposit_list_slice(&address, 10, page_size)
+        .unwrap()
+        .is_empty());
+
+    let votes = state
+        .vote_stake_list_slice(&address, page_size, page_size)
+        .unwrap();
+    assert_eq!(
+        votes
+            .iter()
+            .map(|v| (v.amount, v.unlock_block_number))
+            .collect::<Vec<_>>(),
+        vec![(30.into(), 30.into()), (20.into(), 40.into())]
+    );
+    assert!(state
+        .vote_stake_list_slice(&address, 5, page_size)
+        .unwrap()
+        .is_empty());
+
+    assert!(state
+        .deposit_list_slice(&absent, 0, page_size)
+        .unwrap()
+        .is_empty());
+    assert!(state
+        .vote_stake_list_slice(&absent, 0, page_size)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn evm_tokens_reconciliation_detects_missing_update() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    state.set_check_evm_tokens(true);
+    let a = Address::from_low_u64_be(1).with_evm_space();
+    let b = Address::from_low_u64_be(2).with_evm_space();
+
+    state
+        .add_balance(&a, &U256::from(100), CleanupMode::NoEmpty)
+        .unwrap();
+    state.add_total_evm_tokens(U256::from(100));
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    state.set_check_evm_tokens(true);
+    // A transfer within the eSpace keeps the total.
+    state
+        .transfer_balance(&a, &b, &U256::from(40), CleanupMode::NoEmpty)
+        .unwrap();
+    state.checkpoint();
+    // The minted balance is not added to `total_evm_tokens`.
+    state
+        .add_balance(&b, &U256::from(10), CleanupMode::NoEmpty)
+        .unwrap();
+    state.discard_checkpoint().unwrap();
+    match state.compute_state_root(None) {
+        Err(e) => match e.kind() {
+            DbErrorKind::EvmTokensMismatch(
+                balances_before,
+                balances_after,
+                tokens_before,
+                tokens_after,
+            ) => {
+                assert_eq!(*balances_before, U256::from(100));
+                assert_eq!(*balances_after, U256::from(110));
+                assert_eq!(*tokens_before, U256::from(100));
+                assert_eq!(*tokens_after, U256::from(100));
+            }
+            kind => panic!("unexpected error: {:?}", kind),
+        },
+        Ok(_) => panic!("the missing update is not detected"),
+    }
+}
+
+#[test]
+fn transfer_balance_same_space_rejects_cross_space() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let address = Address::from_low_u64_be(1);
+    let native = address.with_native_space();
+    let evm = address.with_evm_space();
+    let other = Address::from_low_u64_be(2).with_native_space();
+    state
+        .add_balance(&native, &U256::from(100), CleanupMode::NoEmpty)
+        .unwrap();
+
+    match state.transfer_balance_same_space(
+        &native,
+        &evm,
+        &U256::from(40),
+        CleanupMode::NoEmpty,
+    ) {
+        Err(e) => match e.kind() {
+            DbErrorKind::CrossSpaceTransfer(from, to) => {
+                assert_eq!(*from, native);
+                assert_eq!(*to, evm);
+            }
+            kind => panic!("unexpected error: {:?}", kind),
+        },
+        Ok(_) => panic!("the cross-space transfer is not rejected"),
+    }
+    assert_eq!(state.balance(&native).unwrap(), U256::from(100));
+    assert_eq!(state.balance(&evm).unwrap(), U256::zero());
+
+    state
+        .transfer_balance_same_space(
+            &native,
+            &other,
+            &U256::from(40),
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    assert_eq!(state.balance(&native).unwrap(), U256::from(60));
+    assert_eq!(state.balance(&other).unwrap(), U256::from(40));
+}
+
+#[test]
+fn sweep_expired_vote_stakes_only_touches_expired() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut addresses = vec![];
+    for i in 1..=3u64 {
+        let mut address = Address::from_low_u64_be(i);
+        address.set_user_account_type_bits();
+        addresses.push(address);
+    }
+    let (mixed, unexpired, absent) = (addresses[0], addresses[1], addresses[2]);
+    for address in &[mixed, unexpired] {
+        state
+            .add_balance(
+                &address.with_native_space(),
+                &U256::from(1000),
+                CleanupMode::NoEmpty,
+            )
+            .unwrap();
+        state.deposit(address, &U256::from(100), 1, false).unwrap();
+    }
+    for i in 1..=3u64 {
+        state
+            .vote_lock(&mixed, &U256::from(40 - 10 * i), 10 * i)
+            .unwrap();
+    }
+    state.vote_lock(&unexpired, &U256::from(10), 100).unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    assert_eq!(state.sweep_expired_vote_stakes(&addresses, 20).unwrap(), 2);
+    assert_eq!(state.vote_stake_list_length(&mixed).unwrap(), 1);
+    assert_eq!(state.vote_stake_list_length(&unexpired).unwrap(), 1);
+    // Only the account with expired vote stakes is marked dirty.
+    assert_eq!(state.dirty_account_count(), 1);
+    assert_eq!(state.sweep_expired_vote_stakes(&addresses, 20).unwrap(), 0);
+
+    let epoch_id = EpochId::from_uint(&U256::from(2));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+    let state = get_state(&storage_manager, &epoch_id);
+    assert_eq!(
+        state.vote_stake_list_slice(&mixed, 0, usize::MAX).unwrap()[0]
+            .unlock_block_number,
+        U256::from(30)
+    );
+}
+
+#[test]
+fn inc_nonce_by_matches_repeated_inc_nonce() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let existing = Address::from_low_u64_be(1).with_native_space();
+    let existing_batched = Address::from_low_u64_be(2).with_native_space();
+    let fresh = Address::from_low_u64_be(3).with_evm_space();
+    let fresh_batched = Address::from_low_u64_be(4).with_evm_space();
+    for address in &[existing, existing_batched] {
+        state
+            .add_balance(address, &U256::from(100), CleanupMode::NoEmpty)
+            .unwrap();
+        state.set_nonce(address, &U256::from(7)).unwrap();
+    }
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    let count = 5;
+    for _ in 0..count {
+        state.inc_nonce(&existing).unwrap();
+        state.inc_nonce(&fresh).unwrap();
+    }
+    state.inc_nonce_by(&existing_batched, count).unwrap();
+    state.inc_nonce_by(&fresh_batched, count).unwrap();
+    assert_eq!(state.nonce(&existing).unwrap(), U256::from(12));
+    assert_eq!(
+        state.nonce(&existing_batched).unwrap(),
+        state.nonce(&existing).unwrap()
+    );
+    assert_eq!(
+        state.nonce(&fresh_batched).unwrap(),
+        state.nonce(&fresh).unwrap()
+    );
+
+    // Incrementing by zero does not create the account.
+    let untouched = Address::from_low_u64_be(5).with_native_space();
+    state.inc_nonce_by(&untouched, 0).unwrap();
+    assert!(!state.exists(&untouched).unwrap());
+}
+
+#[test]
+fn deposit_list_returns_entries_in_order() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut address = Address::from_low_u64_be(1);
+    address.set_user_account_type_bits();
+    let mut absent = Address::from_low_u64_be(2);
+    absent.set_user_account_type_bits();
+    assert!(state.deposit_list(&absent).unwrap().is_empty());
+
+    state
+        .add_balance(
+            &address.with_native_space(),
+            &U256::from(1000),
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    state.deposit(&address, &U256::from(100), 5, false).unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    state.deposit(&address, &U256::from(200), 8, false).unwrap();
+    let deposits = state.deposit_list(&address).unwrap();
+    assert_eq!(
+        deposits
+            .iter()
+            .map(|d| (d.amount, d.deposit_time))
+            .collect::<Vec<_>>(),
+        vec![(U256::from(100), U256::from(5)), (U256::from(200), U256::from(8))]
+    );
+    let rate = state.world_statistics.accumulate_interest_rate;
+    assert!(deposits.iter().all(|d| d.accumulated_interest_rate == rate));
+    assert!(state.deposit_list(&absent).unwrap().is_empty());
+}
+
+#[test]
+fn account_storage_delta_counts_net_entries() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut address = Address::zero();
+    address.set_contract_type_bits();
+    let address_with_space = address.with_native_space();
+    let keys = (0..7u64)
+        .map(|i| u256_to_vec(&U256::from(i)))
+        .collect::<Vec<_>>();
+    let set = |state: &mut State, i: usize, value: u64| {
+        state
+            .set_storage(
+                &address_with_space,
+                keys[i].clone(),
+                value.into(),
+                address,
+            )
+            .unwrap()
+    };
+
+    state
+        .new_contract_with_code(&address_with_space, U256::zero())
+        .unwrap();
+    for i in 1..=3 {
+        set(&mut state, i, 1);
+    }
+    // The storage of a new contract is all new.
+    assert_eq!(state.account_storage_delta(&address_with_space).unwrap(), 3);
+    state
+        .collect_ownership_changed(&mut Substate::new())
+        .unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    assert_eq!(state.account_storage_delta(&address_with_space).unwrap(), 0);
+    // Two slots added, one removed and one modified.
+    set(&mut state, 4, 1);
+    set(&mut state, 5, 1);
+    set(&mut state, 1, 0);
+    set(&mut state, 2, 2);
+    // A slot added and then removed again.
+    set(&mut state, 6, 1);
+    set(&mut state, 6, 0);
+    assert_eq!(state.account_storage_delta(&address_with_space).unwrap(), 1);
+    set(&mut state, 3, 0);
+    assert_eq!(state.account_storage_delta(&address_with_space).unwrap(), 0);
+
+    let absent = Address::from_low_u64_be(1).with_native_space();
+    assert_eq!(state.account_storage_delta(&absent).unwrap(), 0);
+}
+
+#[test]
+fn vote_stake_list_returns_locks_in_order() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut address = Address::from_low_u64_be(1);
+    address.set_user_account_type_bits();
+    let mut absent = Address::from_low_u64_be(2);
+    absent.set_user_account_type_bits();
+    assert!(state.vote_stake_list(&absent).unwrap().is_empty());
+
+    state
+        .add_balance(
+            &address.with_native_space(),
+            &U256::from(1000),
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    state.deposit(&address, &U256::from(500), 1, false).unwrap();
+    assert!(state.vote_stake_list(&address).unwrap().is_empty());
+    state.vote_lock(&address, &U256::from(100), 200).unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    state.vote_lock(&address, &U256::from(300), 100).unwrap();
+    assert_eq!(
+        state
+            .vote_stake_list(&address)
+            .unwrap()
+            .iter()
+            .map(|v| (v.amount, v.unlock_block_number))
+            .collect::<Vec<_>>(),
+        vec![
+            (U256::from(300), U256::from(100)),
+            (U256::from(100), U256::from(200))
+        ]
+    );
+}
+
+#[test]
+fn total_circulating_tokens_matches_pos_interest_formula() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let cfx = |n: u64| U256::from(ONE_CFX_IN_DRIP) * U256::from(n);
+    let zero = Address::zero().with_native_space();
+    let four_year = genesis_contract_address_four_year();
+    let two_year = genesis_contract_address_two_year();
+    state.add_total_issued(cfx(1000000));
+    state.add_total_pos_staking(*POS_VOTE_PRICE * U256::from(10));
+    for (address, balance) in &[(zero, 1), (four_year, 400), (two_year, 200)] {
+        state
+            .add_balance(address, &cfx(*balance), CleanupMode::NoEmpty)
+            .unwrap();
+    }
+    let inline = |state: &State| {
+        state.total_issued_tokens()
+            - state.balance(&zero).unwrap()
+            - state.balance(&four_year).unwrap()
+            - state.balance(&two_year).unwrap()
+    };
+    assert_eq!(state.total_circulating_tokens().unwrap(), inline(&state));
+    assert_eq!(state.total_circulating_tokens().unwrap(), cfx(999399));
+
+    let expected = pos_interest_increment(
+        inline(&state),
+        state.total_pos_staking_tokens(),
+        state.world_statistics.interest_rate_per_block,
+        BLOCKS_PER_YEAR,
+    );
+    state.inc_distributable_pos_interest(1).unwrap();
+    assert_eq!(state.distributable_pos_interest(), expected);
+
+    // The cached genesis locked tokens follow the balance changes.
+    state.checkpoint();
+    state
+        .sub_balance(&four_year, &cfx(100), &mut CleanupMode::NoEmpty)
+        .unwrap();
+    assert_eq!(state.total_circulating_tokens().unwrap(), inline(&state));
+    state.revert_to_checkpoint().unwrap();
+    assert_eq!(state.total_circulating_tokens().unwrap(), inline(&state));
+    assert_eq!(state.total_circulating_tokens().unwrap(), cfx(999399));
+}
+
+#[test]
+fn interest_rate_per_block_matches_on_new_and_clear() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let annual_rates = [
+        U256::zero(),
+        U256::from(BLOCKS_PER_YEAR) - U256::one(),
+        *INITIAL_INTEREST_RATE_PER_BLOCK * U256::from(BLOCKS_PER_YEAR),
+        *INTEREST_RATE_PER_BLOCK_SCALE * U256::from(7) + U256::from(12345),
+    ];
+    for (i, annual) in annual_rates.iter().enumerate() {
+        state.db.set_annual_interest_rate(annual, None).unwrap();
+        state.clear();
+        let per_block = state.world_statistics.interest_rate_per_block;
+        assert_eq!(per_block, annual_to_per_block(*annual));
+        assert_eq!(per_block, *annual / U256::from(BLOCKS_PER_YEAR));
+
+        let epoch_id = EpochId::from_uint(&U256::from(i + 1));
+        state.commit(epoch_id, /* debug_record = */ None).unwrap();
+        state = get_state(&storage_manager, &epoch_id);
+        assert_eq!(state.world_statistics.interest_rate_per_block, per_block);
+    }
+}
+
+#[test]
+fn can_deposit_does_not_change_state() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut address = Address::from_low_u64_be(1);
+    address.set_user_account_type_bits();
+    let mut absent = Address::from_low_u64_be(2);
+    absent.set_user_account_type_bits();
+    state
+        .add_balance(
+            &address.with_native_space(),
+            &U256::from(1000),
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut state = get_state(&storage_manager, &epoch_id);
+    let staking_before = state.total_staking_tokens();
+    assert!(state.can_deposit(&address, &U256::from(1000)).unwrap());
+    assert!(!state.can_deposit(&address, &U256::zero()).unwrap());
+    assert!(!state.can_deposit(&address, &U256::from(1001)).unwrap());
+    assert!(!state.can_deposit(&absent, &U256::from(1)).unwrap());
+    assert_eq!(state.dirty_account_count(), 0);
+    assert_eq!(state.total_staking_tokens(), staking_before);
+    assert!(state.deposit_list(&address).unwrap().is_empty());
+
+    state.deposit(&address, &U256::from(1000), 1, false).unwrap();
+    assert_eq!(state.staking_balance(&address).unwrap(), U256::from(1000));
+}
+
+#[test]
+fn withdraw_rejects_inconsistent_total_staking_tokens() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    let a_s = a.with_native_space();
+
+    state
+        .add_balance(&a_s, &U256::from(1000), CleanupMode::NoEmpty)
+        .unwrap();
+    state.deposit(&a, &U256::from(1000), 0, false).unwrap();
+    state.world_statistics.total_staking_tokens = U256::from(100);
+
+    match state.withdraw(&a, &U256::from(1000), false) {
+        Err(e) => match e.kind() {
+            DbErrorKind::WorldStatisticsUnderflow(statistic, value, amount) => {
+                assert_eq!(*statistic, "total_staking_tokens");
+                assert_eq!(*value, U256::from(100));
+                assert_eq!(*amount, U256::from(1000));
+            }
+            kind => panic!("unexpected error {:?}", kind),
+        },
+        Ok(_) => panic!("the underflow is not detected"),
+    }
+    // Nothing is withdrawn.
+    assert_eq!(state.total_staking_tokens(), U256::from(100));
+    assert_eq!(state.staking_balance(&a).unwrap(), U256::from(1000));
+}
+
+#[test]
+#[should_panic(expected = "total_staking_tokens 100 is less than")]
+fn strict_world_statistics_panics_on_underflow() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    state.set_strict_world_statistics(true);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+
+    state
+        .add_balance(
+            &a.with_native_space(),
+            &U256::from(1000),
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    state.deposit(&a, &U256::from(1000), 0, false).unwrap();
+    state.world_statistics.total_staking_tokens = U256::from(100);
+    let _ = state.withdraw(&a, &U256::from(1000), false);
+}
+
+#[test]
+fn strict_world_statistics_checks_staking_balances_on_commit() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    state.set_strict_world_statistics(true);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+
+    state
+        .add_balance(
+            &a.with_native_space(),
+            &U256::from(1000),
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    state.deposit(&a, &U256::from(1000), 0, false).unwrap();
+    state
+        .commit(EpochId::from_uint(&U256::from(1)), /* debug_record = */ None)
+        .unwrap();
+
+    let mut state =
+        get_state(&storage_manager, &EpochId::from_uint(&U256::from(1)));
+    state.set_strict_world_statistics(true);
+    state.withdraw(&a, &U256::from(400), false).unwrap();
+    assert_eq!(state.total_staking_tokens(), U256::from(600));
+    state.compute_state_root(None).unwrap();
+
+    // A counter out of sync with the staking balances fails the check.
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        state.world_statistics.total_staking_tokens = U256::from(500);
+        state.compute_state_root(None)
+    }));
+    assert!(result.is_err());
+}
+
+#[test]
+fn remove_expired_vote_stake_info_many_matches_single_version() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut addresses = vec![];
+    for i in 1..=3u64 {
+        let mut address = Address::from_low_u64_be(i);
+        address.set_user_account_type_bits();
+        addresses.push(address);
+    }
+    let (mixed, unexpired, absent) = (addresses[0], addresses[1], addresses[2]);
+    for address in &[mixed, unexpired] {
+        state
+            .add_balance(
+                &address.with_native_space(),
+                &U256::from(1000),
+                CleanupMode::NoEmpty,
+            )
+            .unwrap();
+        state.deposit(address, &U256::from(100), 1, false).unwrap();
+    }
+    for i in 1..=3u64 {
+        state
+            .vote_lock(&mixed, &U256::from(40 - 10 * i), 10 * i)
+            .unwrap();
+    }
+    state.vote_lock(&unexpired, &U256::from(10), 100).unwrap();
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let mut batched = get_state(&storage_manager, &epoch_id);
+    batched
+        .remove_expired_vote_stake_info_many(&addresses, 20)
+        .unwrap();
+    let mut single = get_state(&storage_manager, &epoch_id);
+    for address in &[mixed, unexpired] {
+        single.remove_expired_vote_stake_info(address, 20).unwrap();
+    }
+
+    for address in &[mixed, unexpired] {
+        assert_eq!(
+            batched.vote_stake_list(address).unwrap(),
+            single.vote_stake_list(address).unwrap()
+        );
+    }
+    assert_eq!(batched.vote_stake_list_length(&mixed).unwrap(), 1);
+    assert_eq!(batched.vote_stake_list_length(&unexpired).unwrap(), 1);
+    assert_eq!(batched.dirty_account_count(), single.dirty_account_count());
+    assert!(!batched.exists(&absent.with_native_space()).unwrap());
+}
+
+#[test]
+fn last_statistics_delta_reports_epoch_changes() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut a = Address::zero();
+    a.set_user_account_type_bits();
+    assert!(state.last_statistics_delta().is_none());
+
+    state.add_total_issued(U256::from(10000));
+    state.subtract_total_issued(U256::from(1000));
+    state
+        .add_balance(
+            &a.with_native_space(),
+            &U256::from(5000),
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    state.deposit(&a, &U256::from(3000), 0, false).unwrap();
+    state
+        .add_collateral_for_storage(&a, &U256::from(500))
+        .unwrap();
+    state.add_total_pos_staking(U256::from(200));
+    let epoch_id = EpochId::from_uint(&U256::from(1));
+    state.commit(epoch_id, /* debug_record = */ None).unwrap();
+
+    let change = |old: u64, new: u64| StatisticChange {
+        old: U256::from(old),
+        new: U256::from(new),
+    };
+    let delta = *state.last_statistics_delta().unwrap();
+    assert_eq!(delta.total_issued_tokens, change(0, 9000));
+    assert_eq!(delta.total_staking_tokens, change(0, 3000));
+    assert_eq!(delta.total_storage_tokens, change(0, 500));
+    assert_eq!(delta.total_pos_staking_tokens, change(0, 200));
+    assert!(!delta.total_evm_tokens.is_changed());
+    assert!(!delta.used_storage_points.is_changed());
+
+    // The next epoch starts from the committed values.
+    let mut state = get_state(&storage_manager, &epoch_id);
+    assert!(state.last_statistics_delta().is_none());
+    state.subtract_total_issued(U256::from(500));
+    state.withdraw(&a, &U256::from(1000), false).unwrap();
+    state
+        .commit(EpochId::from_uint(&U256::from(2)), /* debug_record = */ None)
+        .unwrap();
+    let delta = *state.last_statistics_delta().unwrap();
+    assert_eq!(delta.total_issued_tokens, change(9000, 8500));
+    assert_eq!(delta.total_staking_tokens, change(3000, 2000));
+    assert!(!delta.total_storage_tokens.is_changed());
+    assert!(!delta.total_pos_staking_tokens.is_changed());
+}
+
+#[test]
+fn accounts_to_notify_only_holds_last_commit() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let a = Address::from_low_u64_be(1).with_native_space();
+    let b = Address::from_low_u64_be(2).with_native_space();
+    state
+        .add_balance(&a, &U256::from(100), CleanupMode::NoEmpty)
+        .unwrap();
+    state
+        .commit(EpochId::from_uint(&U256::from(1)), /* debug_record = */ None)
+        .unwrap();
+    let (updated, deleted) = state.txpool_notification();
+    assert_eq!(
+        updated.iter().map(|acc| *acc.address()).collect::<Vec<_>>(),
+        vec![a]
+    );
+    assert!(deleted.is_empty());
+
+    let mut state =
+        get_state(&storage_manager, &EpochId::from_uint(&U256::from(1)));
+    state
+        .add_balance(&b, &U256::from(200), CleanupMode::NoEmpty)
+        .unwrap();
+    // Accounts are only notified once they are committed.
+    state.compute_state_root(/* debug_record = */ None).unwrap();
+    assert_eq!(state.txpool_notification(), (vec![], vec![]));
+    state
+        .commit(EpochId::from_uint(&U256::from(2)), /* debug_record = */ None)
+        .unwrap();
+    let (updated, deleted) = state.txpool_notification();
+    assert_eq!(updated.len(), 1);
+    assert_eq!(updated[0].address(), &b);
+    assert_eq!(updated[0].balance, U256::from(200));
+    assert!(deleted.is_empty());
+}
+
+#[test]
+fn storage_points_info_before_and_after_cip107() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut sponsor = Address::zero();
+    sponsor.set_user_account_type_bits();
+    let mut contract = Address::from_low_u64_be(1);
+    contract.set_contract_type_bits();
+
+    // Half of the collateral is converted to storage points.
+    state
+        .set_system_storage(
+            storage_point_prop().to_vec(),
+            U256::from(ONE_CFX_IN_DRIP),
+        )
+        .unwrap();
+    state.add_total_issued(U256::from(10000));
+    state
+        .new_contract_with_admin(
+            &contract.with_native_space(),
+            &sponsor,
+            U256::zero(),
+            Some(STORAGE_LAYOUT_REGULAR_V0),
+            /* cip107 = */ false,
+        )
+        .unwrap();
+    state
+        .set_sponsor_for_collateral(
+            &contract,
+            &sponsor,
+            &U256::from(100),
+            /* is_cip107 = */ false,
+        )
+        .unwrap();
+    state
+        .add_collateral_for_storage(&contract, &U256::from(300))
+        .unwrap();
+
+    assert_eq!(
+        state.storage_points_info(&contract).unwrap(),
+        StoragePointsInfo {
+            used: U256::zero(),
+            unused: U256::zero(),
+            token_collateral: U256::from(300),
+            cip107_initialized: false,
+        }
+    );
+
+    // 200 points are converted, 100 of them from the collateral.
+    state.initialize_cip107(&contract).unwrap();
+    let info = state.storage_points_info(&contract).unwrap();
+    assert_eq!(
+        info,
+        StoragePointsInfo {
+            used: U256::from(100),
+            unused: U256::from(100),
+            token_collateral: U256::from(200),
+            cip107_initialized: true,
+        }
+    );
+    assert_eq!(
+        info.unused,
+        state.available_storage_point_for_collateral(&contract).unwrap()
+    );
+    assert_eq!(
+        info.used + info.token_collateral,
+        state.collateral_for_storage(&contract).unwrap()
+    );
+
+    let mut missing = Address::from_low_u64_be(2);
+    missing.set_contract_type_bits();
+    assert_eq!(
+        state.storage_points_info(&missing).unwrap(),
+        StoragePointsInfo::default()
+    );
+}
+
+#[test]
+fn is_cip107_initialized_after_collateral_change() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut spec = Spec::new_spec_for_test();
+    spec.cip107 = true;
+    let mut sponsor = Address::zero();
+    sponsor.set_user_account_type_bits();
+    let mut contract = Address::from_low_u64_be(1);
+    contract.set_contract_type_bits();
+
+    state
+        .set_system_storage(
+            storage_point_prop().to_vec(),
+            U256::from(ONE_CFX_IN_DRIP),
+        )
+        .unwrap();
+    state
+        .new_contract_with_admin(
+            &contract.with_native_space(),
+            &sponsor,
+            U256::zero(),
+            Some(STORAGE_LAYOUT_REGULAR_V0),
+            /* cip107 = */ false,
+        )
+        .unwrap();
+    state
+        .set_sponsor_for_collateral(
+            &contract,
+            &sponsor,
+            &(*DRIPS_PER_STORAGE_COLLATERAL_UNIT * U256::from(10)),
+            /* is_cip107 = */ false,
+        )
+        .unwrap();
+    // Covers the collateral in case it is not charged to the sponsor.
+    state
+        .add_balance(
+            &contract.with_native_space(),
+            &(*DRIPS_PER_STORAGE_COLLATERAL_UNIT * U256::from(10)),
+            CleanupMode::NoEmpty,
+        )
+        .unwrap();
+    assert!(!state.is_cip107_initialized(&contract).unwrap());
+    assert!(!state
+        .is_cip107_initialized(&Address::from_low_u64_be(2))
+        .unwrap());
+
+    let mut substate = Substate::new();
+    substate.record_storage_occupy(&contract, 1);
+    assert_eq!(
+        state
+            .settle_collateral_for_all(&mut substate, &mut (), &spec, false)
+            .unwrap(),
+        CollateralCheckResult::Valid
+    );
+    assert!(state.is_cip107_initialized(&contract).unwrap());
+}
+
+#[test]
+fn dao_voted_params_detailed_match_stored_values() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+
+    // The storage point proportion is not written before it is set.
+    let params = state
+        .initialize_or_update_dao_voted_params_detailed(
+            /* set_pos_staking = */ false,
+        )
+        .unwrap();
+    assert_eq!(
+        params.interest_rate_per_block,
+        state.world_statistics.interest_rate_per_block
+    );
+    assert_eq!(
+        Some(params.pow_base_reward),
+        state.pow_base_reward_opt().unwrap()
+    );
+    assert_eq!(params.storage_point_prop, None);
+    assert!(state
+        .get_system_storage_opt(&storage_point_prop())
+        .unwrap()
+        .is_none());
+
+    state
+        .set_system_storage(
+            storage_point_prop().to_vec(),
+            U256::from(ONE_CFX_IN_DRIP),
+        )
+        .unwrap();
+    let params = state
+        .initialize_or_update_dao_voted_params_detailed(
+            /* set_pos_staking = */ true,
+        )
+        .unwrap();
+    assert_eq!(
+        params.interest_rate_per_block,
+        state.world_statistics.interest_rate_per_block
+    );
+    assert_eq!(
+        Some(params.pow_base_reward),
+        state.pow_base_reward_opt().unwrap()
+    );
+    assert_eq!(
+        params.storage_point_prop,
+        state.get_system_storage_opt(&storage_point_prop()).unwrap()
+    );
+}
+
+#[test]
+fn estimate_collateral_change_matches_settlement() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut spec = Spec::new_spec_for_test();
+    spec.cip107 = true;
+    let unit = *DRIPS_PER_STORAGE_COLLATERAL_UNIT;
+    let mut user = Address::from_low_u64_be(1);
+    user.set_user_account_type_bits();
+    let mut releasing_user = Address::from_low_u64_be(2);
+    releasing_user.set_user_account_type_bits();
+    let mut sponsor = Address::from_low_u64_be(3);
+    sponsor.set_user_account_type_bits();
+    let mut contract = Address::from_low_u64_be(4);
+    contract.set_contract_type_bits();
+
+    // Half of the sponsored collateral is converted to storage points.
+    state
+        .set_system_storage(
+            storage_point_prop().to_vec(),
+            U256::from(ONE_CFX_IN_DRIP),
+        )
+        .unwrap();
+    state.add_total_issued(unit * U256::from(100));
+    for address in &[user, releasing_user] {
+        state
+            .add_balance(
+                &address.with_native_space(),
+                &(unit * U256::from(10)),
+                CleanupMode::NoEmpty,
+            )
+            .unwrap();
+    }
+    state
+        .add_collateral_for_storage(&releasing_user, &(unit * U256::from(4)))
+        .unwrap();
+    state
+        .new_contract_with_code(&contract.with_native_space(), U256::zero())
+        .unwrap();
+    state
+        .set_sponsor_for_collateral(
+            &contract,
+            &sponsor,
+            &(unit * U256::from(2)),
+            /* is_cip107 = */ false,
+        )
+        .unwrap();
+
+    let new_substate = || {
+        let mut substate = Substate::new();
+        substate.record_storage_occupy(&user, 3);
+        substate.record_storage_release(&releasing_user, 2);
+        substate.record_storage_occupy(&contract, 2);
+        substate
+    };
+    let dirty_before = state.dirty_account_count();
+    let supply_before = state.supply_breakdown();
+    let estimation = state
+        .estimate_collateral_change(&new_substate(), &spec)
+        .unwrap();
+    // The estimation does not change the state.
+    assert_eq!(state.dirty_account_count(), dirty_before);
+    assert_eq!(state.supply_breakdown(), supply_before);
+    assert!(!state.is_cip107_initialized(&contract).unwrap());
+
+    assert!(estimation.is_covered());
+    let owners = &estimation.owners;
+    assert_eq!(
+        owners.iter().map(|owner| owner.address).collect::<Vec<_>>(),
+        vec![user, releasing_user, contract]
+    );
+    assert_eq!(owners[0].charge, unit * U256::from(3));
+    assert_eq!(owners[0].available, unit * U256::from(10));
+    assert_eq!(owners[1].release, unit * U256::from(2));
+    assert!(!owners[1].initializes_cip107);
+    // One unit of the sponsor balance is converted to storage points.
+    assert!(owners[2].initializes_cip107);
+    assert_eq!(owners[2].charge, unit * U256::from(2));
+    assert_eq!(owners[2].available, unit * U256::from(2));
+    assert_eq!(owners[2].charged_storage_points, unit);
+    assert_eq!(owners[2].converted_from_balance, unit);
+    assert_eq!(owners[2].converted_from_collateral, U256::zero());
+
+    let mut substate = new_substate();
+    assert_eq!(
+        state
+            .settle_collateral_for_all(&mut substate, &mut (), &spec, false)
+            .unwrap(),
+        CollateralCheckResult::Valid
+    );
+    for owner in owners {
+        assert_eq!(
+            state.collateral_for_storage(&owner.address).unwrap(),
+            owner.collateral_for_storage
+        );
+    }
+    assert_eq!(state.collateral_for_storage(&user).unwrap(), owners[0].charge);
+    assert_eq!(
+        state.balance(&user.with_native_space()).unwrap(),
+        owners[0].available - owners[0].charge
+    );
+    assert_eq!(
+        state.collateral_for_storage(&releasing_user).unwrap(),
+        unit * U256::from(4) - owners[1].release
+    );
+    assert!(state.is_cip107_initialized(&contract).unwrap());
+    let info = state.storage_points_info(&contract).unwrap();
+    assert_eq!(info.used, owners[2].charged_storage_points);
+    assert_eq!(
+        info.token_collateral,
+        owners[2].charge - owners[2].charged_storage_points
+    );
+    assert_eq!(
+        state.sponsor_balance_for_collateral(&contract).unwrap() + info.unused,
+        owners[2].available - owners[2].charge
+    );
+}
+
+#[test]
+fn estimate_collateral_change_reports_uncovered_charge() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let state = get_state_for_genesis_write(&storage_manager);
+    let spec = Spec::new_spec_for_test();
+    let mut poor = Address::from_low_u64_be(1);
+    poor.set_user_account_type_bits();
+
+    let mut substate = Substate::new();
+    substate.record_storage_occupy(&poor, 1);
+    let estimation =
+        state.estimate_collateral_change(&substate, &spec).unwrap();
+    assert!(!estimation.is_covered());
+    assert_eq!(estimation.owners[0].available, U256::zero());
+    assert_eq!(
+        estimation.owners[0].charge,
+        *DRIPS_PER_STORAGE_COLLATERAL_UNIT
+    );
+}
+
+#[test]
+fn collateral_shortfall_reports_sender_balance() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let spec = Spec::new_spec_for_test();
+    let unit = *DRIPS_PER_STORAGE_COLLATERAL_UNIT;
+    let mut user = Address::from_low_u64_be(1);
+    user.set_user_account_type_bits();
+    state
+        .add_balance(&user.with_native_space(), &unit, CleanupMode::NoEmpty)
+        .unwrap();
+
+    let mut substate = Substate::new();
+    substate.record_storage_occupy(&user, 2);
+    let result = state
+        .settle_collateral_for_all(&mut substate, &mut (), &spec, false)
+        .unwrap();
+    assert_eq!(
+        result,
+        CollateralCheckResult::NotEnoughBalance {
+            required: unit * U256::from(2),
+            got: unit,
+            address: user,
+            source: CollateralSource::Balance,
+        }
+    );
+
+    let error = crate::vm::Error::NotEnoughBalanceForStorage {
+        required: unit * U256::from(2),
+        got: unit,
+        address: user,
+        source: CollateralSource::Balance,
+    };
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "Not enough balance for storage {}/{}: balance of {:?}",
+            unit * U256::from(2),
+            unit,
+            user
+        )
+    );
+}
+
+#[test]
+fn collateral_shortfall_reports_sponsor_balance() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let spec = Spec::new_spec_for_test();
+    let unit = *DRIPS_PER_STORAGE_COLLATERAL_UNIT;
+    let mut sponsor = Address::from_low_u64_be(1);
+    sponsor.set_user_account_type_bits();
+    let mut contract = Address::from_low_u64_be(2);
+    contract.set_contract_type_bits();
+    state
+        .new_contract_with_code(&contract.with_native_space(), U256::zero())
+        .unwrap();
+    state
+        .set_sponsor_for_collateral(
+            &contract,
+            &sponsor,
+            &unit,
+            /* is_cip107 = */ false,
+        )
+        .unwrap();
+
+    let mut substate = Substate::new();
+    substate.record_storage_occupy(&contract, 2);
+    assert_eq!(
+        state
+            .settle_collateral_for_all(&mut substate, &mut (), &spec, false)
+            .unwrap(),
+        CollateralCheckResult::NotEnoughBalance {
+            required: unit * U256::from(2),
+            got: unit,
+            address: contract,
+            source: CollateralSource::Sponsor {
+                sponsor_balance: unit,
+                storage_points: U256::zero(),
+            },
+        }
+    );
+}
+
+#[test]
+fn collateral_shortfall_reports_storage_points() {
+    let storage_manager = new_state_manager_for_unit_test();
+    let mut state = get_state_for_genesis_write(&storage_manager);
+    let mut spec = Spec::new_spec_for_test();
+    spec.cip107 = true;
+    let unit = *DRIPS_PER_STORAGE_COLLATERAL_UNIT;
+    let mut sponsor = Address::from_low_u64_be(1);
+    sponsor.set_user_account_type_bits();
+    let mut contract = Address::from_low_u64_be(2);
+    contract.set_contract_type_bits();
+
+    // Half of the sponsor balance is converted to storage points.
+    state
+        .set_system_storage(
+            storage_point_prop().to_vec(),
+            U256::from(ONE_CFX_IN_DRIP),
+        )
+        .unwrap();
+    state.add_total_issued(unit * U256::from(100));
+    state
+        .new_contract_with_code(&contract.with_native_space(), U256::zero())
+        .unwrap();
+    state
+        .set_sponsor_for_collateral(
+            &contract,
+            &sponsor,
+            &(unit * U256::from(2)),
+            /* is_cip107 = */ false,
+        )
+        .unwrap();
+
+    let mut substate = Substate::new();
+    substate.record_storage_occupy(&contract, 4);
+    let result = state
+        .settle_collateral_for_all(&mut substate, &mut (), &spec, false)
+        .unwrap();
+    assert_eq!(
+        result,
+        CollateralCheckResult::NotEnoughBalance {
+            required: unit * U256::from(4),
+            got: unit * U256::from(2),
+            address: contract,
+            source: CollateralSource::Sponsor {
+                sponsor_balance: unit,
+                storage_points: unit,
+            },
+        }
+    );
+}
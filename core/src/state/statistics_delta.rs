@@ -0,0 +1,109 @@
+// Copyright 2019 Conflux Foundation. All rights reserved.
+// Conflux is free software and distributed under GNU General Public License.
+// See http://www.gnu.org/licenses/
+
+lazy_static! {
+    static ref TOTAL_ISSUED_TOKENS_GAUGE: Arc<dyn Gauge<usize>> =
+        GaugeUsize::register_with_group("state", "total_issued_tokens");
+    static ref TOTAL_STAKING_TOKENS_GAUGE: Arc<dyn Gauge<usize>> =
+        GaugeUsize::register_with_group("state", "total_staking_tokens");
+    static ref TOTAL_STORAGE_TOKENS_GAUGE: Arc<dyn Gauge<usize>> =
+        GaugeUsize::register_with_group("state", "total_storage_tokens");
+    static ref TOTAL_POS_STAKING_TOKENS_GAUGE: Arc<dyn Gauge<usize>> =
+        GaugeUsize::register_with_group("state", "total_pos_staking_tokens");
+    static ref DISTRIBUTABLE_POS_INTEREST_GAUGE: Arc<dyn Gauge<usize>> =
+        GaugeUsize::register_with_group("state", "distributable_pos_interest");
+    static ref TOTAL_EVM_TOKENS_GAUGE: Arc<dyn Gauge<usize>> =
+        GaugeUsize::register_with_group("state", "total_evm_tokens");
+    static ref USED_STORAGE_POINTS_GAUGE: Arc<dyn Gauge<usize>> =
+        GaugeUsize::register_with_group("state", "used_storage_points");
+    static ref CONVERTED_STORAGE_POINTS_GAUGE: Arc<dyn Gauge<usize>> =
+        GaugeUsize::register_with_group("state", "converted_storage_points");
+}
+
+/// The value of a world statistic before and after an epoch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatisticChange<T> {
+    pub old: T,
+    pub new: T,
+}
+
+impl<T: PartialEq> StatisticChange<T> {
+    pub fn is_changed(&self) -> bool { self.old != self.new }
+}
+
+/// The changes of the world statistics made by an epoch, returned by
+/// `State::last_statistics_delta`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorldStatisticsDelta {
+    pub total_issued_tokens: StatisticChange<U256>,
+    pub total_staking_tokens: StatisticChange<U256>,
+    pub total_storage_tokens: StatisticChange<U256>,
+    pub interest_rate_per_block: StatisticChange<U256>,
+    pub accumulate_interest_rate: StatisticChange<U256>,
+    pub total_pos_staking_tokens: StatisticChange<U256>,
+    pub distributable_pos_interest: StatisticChange<U256>,
+    pub last_distribute_block: StatisticChange<u64>,
+    pub total_evm_tokens: StatisticChange<U256>,
+    pub used_storage_points: StatisticChange<U256>,
+    pub converted_storage_points: StatisticChange<U256>,
+}
+
+impl WorldStatisticsDelta {
+    pub(super) fn new(old: &WorldStatistics, new: &WorldStatistics) -> Self {
+        macro_rules! change {
+            ($field:ident) => {
+                StatisticChange {
+                    old: old.$field,
+                    new: new.$field,
+                }
+            };
+        }
+        WorldStatisticsDelta {
+            total_issued_tokens: change!(total_issued_tokens),
+            total_staking_tokens: change!(total_staking_tokens),
+            total_storage_tokens: change!(total_storage_tokens),
+            interest_rate_per_block: change!(interest_rate_per_block),
+            accumulate_interest_rate: change!(accumulate_interest_rate),
+            total_pos_staking_tokens: change!(total_pos_staking_tokens),
+            distributable_pos_interest: change!(distributable_pos_interest),
+            last_distribute_block: change!(last_distribute_block),
+            total_evm_tokens: change!(total_evm_tokens),
+            used_storage_points: change!(used_storage_points),
+            converted_storage_points: change!(converted_storage_points),
+        }
+    }
+
+    /// Updates the gauges of the token statistics to their new values, in
+    /// CFX.
+    pub fn report_metrics(&self) {
+        let in_cfx = |change: &StatisticChange<U256>| {
+            let cfx = change.new / U256::from(ONE_CFX_IN_DRIP);
+            if cfx > U256::from(usize::MAX) {
+                usize::MAX
+            } else {
+                cfx.as_usize()
+            }
+        };
+        TOTAL_ISSUED_TOKENS_GAUGE.update(in_cfx(&self.total_issued_tokens));
+        TOTAL_STAKING_TOKENS_GAUGE.update(in_cfx(&self.total_staking_tokens));
+        TOTAL_STORAGE_TOKENS_GAUGE.update(in_cfx(&self.total_storage_tokens));
+        TOTAL_POS_STAKING_TOKENS_GAUGE
+            .update(in_cfx(&self.total_pos_staking_tokens));
+        DISTRIBUTABLE_POS_INTEREST_GAUGE
+            .update(in_cfx(&self.distributable_pos_interest));
+        TOTAL_EVM_TOKENS_GAUGE.update(in_cfx(&self.total_evm_tokens));
+        USED_STORAGE_POINTS_GAUGE.update(in_cfx(&self.used_storage_points));
+        CONVERTED_STORAGE_POINTS_GAUGE
+            .update(in_cfx(&self.converted_storage_points));
+    }
+}
+
+use super::WorldStatistics;
+use cfx_parameters::consensus::ONE_CFX_IN_DRIP;
+use cfx_types::U256;
+use metrics::{Gauge, GaugeUsize};
+use serde::Serialize;
+use std::sync::Arc;
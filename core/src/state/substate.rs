@@ -123,6 +123,11 @@ pub struct Substate {
     pub logs: Vec<LogEntry>,
     /// Created contracts.
     pub contracts_created: Vec<AddressWithSpace>,
+    /// Collateral destroyed during settlement because the refund exceeds the
+    /// collateral held by the owner.
+    pub collateral_burnt: U256,
+    /// Collateral returned to the owners' balances during settlement.
+    pub collateral_refunded: U256,
 }
 
 impl Substate {
@@ -137,6 +142,8 @@ impl Substate {
         for (address, amount) in s.storage_released {
             *self.storage_released.entry(address).or_insert(0) += amount;
         }
+        self.collateral_burnt += s.collateral_burnt;
+        self.collateral_refunded += s.collateral_refunded;
     }
 
     pub fn new() -> Self { Substate::default() }
@@ -188,6 +195,18 @@ impl Substate {
         *self.storage_released.entry(*address).or_insert(0) += collaterals;
     }
 
+    pub fn record_collateral_settlement(
+        &mut self, refunded: &U256, burnt: &U256,
+    ) {
+        self.collateral_refunded += *refunded;
+        self.collateral_burnt += *burnt;
+    }
+
+    /// Returns the total collateral `(burnt, refunded)` during settlement.
+    pub fn collateral_burnt_and_refunded(&self) -> (U256, U256) {
+        (self.collateral_burnt, self.collateral_refunded)
+    }
+
     pub fn keys_for_collateral_changed(&self) -> HashSet<&Address> {
         let affected_address1: HashSet<_> =
             self.storage_collateralized.keys().collect();
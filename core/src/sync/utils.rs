@@ -196,6 +196,7 @@ pub fn initialize_synchronization_graph_with_data_manager(
             pos_initial_nodes_path: "".to_string(),
             vrf_proposal_threshold: Default::default(),
             pos_state_config: Default::default(),
+            chain_id: Default::default(),
         },
         u64::MAX,
     ));
@@ -269,6 +270,7 @@ pub fn initialize_synchronization_graph_with_data_manager(
         notifications.clone(),
         ConsensusExecutionConfiguration {
             executive_trace: false,
+            state_cache_byte_budget: None,
         },
         verification_config.clone(),
         NodeType::Archive,
@@ -23,6 +23,13 @@ impl AccountCache {
         Ok((self.state.nonce(address)?, self.state.balance(address)?))
     }
 
+    pub fn get_balances(
+        &self, addresses: &[AddressWithSpace],
+    ) -> DbResult<Vec<U256>> {
+        let _timer = MeterTimer::time_func(TX_POOL_GET_STATE_TIMER.as_ref());
+        self.state.balances(addresses)
+    }
+
     pub fn get_nonce(&self, address: &AddressWithSpace) -> DbResult<U256> {
         self.state.nonce(address)
     }
@@ -231,6 +231,23 @@ impl TransactionPool {
         let (state_nonce, _) = self
             .get_state_account_info(address)
             .unwrap_or((0.into(), 0.into()));
+        self.get_next_nonce_from(address, state_nonce)
+    }
+
+    /// The nonce of `address` in `state`, advanced past the consecutive
+    /// transactions of `address` pending in the pool.
+    pub fn next_nonce_with_pending(
+        &self, address: &Address, state: &State,
+    ) -> StateDbResult<U256> {
+        let state_nonce = state.nonce(address)?;
+        Ok(self.get_next_nonce_from(address, state_nonce))
+    }
+
+    /// Returns the next nonce of `address` given its nonce in some state,
+    /// skipping the consecutive nonces already queued in the pool.
+    fn get_next_nonce_from(
+        &self, address: &Address, state_nonce: U256,
+    ) -> U256 {
         self.inner.read().get_next_nonce(address, state_nonce)
     }
 
@@ -741,9 +758,14 @@ impl TransactionPool {
 
     pub fn notify_modified_accounts(
         &self, accounts_from_execution: Vec<Account>,
-    ) {
+        deleted_from_execution: Vec<Address>,
+    )
+    {
         let mut inner = self.inner.write_with_metric(&NOTIFY_MODIFIED_LOCK);
-        inner.notify_modified_accounts(accounts_from_execution)
+        inner.notify_modified_accounts(
+            accounts_from_execution,
+            deleted_from_execution,
+        )
     }
 
     pub fn clear_tx_pool(&self) {
@@ -802,6 +824,16 @@ impl TransactionPool {
         *self.config.max_tx_gas.write() = self.calc_max_tx_gas();
 
         let account_cache = self.get_best_state_account_cache();
+        // Load the senders to recheck in one batch, so that the readiness
+        // checks below are served from the state cache.
+        let senders: Vec<Address> = set_tx_buffer
+            .iter()
+            .chain(recycle_tx_buffer.iter())
+            .map(|tx| tx.sender())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        account_cache.get_balances(&senders)?;
         let mut inner = self.inner.write_with_metric(&NOTIFY_BEST_INFO_LOCK);
         let inner = inner.deref_mut();
 
@@ -26,7 +26,7 @@ use rlp::*;
 use serde::Serialize;
 use std::{
     cmp::{Ordering, Reverse},
-    collections::{BTreeSet, HashMap},
+    collections::{BTreeSet, HashMap, HashSet},
     sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
@@ -672,6 +672,9 @@ pub struct TransactionPoolInner {
     /// It should contain the same transaction set as `deferred_pool`.
     txs: HashMap<H256, Arc<SignedTransaction>>,
     tx_sponsored_gas_map: HashMap<H256, (U256, u64)>,
+    /// The transactions in `tx_sponsored_gas_map` with sponsored gas, indexed
+    /// by the contract they call.
+    gas_sponsored_txs_by_contract: HashMap<Address, HashSet<H256>>,
 }
 
 impl TransactionPoolInner {
@@ -694,6 +697,7 @@ impl TransactionPoolInner {
             garbage_collector: GarbageCollector::default(),
             txs: HashMap::new(),
             tx_sponsored_gas_map: HashMap::new(),
+            gas_sponsored_txs_by_contract: HashMap::new(),
         }
     }
 
@@ -704,6 +708,7 @@ impl TransactionPoolInner {
         self.garbage_collector.clear();
         self.txs.clear();
         self.tx_sponsored_gas_map.clear();
+        self.gas_sponsored_txs_by_contract.clear();
         self.total_received_count = 0;
         self.unpacked_transaction_count = 0;
     }
@@ -874,7 +879,7 @@ impl TransactionPoolInner {
 
             // maintain txs
             self.txs.remove(&to_remove_tx.hash());
-            self.tx_sponsored_gas_map.remove(&to_remove_tx.hash());
+            self.remove_sponsored_gas(&to_remove_tx);
         }
 
         // Insert back skipped nodes to keep `garbage_collector`
@@ -947,8 +952,8 @@ impl TransactionPoolInner {
                 );
                 // GarbageCollector will be updated by the caller.
                 self.txs.insert(transaction.hash(), transaction.clone());
-                self.tx_sponsored_gas_map.insert(
-                    transaction.hash(),
+                self.insert_sponsored_gas(
+                    &transaction,
                     (sponsored_gas, sponsored_storage),
                 );
                 if !packed {
@@ -968,9 +973,9 @@ impl TransactionPoolInner {
                 }
                 self.txs.remove(&replaced_tx.hash());
                 self.txs.insert(transaction.hash(), transaction.clone());
-                self.tx_sponsored_gas_map.remove(&replaced_tx.hash());
-                self.tx_sponsored_gas_map.insert(
-                    transaction.hash(),
+                self.remove_sponsored_gas(&replaced_tx);
+                self.insert_sponsored_gas(
+                    &transaction,
                     (sponsored_gas, sponsored_storage),
                 );
                 if !packed {
@@ -982,6 +987,47 @@ impl TransactionPoolInner {
         result
     }
 
+    /// The contract whose gas sponsor may pay for `tx`.
+    fn gas_sponsoring_contract(tx: &SignedTransaction) -> Option<Address> {
+        match &tx.unsigned {
+            Transaction::Native(utx) => match &utx.action {
+                Action::Call(callee) if callee.is_contract_address() => {
+                    Some(*callee)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn insert_sponsored_gas(
+        &mut self, tx: &SignedTransaction, sponsored: (U256, u64),
+    ) {
+        if !sponsored.0.is_zero() {
+            if let Some(contract) = Self::gas_sponsoring_contract(tx) {
+                self.gas_sponsored_txs_by_contract
+                    .entry(contract)
+                    .or_default()
+                    .insert(tx.hash());
+            }
+        }
+        self.tx_sponsored_gas_map.insert(tx.hash(), sponsored);
+    }
+
+    fn remove_sponsored_gas(&mut self, tx: &SignedTransaction) {
+        self.tx_sponsored_gas_map.remove(&tx.hash());
+        if let Some(contract) = Self::gas_sponsoring_contract(tx) {
+            if let Some(hashes) =
+                self.gas_sponsored_txs_by_contract.get_mut(&contract)
+            {
+                hashes.remove(&tx.hash());
+                if hashes.is_empty() {
+                    self.gas_sponsored_txs_by_contract.remove(&contract);
+                }
+            }
+        }
+    }
+
     pub fn get_account_pending_info(
         &self, address: &AddressWithSpace,
     ) -> Option<(U256, U256, U256, H256)> {
@@ -1310,7 +1356,10 @@ impl TransactionPoolInner {
 
     pub fn notify_modified_accounts(
         &mut self, accounts_from_execution: Vec<Account>,
-    ) {
+        deleted_from_execution: Vec<AddressWithSpace>,
+    )
+    {
+        self.revoke_outdated_gas_sponsorship(&accounts_from_execution);
         for account in &accounts_from_execution {
             self.recalculate_readiness_with_fixed_info(
                 account.address(),
@@ -1318,6 +1367,67 @@ impl TransactionPoolInner {
                 account.balance,
             );
         }
+        // A deleted account reads as an empty account from the state.
+        for address in &deleted_from_execution {
+            self.recalculate_readiness_with_fixed_info(
+                address,
+                U256::zero(),
+                U256::zero(),
+            );
+        }
+    }
+
+    /// Stops sponsoring the gas of the unpacked transactions which call one
+    /// of the modified contracts if its gas sponsor can no longer cover
+    /// them, so that their senders have to pay for the gas.
+    fn revoke_outdated_gas_sponsorship(&mut self, accounts: &[Account]) {
+        let mut outdated_txs = Vec::new();
+        for account in accounts {
+            let address = account.address();
+            if address.space != Space::Native {
+                continue;
+            }
+            let hashes = match self
+                .gas_sponsored_txs_by_contract
+                .get(&address.address)
+            {
+                Some(hashes) => hashes,
+                None => continue,
+            };
+            let sponsor_info = &account.sponsor_info;
+            for hash in hashes {
+                let (tx, sponsored_storage) = match (
+                    self.txs.get(hash),
+                    self.tx_sponsored_gas_map.get(hash),
+                ) {
+                    (Some(tx), Some((_, sponsored_storage))) => {
+                        (tx, *sponsored_storage)
+                    }
+                    _ => continue,
+                };
+                let estimated_gas =
+                    Self::estimated_gas_fee(*tx.gas(), *tx.gas_price());
+                if estimated_gas > sponsor_info.sponsor_gas_bound
+                    || estimated_gas > sponsor_info.sponsor_balance_for_gas
+                {
+                    outdated_txs.push((tx.clone(), sponsored_storage));
+                }
+            }
+        }
+
+        for (tx, sponsored_storage) in outdated_txs {
+            if self.deferred_pool.check_tx_packed(tx.sender(), *tx.nonce()) {
+                continue;
+            }
+            self.insert_transaction_without_readiness_check(
+                tx.clone(),
+                false, /* packed */
+                true,  /* force */
+                None,  /* state_nonce_and_balance */
+                (U256::zero(), sponsored_storage),
+            );
+            self.recalculate_readiness_with_local_info(&tx.sender());
+        }
     }
 
     /// content retrieves the ready and deferred transactions.
@@ -1538,12 +1648,14 @@ impl TransactionPoolInner {
 
 #[cfg(test)]
 mod test_transaction_pool_inner {
-    use super::{DeferredPool, InsertResult, TxWithReadyInfo};
+    use super::{
+        DeferredPool, InsertResult, TransactionPoolInner, TxWithReadyInfo,
+    };
     use crate::transaction_pool::transaction_pool_inner::ReadyAccountPool;
-    use cfx_types::{Address, AddressSpaceUtil, U256};
+    use cfx_types::{address_util::AddressUtil, Address, AddressSpaceUtil, U256};
     use keylib::{Generator, KeyPair, Random};
     use primitives::{
-        Action, NativeTransaction, SignedTransaction, Transaction,
+        Account, Action, NativeTransaction, SignedTransaction, Transaction,
     };
     use std::sync::Arc;
 
@@ -1872,4 +1984,96 @@ mod test_transaction_pool_inner {
         assert_eq!(ready_pool.native_pool.packing_pool.len(), 0);
         assert_eq!(ready_pool.native_pool.waiting_pool.len(), 0);
     }
+
+    #[test]
+    fn test_next_nonce_with_queued_transactions() {
+        let mut pool =
+            TransactionPoolInner::new(100, 1, 1, U256::from(10_000_000));
+        let alice = Random.generate().unwrap();
+        let alice_addr_s = alice.address().with_native_space();
+        let state_nonce = U256::from(5);
+
+        assert_eq!(pool.get_next_nonce(&alice_addr_s, state_nonce), 5.into());
+
+        // Nonces 5 and 6 are consecutive, 8 is queued behind a gap.
+        for nonce in &[5, 6, 8] {
+            assert_eq!(
+                pool.insert_transaction_without_readiness_check(
+                    new_test_tx(&alice, *nonce, 10, 100),
+                    false, /* packed */
+                    false, /* force */
+                    Some((state_nonce, U256::from(1_000_000_000))),
+                    (U256::zero(), 0),
+                ),
+                InsertResult::NewAdded
+            );
+        }
+        assert_eq!(pool.get_next_nonce(&alice_addr_s, state_nonce), 7.into());
+        // The gap does not close until the state nonce passes it.
+        assert_eq!(
+            pool.get_next_nonce(&alice_addr_s, U256::from(7)),
+            7.into()
+        );
+        assert_eq!(
+            pool.get_next_nonce(&alice_addr_s, U256::from(8)),
+            9.into()
+        );
+    }
+
+    #[test]
+    fn test_drained_sponsor_revokes_gas_sponsorship() {
+        let mut pool =
+            TransactionPoolInner::new(100, 1, 1, U256::from(10_000_000));
+        let alice = Random.generate().unwrap();
+        let alice_addr_s = alice.address().with_native_space();
+        let mut contract = Address::from_low_u64_be(1);
+        contract.set_contract_type_bits();
+        let gas = U256::from(50000);
+        let tx = Arc::new(
+            Transaction::from(NativeTransaction {
+                nonce: U256::zero(),
+                gas_price: U256::from(10),
+                gas,
+                action: Action::Call(contract),
+                value: U256::from(100),
+                storage_limit: 0,
+                epoch_height: 0,
+                chain_id: 1,
+                data: Vec::new(),
+            })
+            .sign(alice.secret()),
+        );
+
+        // Alice can only afford the value as her gas is sponsored.
+        assert_eq!(
+            pool.insert_transaction_without_readiness_check(
+                tx.clone(),
+                false, /* packed */
+                false, /* force */
+                Some((U256::zero(), U256::from(100))),
+                (gas, 0),
+            ),
+            InsertResult::NewAdded
+        );
+        pool.recalculate_readiness_with_local_info(&alice_addr_s);
+        assert!(pool.ready_account_pool.get(&alice_addr_s).is_some());
+        assert_eq!(pool.gas_sponsored_txs_by_contract[&contract].len(), 1);
+
+        // The sponsor is drained in the executed epoch.
+        let mut contract_account = Account::new_empty_with_balance(
+            &contract.with_native_space(),
+            &U256::zero(),
+            &U256::one(),
+        );
+        contract_account.sponsor_info.sponsor_gas_bound = U256::from(1000000);
+        contract_account.sponsor_info.sponsor_balance_for_gas = U256::from(10);
+        pool.notify_modified_accounts(vec![contract_account], vec![]);
+
+        assert_eq!(
+            pool.tx_sponsored_gas_map.get(&tx.hash()),
+            Some(&(U256::zero(), 0))
+        );
+        assert!(pool.gas_sponsored_txs_by_contract.is_empty());
+        assert!(pool.ready_account_pool.get(&alice_addr_s).is_none());
+    }
 }
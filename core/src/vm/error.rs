@@ -22,6 +22,7 @@
 
 use super::{action_params::ActionParams, ResumeCall, ResumeCreate};
 use bls_signatures::Error as CryptoError;
+use cfx_state::CollateralSource;
 use cfx_statedb::{Error as DbError, Result as DbResult};
 use cfx_types::{Address, U256};
 use solidity_abi::ABIDecodeError;
@@ -99,6 +100,9 @@ pub enum Error {
     NotEnoughBalanceForStorage {
         required: U256,
         got: U256,
+        /// The account charged for the collateral.
+        address: Address,
+        source: CollateralSource,
     },
     /// `ExceedStorageLimit` is returned when the `collateral_for_storage`
     /// exceed the `storage_limit`.
@@ -184,8 +188,31 @@ impl fmt::Display for Error {
             OutOfSubStack { wanted, limit } => {
                 write!(f, "Out of subroutine stack {}/{}", wanted, limit)
             }
-            NotEnoughBalanceForStorage { required, got } => {
-                write!(f, "Not enough balance for storage {}/{}", required, got,)
+            NotEnoughBalanceForStorage {
+                required,
+                got,
+                address,
+                source,
+            } => {
+                write!(
+                    f,
+                    "Not enough balance for storage {}/{}",
+                    required, got
+                )?;
+                match source {
+                    CollateralSource::Balance => {
+                        write!(f, ": balance of {:?}", address)
+                    }
+                    CollateralSource::Sponsor {
+                        sponsor_balance,
+                        storage_points,
+                    } => write!(
+                        f,
+                        ": sponsor balance {} and storage points {} of \
+                         contract {:?}",
+                        sponsor_balance, storage_points, address
+                    ),
+                }
             }
             ExceedStorageLimit => write!(f, "Exceed storage limit"),
             BuiltIn(name) => write!(f, "Built-in failed: {}", name),
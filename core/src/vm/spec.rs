@@ -21,7 +21,9 @@
 //! Cost spec and other parameterisations for the EVM.
 
 use crate::spec::CommonParams;
-use cfx_parameters::consensus_internal::DAO_PARAMETER_VOTE_PERIOD;
+use cfx_parameters::{
+    consensus_internal::DAO_PARAMETER_VOTE_PERIOD, staking::BLOCKS_PER_HOUR,
+};
 use cfx_types::{address_util::AddressUtil, Address};
 use primitives::BlockNumber;
 
@@ -159,6 +161,9 @@ pub struct Spec {
     /// CIP-107: Reduce storage collateral refund.
     pub cip107: bool,
     pub params_dao_vote_period: u64,
+    /// The number of blocks after the last PoS interest distribution during
+    /// which the PoS interest is accumulated.
+    pub distribute_window_blocks: u64,
 }
 
 /// Wasm cost table
@@ -297,6 +302,7 @@ impl Spec {
             cip105: false,
             cip_sigma_fix: false,
             cip107: false,
+            distribute_window_blocks: BLOCKS_PER_HOUR,
         }
     }
 
@@ -320,6 +326,7 @@ impl Spec {
         spec.cip105 = number >= params.transition_numbers.cip105;
         spec.cip_sigma_fix = number >= params.transition_numbers.cip_sigma_fix;
         spec.params_dao_vote_period = params.params_dao_vote_period;
+        spec.distribute_window_blocks = params.distribute_window_blocks;
         spec.cip107 = number >= params.transition_numbers.cip107;
         spec
     }
@@ -13,10 +13,30 @@ pub mod tracer;
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum CollateralCheckResult {
     ExceedStorageLimit { limit: U256, required: U256 },
-    NotEnoughBalance { required: U256, got: U256 },
+    NotEnoughBalance {
+        required: U256,
+        got: U256,
+        /// The account charged for the collateral.
+        address: Address,
+        /// The funds which were short.
+        source: CollateralSource,
+    },
     Valid,
 }
 
+/// The funds which pay the collateral for storage of an account.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum CollateralSource {
+    /// The balance of a normal account.
+    Balance,
+    /// The sponsor balance and the available storage points of a contract,
+    /// after CIP-107 is initialized.
+    Sponsor {
+        sponsor_balance: U256,
+        storage_points: U256,
+    },
+}
+
 /// Mode of dealing with null accounts.
 #[derive(PartialEq)]
 pub enum CleanupMode<'a> {
@@ -3,7 +3,7 @@
 // See http://www.gnu.org/licenses/
 
 use cfx_storage::Error as StorageError;
-use cfx_types::Address;
+use cfx_types::{Address, AddressWithSpace, H256, U256};
 use primitives::account::AccountError;
 use rlp::DecoderError;
 
@@ -27,5 +27,45 @@ error_chain! {
             description("PoS database error")
             display("PoS database error, err={:?}", err)
         }
+
+        CodeSizeExceeded(address: Address, size: usize, limit: usize) {
+            description("code size exceeded")
+            display("code size exceeded: address={:?}, size={}, limit={}", address, size, limit)
+        }
+
+        InvalidPosIndexStatus(identifier: H256, reason: String) {
+            description("invalid PoS index status")
+            display("invalid PoS index status: identifier={:?}, reason={}", identifier, reason)
+        }
+
+        NoOpenCheckpoint {
+            description("no open checkpoint")
+            display("no open checkpoint to discard or revert")
+        }
+
+        NotEnoughBalance(address: Address, required: U256, got: U256) {
+            description("not enough balance")
+            display("not enough balance: address={:?}, required={}, got={}", address, required, got)
+        }
+
+        DirtyStorage(address: Address) {
+            description("uncommitted storage changes")
+            display("storage of address={:?} has uncommitted changes", address)
+        }
+
+        EvmTokensMismatch(balances_before: U256, balances_after: U256, tokens_before: U256, tokens_after: U256) {
+            description("eSpace balances mismatch total_evm_tokens")
+            display("eSpace balances changed from {} to {}, but total_evm_tokens changed from {} to {}", balances_before, balances_after, tokens_before, tokens_after)
+        }
+
+        CrossSpaceTransfer(from: AddressWithSpace, to: AddressWithSpace) {
+            description("cross-space transfer")
+            display("cross-space transfer is not allowed: from={:?}, to={:?}", from, to)
+        }
+
+        WorldStatisticsUnderflow(statistic: &'static str, value: U256, amount: U256) {
+            description("world statistics underflow")
+            display("{} {} is less than the subtracted amount {}", statistic, value, amount)
+        }
     }
 }
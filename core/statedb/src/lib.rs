@@ -122,6 +122,40 @@ mod impls {
             Ok(r)
         }
 
+        /// Get the value under `key` from the storage together with its proof
+        /// against the state root of the storage. Returns `None` if `key`
+        /// has been modified since the last commit, because the proof
+        /// wouldn't cover the modified value.
+        pub fn get_raw_with_proof(
+            &self, key: StorageKeyWithSpace,
+        ) -> Result<Option<(Option<Arc<[u8]>>, StateProof)>> {
+            let modified = self
+                .accessed_entries
+                .read()
+                .get(&key.to_key_bytes())
+                .map_or(false, EntryValue::is_modified);
+            if modified {
+                return Ok(None);
+            }
+            let (value, proof) = self.storage.get_with_state_proof(key)?;
+            Ok(Some((value.map(Into::into), proof)))
+        }
+
+        /// The state root of the storage, which doesn't include the
+        /// modifications since the last commit.
+        pub fn get_state_root(&self) -> Result<StateRootWithAuxInfo> {
+            Ok(self.storage.get_state_root()?)
+        }
+
+        /// The merkle roots of the storage of `address` in all the tries of
+        /// the storage, which don't include the modifications since the last
+        /// commit.
+        pub fn get_storage_root(
+            &self, address: &AddressWithSpace,
+        ) -> Result<StorageRoot> {
+            Ok(self.storage.get_storage_root(address)?)
+        }
+
         /// Set the value under `key` to `value` in `accessed_entries`.
         /// This method will read from db if `key` is not present.
         /// This method will also update the latest checkpoint if necessary.
@@ -276,6 +310,53 @@ mod impls {
             Ok(deleted_kvs)
         }
 
+        /// Read at most `limit` key values prefixed by `key_prefix` in the
+        /// order of their keys, starting from `lower_bound_incl`. The
+        /// uncommitted changes are merged with the storage, and only as many
+        /// entries as needed are read from the storage.
+        pub fn read_range(
+            &self, key_prefix: StorageKeyWithSpace, lower_bound_incl: &[u8],
+            limit: usize,
+        ) -> Result<Vec<MptKeyValue>>
+        {
+            let key_bytes = key_prefix.to_key_bytes();
+            let lower_bound_incl =
+                std::cmp::max(key_bytes.as_slice(), lower_bound_incl);
+            let iter_range_upper_bound =
+                to_key_prefix_iter_upper_bound(&key_bytes);
+            let accessed_entries = self.accessed_entries.read();
+            let changes = match &iter_range_upper_bound {
+                None => accessed_entries
+                    .range::<[u8], _>((Included(lower_bound_incl), Unbounded)),
+                Some(upper_bound) => accessed_entries.range::<[u8], _>((
+                    Included(lower_bound_incl),
+                    Excluded(&**upper_bound),
+                )),
+            }
+            .filter(|(_, v)| v.is_modified())
+            .collect::<BTreeMap<_, _>>();
+
+            // Each change hides at most one key value of the storage.
+            let mut kvs = self
+                .storage
+                .read_range(
+                    key_prefix,
+                    lower_bound_incl,
+                    limit.saturating_add(changes.len()),
+                )?
+                .into_iter()
+                .filter(|(k, _)| !changes.contains_key(k))
+                .collect::<Vec<_>>();
+            for (k, v) in changes {
+                if let Some(value) = &v.current_value {
+                    kvs.push((k.clone(), (&**value).into()));
+                }
+            }
+            kvs.sort_by(|a, b| a.0.cmp(&b.0));
+            kvs.truncate(limit);
+            Ok(kvs)
+        }
+
         /// Load the storage layout for state commits.
         /// Modification to storage layout is the same as modification of
         /// any other key-values. But as required by MPT structure we
@@ -599,7 +680,7 @@ mod impls {
     };
     use cfx_storage::{
         utils::{access_mode, to_key_prefix_iter_upper_bound},
-        MptKeyValue, StorageStateTrait,
+        MptKeyValue, StateProof, StorageStateTrait,
     };
     use cfx_types::{
         address_util::AddressUtil, Address, AddressWithSpace, Space,
@@ -608,6 +689,7 @@ mod impls {
     use parking_lot::RwLock;
     use primitives::{
         EpochId, SkipInputCheck, StorageKey, StorageKeyWithSpace, StorageLayout,
+        StorageRoot,
     };
     use std::{
         collections::{btree_map::Entry::Occupied, BTreeMap},
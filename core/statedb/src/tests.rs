@@ -5,10 +5,14 @@
 use super::StateDbGeneric;
 use cfx_internal_common::StateRootWithAuxInfo;
 use cfx_storage::{
-    utils::access_mode, ErrorKind, MptKeyValue, Result, StorageStateTrait,
+    utils::access_mode, ErrorKind, MptKeyValue, Result, StateProof,
+    StorageStateTrait,
 };
+use cfx_types::AddressWithSpace;
 use parking_lot::Mutex;
-use primitives::{EpochId, StorageKey, StorageKeyWithSpace, MERKLE_NULL_NODE};
+use primitives::{
+    EpochId, StorageKey, StorageKeyWithSpace, StorageRoot, MERKLE_NULL_NODE,
+};
 use std::collections::HashMap;
 
 type StorageValue = Box<[u8]>;
@@ -106,6 +110,18 @@ impl StorageStateTrait for MockStorage {
         Err(ErrorKind::Msg("No state root".to_owned()).into())
     }
 
+    fn get_with_state_proof(
+        &self, access_key: StorageKeyWithSpace,
+    ) -> Result<(Option<Box<[u8]>>, StateProof)> {
+        unimplemented!()
+    }
+
+    fn get_storage_root(
+        &self, address: &AddressWithSpace,
+    ) -> Result<StorageRoot> {
+        unimplemented!()
+    }
+
     fn set(
         &mut self, access_key: StorageKeyWithSpace, value: Box<[u8]>,
     ) -> Result<()> {
@@ -137,6 +153,35 @@ impl StorageStateTrait for MockStorage {
 
         Ok(Some(kvs))
     }
+
+    fn read_range(
+        &self, access_key_prefix: StorageKeyWithSpace, lower_bound_incl: &[u8],
+        limit: usize,
+    ) -> Result<Vec<MptKeyValue>>
+    {
+        let prefix = access_key_prefix.to_key_bytes();
+
+        let mut keys: Vec<_> = self
+            .contents
+            .keys()
+            .filter(|k| {
+                k.starts_with(&prefix[..]) && k.as_slice() >= lower_bound_incl
+            })
+            .cloned()
+            .collect();
+        keys.sort();
+        keys.truncate(limit);
+
+        let mut kvs = vec![];
+
+        for k in keys {
+            *self.num_reads.lock() += 1;
+            let v = self.contents.get(&k).unwrap();
+            kvs.push((k, v.clone()));
+        }
+
+        Ok(kvs)
+    }
 }
 
 type StateDbTest = StateDbGeneric;
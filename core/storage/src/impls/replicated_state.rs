@@ -3,10 +3,11 @@ use crate::{
     StateProof, StorageStateTraitExt,
 };
 use cfx_internal_common::StateRootWithAuxInfo;
-use cfx_types::Space;
+use cfx_types::{AddressWithSpace, Space};
 use parking_lot::Mutex;
 use primitives::{
     EpochId, NodeMerkleTriplet, StaticBool, StorageKey, StorageKeyWithSpace,
+    StorageRoot,
 };
 use std::{
     sync::mpsc::{channel, Sender},
@@ -266,6 +267,18 @@ impl<Main: StateTrait> StateTrait for ReplicatedState<Main> {
         self.state.get(access_key)
     }
 
+    fn get_with_state_proof(
+        &self, access_key: StorageKeyWithSpace,
+    ) -> Result<(Option<Box<[u8]>>, StateProof)> {
+        self.state.get_with_state_proof(access_key)
+    }
+
+    fn get_storage_root(
+        &self, address: &AddressWithSpace,
+    ) -> Result<StorageRoot> {
+        self.state.get_storage_root(address)
+    }
+
     fn set(
         &mut self, access_key: StorageKeyWithSpace, value: Box<[u8]>,
     ) -> Result<()> {
@@ -304,6 +317,14 @@ impl<Main: StateTrait> StateTrait for ReplicatedState<Main> {
         self.state.read_all(access_key_prefix)
     }
 
+    fn read_range(
+        &self, access_key_prefix: StorageKeyWithSpace, lower_bound_incl: &[u8],
+        limit: usize,
+    ) -> Result<Vec<MptKeyValue>>
+    {
+        self.state.read_range(access_key_prefix, lower_bound_incl, limit)
+    }
+
     fn compute_state_root(&mut self) -> Result<StateRootWithAuxInfo> {
         self.replication_handler
             .send_op(StateOperation::ComputeStateRoot);
@@ -3,12 +3,13 @@ use crate::{
     state::StateTrait,
     utils::access_mode,
     CowNodeRef, DeltaMpt, MptKeyValue, NodeRefDeltaMpt, OwnedNodeSet,
-    SubTrieVisitor,
+    StateProof, SubTrieVisitor,
 };
 use cfx_internal_common::{StateRootAuxInfo, StateRootWithAuxInfo};
+use cfx_types::AddressWithSpace;
 use primitives::{
     EpochId, MerkleHash, MptValue, StateRoot, StorageKeyWithSpace,
-    MERKLE_NULL_NODE,
+    StorageRoot, MERKLE_NULL_NODE,
 };
 use std::{
     cell::UnsafeCell, collections::HashSet, hint::unreachable_unchecked,
@@ -337,6 +338,22 @@ impl StateTrait for SingleMptState {
         }
     }
 
+    fn get_with_state_proof(
+        &self, _access_key: StorageKeyWithSpace,
+    ) -> Result<(Option<Box<[u8]>>, StateProof)> {
+        Err(ErrorKind::Msg("Proof is not supported by single mpt".into())
+            .into())
+    }
+
+    fn get_storage_root(
+        &self, _address: &AddressWithSpace,
+    ) -> Result<StorageRoot> {
+        Err(ErrorKind::Msg(
+            "Storage root is not supported by single mpt".into(),
+        )
+        .into())
+    }
+
     fn set(
         &mut self, access_key: StorageKeyWithSpace, value: Box<[u8]>,
     ) -> Result<()> {
@@ -376,6 +393,38 @@ impl StateTrait for SingleMptState {
         self.delete_all_impl::<access_mode::Read>(access_key_prefix)
     }
 
+    fn read_range(
+        &self, access_key_prefix: StorageKeyWithSpace, lower_bound_incl: &[u8],
+        limit: usize,
+    ) -> Result<Vec<MptKeyValue>>
+    {
+        self.ensure_temp_slab_for_db_load();
+
+        // Reading won't create any new nodes so it's fine to pass an empty
+        // owned_node_set.
+        let mut empty_owned_node_set: Option<OwnedNodeSet> =
+            Some(Default::default());
+        let key_prefix = access_key_prefix.to_key_bytes();
+        let kvs = SubTrieVisitor::new(
+            &self.trie,
+            self.trie_root.clone(),
+            &mut empty_owned_node_set,
+        )?
+        .traversal(&key_prefix, &key_prefix)?;
+        let mut result = kvs
+            .into_iter()
+            .flatten()
+            .filter(|(_, v)| v.len() > 0)
+            .map(|(k, v)| {
+                (StorageKeyWithSpace::from_delta_mpt_key(&k).to_key_bytes(), v)
+            })
+            .filter(|(k, _)| k.as_slice() >= lower_bound_incl)
+            .collect::<Vec<_>>();
+        result.sort_by(|a, b| a.0.cmp(&b.0));
+        result.truncate(limit);
+        Ok(result)
+    }
+
     fn compute_state_root(&mut self) -> Result<StateRootWithAuxInfo> {
         self.ensure_temp_slab_for_db_load();
 
@@ -236,6 +236,18 @@ impl StateTrait for State {
             .map(|(value, _)| value)
     }
 
+    fn get_with_state_proof(
+        &self, access_key: StorageKeyWithSpace,
+    ) -> Result<(Option<Box<[u8]>>, StateProof)> {
+        StateTraitExt::get_with_proof(self, access_key)
+    }
+
+    fn get_storage_root(
+        &self, address: &AddressWithSpace,
+    ) -> Result<StorageRoot> {
+        self.get_original_storage_root(address)
+    }
+
     fn set(
         &mut self, access_key: StorageKeyWithSpace, value: Box<[u8]>,
     ) -> Result<()> {
@@ -305,6 +317,78 @@ impl StateTrait for State {
         self.delete_all_impl::<access_mode::Read>(access_key_prefix)
     }
 
+    /// The Delta Trie and the Intermediate Trie only hold the changes since
+    /// the snapshot, so they are read as a whole. The Snapshot DB is read from
+    /// `lower_bound_incl` until enough key/value pairs are found, taking into
+    /// account the pairs which the tries override.
+    fn read_range(
+        &self, access_key_prefix: StorageKeyWithSpace, lower_bound_incl: &[u8],
+        limit: usize,
+    ) -> Result<Vec<MptKeyValue>>
+    {
+        self.ensure_temp_slab_for_db_load();
+
+        let prefix = access_key_prefix.to_key_bytes();
+        let lower_bound_incl =
+            std::cmp::max(prefix.as_slice(), lower_bound_incl);
+        // The changes in the Delta Trie override the ones in the Intermediate
+        // Trie, so they are read last.
+        let mut changes = BTreeMap::new();
+        if let (Some(root_node), Some(intermediate_trie), Some(key_padding)) = (
+            &self.intermediate_trie_root,
+            self.maybe_intermediate_trie.as_ref(),
+            self.maybe_intermediate_trie_key_padding.as_ref(),
+        ) {
+            Self::read_delta_changes(
+                intermediate_trie,
+                root_node.clone(),
+                key_padding,
+                &access_key_prefix,
+                lower_bound_incl,
+                &mut changes,
+            )?;
+        }
+        if let Some(root_node) = &self.delta_trie_root {
+            Self::read_delta_changes(
+                &self.delta_trie,
+                root_node.clone(),
+                &self.delta_trie_key_padding,
+                &access_key_prefix,
+                lower_bound_incl,
+                &mut changes,
+            )?;
+        }
+
+        // Each change hides at most one key/value pair of the snapshot.
+        let snapshot_limit = limit.saturating_add(changes.len());
+        let upper_bound_excl = to_key_prefix_iter_upper_bound(&prefix);
+        let mut kv_iterator = self.snapshot_db.snapshot_kv_iterator()?.take();
+        let mut kvs = kv_iterator
+            .iter_range(
+                lower_bound_incl,
+                upper_bound_excl.as_ref().map(|v| &**v),
+            )?
+            .take();
+        let mut result = Vec::new();
+        let mut snapshot_read = 0;
+        while snapshot_read < snapshot_limit {
+            match kvs.next()? {
+                Some((k, v)) => {
+                    snapshot_read += 1;
+                    if !changes.contains_key(&k) {
+                        result.push((k, v));
+                    }
+                }
+                None => break,
+            }
+        }
+        // Tombstones are empty values.
+        result.extend(changes.into_iter().filter(|(_, v)| v.len() > 0));
+        result.sort_by(|a, b| a.0.cmp(&b.0));
+        result.truncate(limit);
+        Ok(result)
+    }
+
     fn compute_state_root(&mut self) -> Result<StateRootWithAuxInfo> {
         self.ensure_temp_slab_for_db_load();
 
@@ -812,6 +896,36 @@ impl State {
     /// necessary.
     ///
     /// When AM is Read, only calculate the key values to be deleted.
+    /// Insert the key/value pairs of `mpt` prefixed by `access_key_prefix`
+    /// and not before `lower_bound_incl` into `changes`, with their keys
+    /// converted to the snapshot keys. Deleted keys have empty values.
+    fn read_delta_changes(
+        mpt: &DeltaMpt, root_node: NodeRefDeltaMpt,
+        key_padding: &DeltaMptKeyPadding,
+        access_key_prefix: &StorageKeyWithSpace, lower_bound_incl: &[u8],
+        changes: &mut BTreeMap<Vec<u8>, Box<[u8]>>,
+    ) -> Result<()>
+    {
+        // Reading won't create any new nodes so it's fine to pass an empty
+        // owned_node_set.
+        let mut empty_owned_node_set: Option<OwnedNodeSet> =
+            Some(Default::default());
+        let key_prefix = access_key_prefix.to_delta_mpt_key_bytes(key_padding);
+        let kvs = SubTrieVisitor::new(
+            mpt,
+            root_node,
+            &mut empty_owned_node_set,
+        )?
+        .traversal(&key_prefix, &key_prefix)?;
+        for (k, v) in kvs.into_iter().flatten() {
+            let k = StorageKeyWithSpace::from_delta_mpt_key(&k).to_key_bytes();
+            if k.as_slice() >= lower_bound_incl {
+                changes.insert(k, v);
+            }
+        }
+        Ok(())
+    }
+
     fn delete_all_impl<AM: access_mode::AccessMode>(
         &mut self, access_key_prefix: StorageKeyWithSpace,
     ) -> Result<Option<Vec<MptKeyValue>>> {
@@ -22,6 +22,16 @@ pub trait StateTrait: Sync + Send {
     // Actions.
     fn get(&self, access_key: StorageKeyWithSpace)
         -> Result<Option<Box<[u8]>>>;
+    /// Same as `StateTraitExt::get_with_proof`, available to users of
+    /// `dyn StateTrait`. The proof is against the last computed state root.
+    fn get_with_state_proof(
+        &self, access_key: StorageKeyWithSpace,
+    ) -> Result<(Option<Box<[u8]>>, StateProof)>;
+    /// Same as `StateDbGetOriginalMethods::get_original_storage_root`,
+    /// available to users of `dyn StateTrait`.
+    fn get_storage_root(
+        &self, address: &AddressWithSpace,
+    ) -> Result<StorageRoot>;
     fn set(
         &mut self, access_key: StorageKeyWithSpace, value: Box<[u8]>,
     ) -> Result<()>;
@@ -38,6 +48,13 @@ pub trait StateTrait: Sync + Send {
     fn read_all(
         &mut self, access_key_prefix: StorageKeyWithSpace,
     ) -> Result<Option<Vec<MptKeyValue>>>;
+    /// Read at most `limit` key/value pairs prefixed by access_key_prefix,
+    /// ordered by key and starting from `lower_bound_incl`. Unlike
+    /// `read_all`, the snapshot is only read as far as needed.
+    fn read_range(
+        &self, access_key_prefix: StorageKeyWithSpace, lower_bound_incl: &[u8],
+        limit: usize,
+    ) -> Result<Vec<MptKeyValue>>;
 
     // Finalize
     /// It's costly to compute state root however it's only necessary to compute
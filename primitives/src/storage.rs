@@ -146,7 +146,7 @@ impl Decodable for MptValue<H256> {
     }
 }
 
-#[derive(Clone, Debug, RlpEncodable, RlpDecodable, Serialize)]
+#[derive(Clone, Debug, PartialEq, RlpEncodable, RlpDecodable, Serialize)]
 pub struct NodeMerkleTriplet {
     pub delta: MptValue<H256>,
     pub intermediate: MptValue<H256>,
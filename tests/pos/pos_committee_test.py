@@ -0,0 +1,46 @@
+#!/usr/bin/env python3
+
+# allow imports from parent directory
+# source: https://stackoverflow.com/a/11158224
+import os, sys
+sys.path.insert(1, os.path.join(sys.path[0], '..'))
+
+from conflux.rpc import RpcClient
+from conflux.utils import int_to_hex
+from test_framework.test_framework import DefaultConfluxTestFramework
+from test_framework.util import *
+
+
+class PosCommitteeTest(DefaultConfluxTestFramework):
+    def set_test_params(self):
+        self.num_nodes = 4
+        self.conf_parameters["vrf_proposal_threshold"] = '"{}"'.format(int_to_hex(int(2 ** 256 - 1)))
+        self.conf_parameters["pos_pivot_decision_defer_epoch_count"] = '120'
+        self.conf_parameters["pos_round_per_term"] = '10'
+
+    def run_test(self):
+        client = RpcClient(self.nodes[0])
+        wait_until(lambda: int(client.pos_status()["latestCommitted"], 0) >= 1)
+
+        # The genesis committee is formed by all the initial nodes.
+        genesis_committee = self.nodes[0].pos_getCommittee(int_to_hex(1))
+        assert_equal(genesis_committee["currentTerm"], "0x0")
+        assert_equal(genesis_committee["currentCommittee"]["epochNumber"], "0x1")
+        assert_equal(len(genesis_committee["currentCommittee"]["nodes"]), self.num_nodes)
+        assert_equal(len(genesis_committee["elections"]), 2)
+
+        # Wait for a term change.
+        wait_until(lambda: int(client.pos_status()["epoch"], 0) >= 2)
+        committee = self.nodes[0].pos_getCommittee(int_to_hex(10))
+        assert_equal(committee["currentTerm"], "0x1")
+        assert_equal(committee["currentCommittee"]["epochNumber"], "0x2")
+        total_voting_power = sum(int(node["votingPower"], 0) for node in committee["currentCommittee"]["nodes"])
+        assert_equal(int(committee["currentCommittee"]["totalVotingPower"], 0), total_voting_power)
+
+        # A view that is not executed yet should be rejected.
+        latest_committed = int(client.pos_status()["latestCommitted"], 0)
+        assert_raises_rpc_error(None, None, self.nodes[0].pos_getCommittee, int_to_hex(latest_committed + 1000))
+
+
+if __name__ == '__main__':
+    PosCommitteeTest().main()
@@ -0,0 +1,39 @@
+#!/usr/bin/env python3
+
+# allow imports from parent directory
+# source: https://stackoverflow.com/a/11158224
+import os, sys
+sys.path.insert(1, os.path.join(sys.path[0], '..'))
+
+from conflux.config import DEFAULT_PY_TEST_CHAIN_ID
+from conflux.rpc import RpcClient
+from conflux.utils import int_to_hex
+from test_framework.test_framework import DefaultConfluxTestFramework
+from test_framework.util import *
+
+
+class PosStatusTest(DefaultConfluxTestFramework):
+    def set_test_params(self):
+        self.num_nodes = 4
+        self.conf_parameters["vrf_proposal_threshold"] = '"{}"'.format(int_to_hex(int(2 ** 256 - 1)))
+        self.conf_parameters["pos_pivot_decision_defer_epoch_count"] = '120'
+        self.conf_parameters["pos_round_per_term"] = '10'
+
+    def run_test(self):
+        client = RpcClient(self.nodes[0])
+        wait_until(lambda: client.pos_status()["latestVoted"] is not None)
+        wait_until(lambda: int(client.pos_status()["latestCommitted"], 0) >= 2)
+
+        status = client.pos_status()
+        # The PoS genesis of the test framework is generated with the test
+        # chain id, and the chain id is reported as a plain number.
+        assert_equal(status["chainId"], DEFAULT_PY_TEST_CHAIN_ID)
+
+        committed_block = self.nodes[0].pos_getBlockByHash(status["latestCommittedBlockHash"])
+        assert_equal(committed_block["height"], status["latestCommitted"])
+        assert_equal(committed_block["lastTxNumber"], status["latestTxNumber"])
+        assert status["latestVotedRound"] is not None
+
+
+if __name__ == '__main__':
+    PosStatusTest().main()
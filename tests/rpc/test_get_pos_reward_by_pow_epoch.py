@@ -40,4 +40,7 @@ class TestGetPosRewardByPowEpoch(RpcClient):
         for key in pow_reward_map:
             assert_equal(pow_reward_map[key], pos_reward_map[key])
 
+        total_reward = sum(int(x["reward"], 0) for x in reward_by_pos_epoch["accountRewards"])
+        assert_equal(int(reward_by_pos_epoch["totalReward"], 0), total_reward)
+
         return True
\ No newline at end of file